@@ -66,6 +66,8 @@ import("./pkg")
           BigInt(mouseX - initialMouseX),
           BigInt(mouseY - initialMouseY)
         );
+        // The whole drag becomes a single step of the undo history.
+        wasm.finish_move();
         wasm.nodes(ctx_nodes, nodes_width, nodes_height);
       }
 
@@ -1,6 +1,7 @@
-use anyhow::Result;
 use rusvid_core::prelude::{Pixel, Plane};
 
+use crate::error::Result;
+
 // Source: https://lpc.opengameart.org/content/8x8-ascii-bitmap-font-with-c-source
 // TODO replace 'font' with another license, maybe cc
 /*
@@ -1,6 +1,9 @@
 use std::fmt::Display;
+use std::ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign};
 
-#[derive(Debug)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct Coordinate {
     x: f64,
     y: f64,
@@ -40,4 +43,291 @@ impl Coordinate {
     pub fn z(&self) -> f64 {
         self.z
     }
+
+    /// Dot product of both coordinates as vectors.
+    pub fn dot(&self, other: &Coordinate) -> f64 {
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    /// Euclidean length of the coordinate as a vector.
+    pub fn length(&self) -> f64 {
+        self.dot(self).sqrt()
+    }
+}
+
+impl Add for Coordinate {
+    type Output = Coordinate;
+
+    fn add(self, rhs: Coordinate) -> Coordinate {
+        Coordinate::new(self.x + rhs.x, self.y + rhs.y, self.z + rhs.z)
+    }
+}
+
+impl AddAssign for Coordinate {
+    fn add_assign(&mut self, rhs: Coordinate) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub for Coordinate {
+    type Output = Coordinate;
+
+    fn sub(self, rhs: Coordinate) -> Coordinate {
+        Coordinate::new(self.x - rhs.x, self.y - rhs.y, self.z - rhs.z)
+    }
+}
+
+impl SubAssign for Coordinate {
+    fn sub_assign(&mut self, rhs: Coordinate) {
+        *self = *self - rhs;
+    }
+}
+
+impl Neg for Coordinate {
+    type Output = Coordinate;
+
+    fn neg(self) -> Coordinate {
+        Coordinate::new(-self.x, -self.y, -self.z)
+    }
+}
+
+impl Mul<f64> for Coordinate {
+    type Output = Coordinate;
+
+    fn mul(self, rhs: f64) -> Coordinate {
+        Coordinate::new(self.x * rhs, self.y * rhs, self.z * rhs)
+    }
+}
+
+/// Multiplies the coordinates component by component, e.g. to scale every axis separately.
+impl Mul for Coordinate {
+    type Output = Coordinate;
+
+    fn mul(self, rhs: Coordinate) -> Coordinate {
+        Coordinate::new(self.x * rhs.x, self.y * rhs.y, self.z * rhs.z)
+    }
+}
+
+impl Div<f64> for Coordinate {
+    type Output = Coordinate;
+
+    fn div(self, rhs: f64) -> Coordinate {
+        Coordinate::new(self.x / rhs, self.y / rhs, self.z / rhs)
+    }
+}
+
+/// An affine transformation of coordinates: a linear part (rotation, scale, shear) followed by a translation.
+///
+/// `a * b` applies `b` first and then `a`, like the product of the matrices.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Matrix {
+    /// The rows of the linear part with the translation in the last column.
+    rows: [[f64; 4]; 3],
+}
+
+impl Matrix {
+    pub const IDENTITY: Matrix = Matrix {
+        rows: [
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+        ],
+    };
+
+    pub fn new(rows: [[f64; 4]; 3]) -> Self {
+        Matrix { rows }
+    }
+
+    pub fn rows(&self) -> [[f64; 4]; 3] {
+        self.rows
+    }
+
+    pub fn translation(offset: Coordinate) -> Self {
+        Matrix::new([
+            [1.0, 0.0, 0.0, offset.x],
+            [0.0, 1.0, 0.0, offset.y],
+            [0.0, 0.0, 1.0, offset.z],
+        ])
+    }
+
+    pub fn scaling(scale: Coordinate) -> Self {
+        Matrix::new([
+            [scale.x, 0.0, 0.0, 0.0],
+            [0.0, scale.y, 0.0, 0.0],
+            [0.0, 0.0, scale.z, 0.0],
+        ])
+    }
+
+    /// Rotates around the z axis by `angle` radians, i.e. in the plane of the image.
+    pub fn rotation_z(angle: f64) -> Self {
+        let (sin, cos) = angle.sin_cos();
+
+        Matrix::new([
+            [cos, -sin, 0.0, 0.0],
+            [sin, cos, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+        ])
+    }
+
+    /// Shears in the plane of the image, `x` moves by `x_by_y` times `y` and `y` by `y_by_x` times `x`.
+    pub fn shear(x_by_y: f64, y_by_x: f64) -> Self {
+        Matrix::new([
+            [1.0, x_by_y, 0.0, 0.0],
+            [y_by_x, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+        ])
+    }
+
+    /// Applies the transformation with `pivot` as the origin, e.g. to rotate around the pivot.
+    pub fn around(self, pivot: Coordinate) -> Self {
+        Matrix::translation(pivot) * self * Matrix::translation(-pivot)
+    }
+
+    pub fn transform(&self, coordinate: &Coordinate) -> Coordinate {
+        let [x, y, z] = self.rows.map(|row| {
+            row[0] * coordinate.x + row[1] * coordinate.y + row[2] * coordinate.z + row[3]
+        });
+
+        Coordinate::new(x, y, z)
+    }
+
+    /// Determinant of the linear part, zero if the transformation collapses an axis.
+    pub fn determinant(&self) -> f64 {
+        let [a, b, c] = self.rows;
+
+        a[0] * (b[1] * c[2] - b[2] * c[1]) - a[1] * (b[0] * c[2] - b[2] * c[0])
+            + a[2] * (b[0] * c[1] - b[1] * c[0])
+    }
+
+    /// The transformation that undoes this one, `None` if it collapses an axis.
+    pub fn inverse(&self) -> Option<Matrix> {
+        let determinant = self.determinant();
+        if determinant.abs() < f64::EPSILON || !determinant.is_finite() {
+            return None;
+        }
+
+        let m = |row: usize, column: usize| self.rows[row][column];
+        // The inverse of the linear part is its adjugate divided by the determinant.
+        let cofactor = |row: usize, column: usize| {
+            let (r1, r2) = ((row + 1) % 3, (row + 2) % 3);
+            let (c1, c2) = ((column + 1) % 3, (column + 2) % 3);
+
+            m(r1, c1) * m(r2, c2) - m(r1, c2) * m(r2, c1)
+        };
+        let mut rows = [[0.0; 4]; 3];
+        for (row, values) in rows.iter_mut().enumerate() {
+            for (column, value) in values.iter_mut().take(3).enumerate() {
+                *value = cofactor(column, row) / determinant;
+            }
+        }
+        // The translation is undone after the linear part.
+        let translation = Coordinate::new(m(0, 3), m(1, 3), m(2, 3));
+        let inverse = Matrix::new(rows);
+        let offset = -inverse.transform(&translation);
+        for (row, value) in rows.iter_mut().zip([offset.x, offset.y, offset.z]) {
+            row[3] = value;
+        }
+
+        Some(Matrix::new(rows))
+    }
+}
+
+impl Default for Matrix {
+    fn default() -> Self {
+        Matrix::IDENTITY
+    }
+}
+
+impl Mul for Matrix {
+    type Output = Matrix;
+
+    fn mul(self, rhs: Matrix) -> Matrix {
+        let mut rows = [[0.0; 4]; 3];
+        for (row, values) in rows.iter_mut().enumerate() {
+            for (column, value) in values.iter_mut().take(3).enumerate() {
+                *value = (0..3)
+                    .map(|index| self.rows[row][index] * rhs.rows[index][column])
+                    .sum();
+            }
+        }
+        // The translation of `rhs` gets transformed by `self` like any other coordinate.
+        let translation = self.transform(&Coordinate::new(
+            rhs.rows[0][3],
+            rhs.rows[1][3],
+            rhs.rows[2][3],
+        ));
+        for (row, value) in rows
+            .iter_mut()
+            .zip([translation.x, translation.y, translation.z])
+        {
+            row[3] = value;
+        }
+
+        Matrix::new(rows)
+    }
+}
+
+impl Mul<Coordinate> for Matrix {
+    type Output = Coordinate;
+
+    fn mul(self, rhs: Coordinate) -> Coordinate {
+        self.transform(&rhs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::FRAC_PI_2;
+
+    use super::{Coordinate, Matrix};
+
+    fn assert_close(a: Coordinate, b: Coordinate) {
+        assert!((a - b).length() < 1e-9, "{a} != {b}");
+    }
+
+    #[test]
+    fn coordinates_support_vector_arithmetic() {
+        let a = Coordinate::new(1.0, 2.0, 3.0);
+        let b = Coordinate::new(4.0, -1.0, 0.5);
+
+        assert_eq!(a + b, Coordinate::new(5.0, 1.0, 3.5));
+        assert_eq!(a - b, Coordinate::new(-3.0, 3.0, 2.5));
+        assert_eq!(-a, Coordinate::new(-1.0, -2.0, -3.0));
+        assert_eq!(a * 2.0, Coordinate::new(2.0, 4.0, 6.0));
+        assert_eq!(a * b, Coordinate::new(4.0, -2.0, 1.5));
+        assert_eq!(a / 2.0, Coordinate::new(0.5, 1.0, 1.5));
+        assert_eq!(a.dot(&b), 3.5);
+        assert_eq!(Coordinate::new(3.0, 4.0, 0.0).length(), 5.0);
+    }
+
+    #[test]
+    fn matrices_compose_and_invert() {
+        let pivot = Coordinate::new_xy(2.0, 2.0);
+        let rotation = Matrix::rotation_z(FRAC_PI_2).around(pivot);
+        assert_close(
+            rotation * Coordinate::new_xy(3.0, 2.0),
+            Coordinate::new_xy(2.0, 3.0),
+        );
+
+        // The right matrix is applied first.
+        let matrix = Matrix::translation(Coordinate::new(1.0, 0.0, -1.0))
+            * Matrix::scaling(Coordinate::new(2.0, 3.0, 4.0))
+            * Matrix::shear(0.5, 0.0)
+            * rotation;
+        let point = Coordinate::new(0.3, -1.2, 2.0);
+        let expected = Coordinate::new(1.0, 0.0, -1.0)
+            + Coordinate::new(2.0, 3.0, 4.0) * (Matrix::shear(0.5, 0.0) * (rotation * point));
+        assert_close(matrix * point, expected);
+        assert_close(matrix.inverse().unwrap() * (matrix * point), point);
+        assert_close(
+            (matrix.inverse().unwrap() * matrix) * point,
+            Matrix::IDENTITY * point,
+        );
+
+        assert_eq!(
+            Matrix::scaling(Coordinate::new(1.0, 0.0, 1.0)).inverse(),
+            None
+        );
+        assert_eq!(Matrix::shear(1.0, 1.0).inverse(), None);
+    }
 }
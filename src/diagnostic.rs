@@ -0,0 +1,110 @@
+use std::fmt::Display;
+
+use itertools::Itertools;
+use petgraph::stable_graph::NodeIndex;
+
+use crate::input_output_value::InputOutputKind;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// The graph can be rendered, but probably not as intended.
+    Warning,
+    /// The graph can't be rendered.
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiagnosticKind {
+    /// The node is part of a cycle.
+    Cycle,
+    /// A required input of the node isn't connected.
+    MissingInput,
+    /// An edge ends in an input that the node doesn't have or starts at a node without outputs.
+    DanglingEdge,
+    /// The connected node produces values that the input can't handle.
+    TypeMismatch {
+        expected: Vec<InputOutputKind>,
+        found: Vec<InputOutputKind>,
+    },
+    /// The node doesn't contribute to any output.
+    UnreachableNode,
+    /// Another output node uses the same channel name.
+    DuplicateChannel,
+    /// A parameter of the node holds a value that can't be used, see [`Node::diagnostics`](crate::node::Node::diagnostics).
+    InvalidParameter { parameter: String, reason: String },
+    /// The group contains an instance of itself, directly or inside of other groups.
+    RecursiveGroup,
+    /// Another group input of the graph uses the same name.
+    DuplicateGroupInput,
+}
+
+/// A problem in the graph of a [`Generator`](crate::generator::Generator) found by
+/// [`Generator::validate`](crate::generator::Generator::validate).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub kind: DiagnosticKind,
+    pub node: NodeIndex,
+    /// The [`SpaceInfo::name`](crate::node::SpaceInfo::name) of the node.
+    pub node_name: String,
+    pub port: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn new<S: Into<String>>(kind: DiagnosticKind, node: NodeIndex, node_name: S) -> Self {
+        Diagnostic {
+            kind,
+            node,
+            node_name: node_name.into(),
+            port: None,
+        }
+    }
+
+    pub fn with_port<S: Into<String>>(mut self, port: S) -> Self {
+        self.port = Some(port.into());
+
+        self
+    }
+
+    pub fn severity(&self) -> Severity {
+        match self.kind {
+            DiagnosticKind::UnreachableNode => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity() == Severity::Error
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.port {
+            Some(port) => write!(f, "{}.{}: ", self.node_name, port)?,
+            None => write!(f, "{}: ", self.node_name)?,
+        }
+
+        match &self.kind {
+            DiagnosticKind::Cycle => write!(f, "node is part of a cycle"),
+            DiagnosticKind::MissingInput => write!(f, "required input is not connected"),
+            DiagnosticKind::DanglingEdge => write!(f, "edge is not connected to a valid port"),
+            DiagnosticKind::TypeMismatch { expected, found } => write!(
+                f,
+                "expected one of [{}] but found [{}]",
+                expected.iter().join(", "),
+                found.iter().join(", ")
+            ),
+            DiagnosticKind::UnreachableNode => write!(f, "node is not connected to an output"),
+            DiagnosticKind::DuplicateChannel => {
+                write!(f, "another output uses the same channel name")
+            }
+            DiagnosticKind::InvalidParameter { parameter, reason } => {
+                write!(f, "invalid value for the parameter {parameter:?}: {reason}")
+            }
+            DiagnosticKind::RecursiveGroup => write!(f, "group contains an instance of itself"),
+            DiagnosticKind::DuplicateGroupInput => {
+                write!(f, "another group input uses the same name")
+            }
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use itertools::Itertools;
+use petgraph::stable_graph::{EdgeIndex, NodeIndex};
+use rusvid_core::plane::PlaneError;
+use thiserror::Error;
+
+use crate::diagnostic::Diagnostic;
+use crate::input_output_value::InputOutputKind;
+
+#[derive(Debug, Error)]
+pub enum GeneratorError {
+    #[error("the node {node:?} is missing the input {port:?}")]
+    MissingInput { node: String, port: String },
+
+    #[error("the input {port:?} of the node {node:?} can't handle a value of kind {found}")]
+    TypeMismatch {
+        node: String,
+        port: String,
+        expected: Vec<InputOutputKind>,
+        found: InputOutputKind,
+    },
+
+    #[error("the graph contains a cycle at the node {node:?}")]
+    CycleDetected { node: String },
+
+    #[error("invalid value for the parameter {parameter:?}: {reason}")]
+    InvalidParameter { parameter: String, reason: String },
+
+    #[error("the node {node:?} has no parameter named {parameter:?}")]
+    UnknownParameter { node: String, parameter: String },
+
+    #[error("another output already uses the channel {0:?}")]
+    DuplicateChannel(String),
+
+    #[error("unknown group {0:?}")]
+    UnknownGroup(String),
+
+    #[error("two different groups are named {0:?}")]
+    DuplicateGroup(String),
+
+    #[error("the group {0:?} contains an instance of itself")]
+    RecursiveGroup(String),
+
+    #[error("the node {node:?} has no port named {port:?}")]
+    UnknownPort { node: String, port: String },
+
+    #[error("the node {0:?} doesn't exist")]
+    UnknownNode(NodeIndex),
+
+    #[error("the edge {0:?} doesn't exist")]
+    UnknownEdge(EdgeIndex),
+
+    #[error("the output node can't be removed or replaced by another kind of node")]
+    OutputNodeRequired,
+
+    #[error("a link can't connect the node {0:?} with itself")]
+    SelfLink(NodeIndex),
+
+    #[error("the graph is invalid: {}", .0.iter().join("; "))]
+    InvalidGraph(Vec<Diagnostic>),
+
+    #[error("the graph was changed outside of the history")]
+    HistoryOutOfSync,
+
+    #[error("unknown node type {0:?}")]
+    UnknownNodeType(String),
+
+    #[error(
+        "the saved graph has the version {found} but only versions up to {supported} are supported"
+    )]
+    UnsupportedVersion { found: u32, supported: u32 },
+
+    #[error(transparent)]
+    PlaneError(#[from] PlaneError),
+
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+
+    #[error(transparent)]
+    Ron(#[from] ron::Error),
+
+    #[error(transparent)]
+    RonParse(#[from] ron::error::SpannedError),
+}
+
+pub type Result<T> = std::result::Result<T, GeneratorError>;
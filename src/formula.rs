@@ -0,0 +1,524 @@
+use std::fmt::Display;
+
+use thiserror::Error;
+
+use crate::library::math::MathOperation;
+
+/// Why a formula couldn't be parsed, `position` is the byte offset in the source where the problem starts.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("{message} at position {position}")]
+pub struct ParseError {
+    pub message: String,
+    pub position: usize,
+}
+
+impl ParseError {
+    fn new<S: Into<String>>(message: S, position: usize) -> Self {
+        ParseError {
+            message: message.into(),
+            position,
+        }
+    }
+}
+
+/// Functions that can be called in a formula besides the [`MathOperation`]s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Function {
+    Math(MathOperation),
+    Tan,
+    Sqrt,
+    Exp,
+    Ln,
+    Ceil,
+}
+
+impl Function {
+    fn from_name(name: &str) -> Option<Function> {
+        let function = match name {
+            "tan" => Function::Tan,
+            "sqrt" => Function::Sqrt,
+            "exp" => Function::Exp,
+            "ln" => Function::Ln,
+            "ceil" => Function::Ceil,
+            "pow" => Function::Math(MathOperation::Power),
+            "mod" => Function::Math(MathOperation::Modulo),
+            name => Function::Math(MathOperation::from_name(name)?),
+        };
+
+        Some(function)
+    }
+
+    fn arity(&self) -> usize {
+        match self {
+            Function::Math(operation) => operation.inputs().len(),
+            Function::Tan | Function::Sqrt | Function::Exp | Function::Ln | Function::Ceil => 1,
+        }
+    }
+}
+
+/// The largest number of arguments a [`Function`] takes.
+const MAX_ARGUMENTS: usize = 5;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Number(f64),
+    /// Index into [`Formula::variables`].
+    Variable(usize),
+    Negate(Box<Expr>),
+    Call(Function, Vec<Expr>),
+    /// Operators of the same precedence applied from left to right.
+    Chain(Box<Expr>, Vec<(MathOperation, Expr)>),
+}
+
+impl Expr {
+    fn evaluate(&self, variables: &[f64]) -> f64 {
+        match self {
+            Expr::Number(value) => *value,
+            Expr::Variable(index) => variables[*index],
+            Expr::Negate(expr) => -expr.evaluate(variables),
+            Expr::Call(function, arguments) => {
+                let mut values = [0.0; MAX_ARGUMENTS];
+                for (value, argument) in values.iter_mut().zip(arguments) {
+                    *value = argument.evaluate(variables);
+                }
+
+                match function {
+                    Function::Math(operation) => operation.apply(&values[..arguments.len()]),
+                    Function::Tan => values[0].tan(),
+                    Function::Sqrt => values[0].sqrt(),
+                    Function::Exp => values[0].exp(),
+                    Function::Ln => values[0].ln(),
+                    Function::Ceil => values[0].ceil(),
+                }
+            }
+            Expr::Chain(first, rest) => {
+                rest.iter()
+                    .fold(first.evaluate(variables), |value, (operation, operand)| {
+                        operation.apply(&[value, operand.evaluate(variables)])
+                    })
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Token<'a> {
+    Number(f64),
+    Identifier(&'a str),
+    Operator(char),
+    End,
+}
+
+impl Display for Token<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Number(value) => write!(f, "number {value}"),
+            Token::Identifier(name) => write!(f, "name {name:?}"),
+            Token::Operator(operator) => write!(f, "{operator:?}"),
+            Token::End => write!(f, "end of the formula"),
+        }
+    }
+}
+
+/// Splits the source into tokens together with their byte offset, the last token is always [`Token::End`].
+fn tokenize(source: &str) -> Result<Vec<(Token<'_>, usize)>, ParseError> {
+    let bytes = source.as_bytes();
+    let mut tokens = Vec::new();
+    let mut position = 0;
+
+    while position < bytes.len() {
+        let start = position;
+        let byte = bytes[position];
+
+        if byte.is_ascii_whitespace() {
+            position += 1;
+            continue;
+        }
+
+        if byte.is_ascii_digit() || byte == b'.' {
+            while position < bytes.len()
+                && (bytes[position].is_ascii_digit() || bytes[position] == b'.')
+            {
+                position += 1;
+            }
+            // An exponent like in `1.5e-3`.
+            if position < bytes.len() && matches!(bytes[position], b'e' | b'E') {
+                let mut end = position + 1;
+                if end < bytes.len() && matches!(bytes[end], b'+' | b'-') {
+                    end += 1;
+                }
+                if end < bytes.len() && bytes[end].is_ascii_digit() {
+                    position = end;
+                    while position < bytes.len() && bytes[position].is_ascii_digit() {
+                        position += 1;
+                    }
+                }
+            }
+
+            let text = &source[start..position];
+            let value = text
+                .parse()
+                .map_err(|_| ParseError::new(format!("invalid number {text:?}"), start))?;
+            tokens.push((Token::Number(value), start));
+        } else if byte.is_ascii_alphabetic() || byte == b'_' {
+            while position < bytes.len()
+                && (bytes[position].is_ascii_alphanumeric() || bytes[position] == b'_')
+            {
+                position += 1;
+            }
+            tokens.push((Token::Identifier(&source[start..position]), start));
+        } else if b"+-*/%^(),".contains(&byte) {
+            position += 1;
+            tokens.push((Token::Operator(byte as char), start));
+        } else {
+            let character = source[start..].chars().next().unwrap_or_default();
+            return Err(ParseError::new(
+                format!("unexpected character {character:?}"),
+                start,
+            ));
+        }
+    }
+    tokens.push((Token::End, source.len()));
+
+    Ok(tokens)
+}
+
+/// Recursive descent parser, from the lowest to the highest precedence:
+///
+/// ```text
+/// sum     = product (("+" | "-") product)*
+/// product = unary (("*" | "/" | "%") unary)*
+/// unary   = ("-" | "+") unary | power
+/// power   = primary ("^" unary)?
+/// primary = number | name | name "(" sum ("," sum)* ")" | "(" sum ")"
+/// ```
+///
+/// Parentheses, signs and calls nest the parsed expression deeper, at most [`MAX_DEPTH`] levels.
+struct Parser<'a> {
+    tokens: Vec<(Token<'a>, usize)>,
+    next: usize,
+    variables: Vec<String>,
+    depth: usize,
+}
+
+/// How deep a formula can nest, so that neither parsing nor evaluating it overflows the stack.
+const MAX_DEPTH: usize = 128;
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> (Token<'a>, usize) {
+        self.tokens[self.next]
+    }
+
+    fn advance(&mut self) -> (Token<'a>, usize) {
+        let token = self.peek();
+        if token.0 != Token::End {
+            self.next += 1;
+        }
+
+        token
+    }
+
+    fn expect(&mut self, operator: char) -> Result<(), ParseError> {
+        match self.advance() {
+            (Token::Operator(found), _) if found == operator => Ok(()),
+            (token, position) => Err(ParseError::new(
+                format!("expected {operator:?} but found {token}"),
+                position,
+            )),
+        }
+    }
+
+    /// Goes one level deeper, the caller restores [`Parser::depth`] when it's done.
+    fn nest(&mut self) -> Result<(), ParseError> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(ParseError::new(
+                "the formula is nested too deeply",
+                self.peek().1,
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn binary(operation: MathOperation, left: Expr, right: Expr) -> Expr {
+        Expr::Call(Function::Math(operation), vec![left, right])
+    }
+
+    /// Keeps a chain of operators flat, so long sums don't count as nesting.
+    fn chain(first: Expr, rest: Vec<(MathOperation, Expr)>) -> Expr {
+        if rest.is_empty() {
+            first
+        } else {
+            Expr::Chain(Box::new(first), rest)
+        }
+    }
+
+    fn sum(&mut self) -> Result<Expr, ParseError> {
+        let first = self.product()?;
+        let mut rest = Vec::new();
+        loop {
+            let operation = match self.peek().0 {
+                Token::Operator('+') => MathOperation::Add,
+                Token::Operator('-') => MathOperation::Subtract,
+                _ => return Ok(Parser::chain(first, rest)),
+            };
+            self.advance();
+            rest.push((operation, self.product()?));
+        }
+    }
+
+    fn product(&mut self) -> Result<Expr, ParseError> {
+        let first = self.unary()?;
+        let mut rest = Vec::new();
+        loop {
+            let operation = match self.peek().0 {
+                Token::Operator('*') => MathOperation::Multiply,
+                Token::Operator('/') => MathOperation::Divide,
+                Token::Operator('%') => MathOperation::Modulo,
+                _ => return Ok(Parser::chain(first, rest)),
+            };
+            self.advance();
+            rest.push((operation, self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, ParseError> {
+        self.nest()?;
+        let expr = match self.peek().0 {
+            Token::Operator('-') => {
+                self.advance();
+                Expr::Negate(Box::new(self.unary()?))
+            }
+            Token::Operator('+') => {
+                self.advance();
+                self.unary()?
+            }
+            _ => self.power()?,
+        };
+        self.depth -= 1;
+
+        Ok(expr)
+    }
+
+    fn power(&mut self) -> Result<Expr, ParseError> {
+        let base = self.primary()?;
+        if self.peek().0 != Token::Operator('^') {
+            return Ok(base);
+        }
+        self.advance();
+
+        Ok(Parser::binary(MathOperation::Power, base, self.unary()?))
+    }
+
+    fn primary(&mut self) -> Result<Expr, ParseError> {
+        match self.advance() {
+            (Token::Number(value), _) => Ok(Expr::Number(value)),
+            (Token::Identifier(name), position) => {
+                if self.peek().0 == Token::Operator('(') {
+                    self.call(name, position)
+                } else {
+                    Ok(Expr::Variable(self.variable(name)))
+                }
+            }
+            (Token::Operator('('), _) => {
+                let expr = self.sum()?;
+                self.expect(')')?;
+
+                Ok(expr)
+            }
+            (token, position) => Err(ParseError::new(
+                format!("expected a value but found {token}"),
+                position,
+            )),
+        }
+    }
+
+    fn call(&mut self, name: &str, position: usize) -> Result<Expr, ParseError> {
+        let function = Function::from_name(name)
+            .ok_or_else(|| ParseError::new(format!("unknown function {name:?}"), position))?;
+
+        self.expect('(')?;
+        let mut arguments = vec![self.sum()?];
+        while self.peek().0 == Token::Operator(',') {
+            self.advance();
+            arguments.push(self.sum()?);
+        }
+        self.expect(')')?;
+
+        if arguments.len() != function.arity() {
+            return Err(ParseError::new(
+                format!(
+                    "{name} expects {} arguments but got {}",
+                    function.arity(),
+                    arguments.len()
+                ),
+                position,
+            ));
+        }
+
+        Ok(Expr::Call(function, arguments))
+    }
+
+    fn variable(&mut self, name: &str) -> usize {
+        match self.variables.iter().position(|variable| variable == name) {
+            Some(index) => index,
+            None => {
+                self.variables.push(name.to_string());
+                self.variables.len() - 1
+            }
+        }
+    }
+}
+
+/// A parsed arithmetic formula like `sin(x * 10 + a) * 0.5 + 0.5`, evaluated with the values of its variables.
+///
+/// Formulas know the operators `+`, `-`, `*`, `/`, `%` (modulo) and `^` (power) with the usual precedence,
+/// parentheses and the functions `tan`, `sqrt`, `exp`, `ln`, `ceil`, `pow`, `mod` and those named like a
+/// [`MathOperation`], e.g. `sin`, `clamp` or `smoothstep`. Math operations take their arguments in the order of
+/// [`MathOperation::inputs`], e.g. `smoothstep(value, edge0, edge1)`. Like the math nodes, results that aren't finite
+/// are `0.0`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Formula {
+    root: Expr,
+    variables: Vec<String>,
+}
+
+impl Formula {
+    pub fn parse(source: &str) -> Result<Formula, ParseError> {
+        let mut parser = Parser {
+            tokens: tokenize(source)?,
+            next: 0,
+            variables: Vec::new(),
+            depth: 0,
+        };
+
+        let root = parser.sum()?;
+        match parser.peek() {
+            (Token::End, _) => Ok(Formula {
+                root,
+                variables: parser.variables,
+            }),
+            (token, position) => Err(ParseError::new(
+                format!("expected an operator but found {token}"),
+                position,
+            )),
+        }
+    }
+
+    /// The names of the variables in the order of their first appearance.
+    pub fn variables(&self) -> &[String] {
+        &self.variables
+    }
+
+    /// Calculates the formula, `variables` must hold a value for every one of [`Formula::variables`] in the same order.
+    pub fn evaluate(&self, variables: &[f64]) -> f64 {
+        let value = self.root.evaluate(variables);
+
+        if value.is_finite() {
+            value
+        } else {
+            0.0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Formula, ParseError};
+
+    fn evaluate(source: &str, variables: &[f64]) -> f64 {
+        Formula::parse(source).unwrap().evaluate(variables)
+    }
+
+    #[test]
+    fn formulas_follow_the_usual_precedence() {
+        assert_eq!(evaluate("1 + 2 * 3", &[]), 7.0);
+        assert_eq!(evaluate("(1 + 2) * 3", &[]), 9.0);
+        assert_eq!(evaluate("-2 ^ 2", &[]), -4.0);
+        assert_eq!(evaluate("2 ^ 3 ^ 2", &[]), 512.0);
+        assert_eq!(evaluate("2 ^ -1", &[]), 0.5);
+        assert_eq!(evaluate("7 % 4 - 1 / 4", &[]), 2.75);
+        assert_eq!(evaluate("1.5e1 + .5", &[]), 15.5);
+        assert_eq!(evaluate("1 / 0", &[]), 0.0);
+    }
+
+    #[test]
+    fn variables_and_functions_are_resolved() {
+        let formula = Formula::parse("sin(x * 10 + a) * 0.5 + 0.5 + clamp(a, 0, x)").unwrap();
+        assert_eq!(formula.variables(), ["x", "a"]);
+
+        let expected = (0.2f64 * 10.0 + 1.0).sin() * 0.5 + 0.5 + 0.2;
+        assert!((formula.evaluate(&[0.2, 1.0]) - expected).abs() < 1e-12);
+
+        assert_eq!(evaluate("max(pow(2, 3), sqrt(81))", &[]), 9.0);
+        assert_eq!(evaluate("remap(0, -1, 1, 0, 10)", &[]), 5.0);
+    }
+
+    #[test]
+    fn errors_point_at_the_problem() {
+        let error = |source: &str| Formula::parse(source).unwrap_err();
+
+        assert_eq!(
+            error("1 + * 2"),
+            ParseError::new("expected a value but found '*'", 4)
+        );
+        assert_eq!(
+            error("(1 + 2"),
+            ParseError::new("expected ')' but found end of the formula", 6)
+        );
+        assert_eq!(
+            error("foo(1)"),
+            ParseError::new("unknown function \"foo\"", 0)
+        );
+        assert_eq!(
+            error("2 * min(1)"),
+            ParseError::new("min expects 2 arguments but got 1", 4)
+        );
+        assert_eq!(
+            error("1 2"),
+            ParseError::new("expected an operator but found number 2", 2)
+        );
+        assert_eq!(
+            error("x # 2"),
+            ParseError::new("unexpected character '#'", 2)
+        );
+        assert_eq!(
+            error(""),
+            ParseError::new("expected a value but found end of the formula", 0)
+        );
+    }
+
+    #[test]
+    fn deeply_nested_formulas_are_errors() {
+        let nested = |open: &str, inner: &str, close: &str, depth: usize| {
+            format!("{}{inner}{}", open.repeat(depth), close.repeat(depth))
+        };
+
+        assert_eq!(evaluate(&nested("(", "1", ")", 100), &[]), 1.0);
+        assert_eq!(evaluate(&nested("-", "1", "", 100), &[]), 1.0);
+        assert_eq!(evaluate(&nested("", "1", " + 1", 100), &[]), 101.0);
+
+        for source in [
+            nested("(", "1", ")", 100_000),
+            nested("-", "x", "", 100_000),
+            nested("sin(", "x", ")", 100_000),
+            nested("", "x", " ^ x", 100_000),
+        ] {
+            let error = Formula::parse(&source).unwrap_err();
+            assert_eq!(error.message, "the formula is nested too deeply");
+        }
+    }
+
+    #[test]
+    fn long_chains_of_operators_are_not_nested() {
+        let sum = format!("x{}", " + x".repeat(10_000));
+        assert_eq!(evaluate(&sum, &[1.0]), 10_001.0);
+
+        let product = format!("x{}", " * x / x".repeat(10_000));
+        assert_eq!(evaluate(&product, &[2.0]), 2.0);
+
+        assert_eq!(evaluate("10 - 4 - 3 + 1", &[]), 4.0);
+        assert_eq!(evaluate("24 / 4 / 2 * 3 % 5", &[]), 4.0);
+    }
+}
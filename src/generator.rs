@@ -1,39 +1,84 @@
-use std::cell::RefCell;
-use std::collections::HashMap;
-use std::rc::Rc;
-
-use anyhow::Result;
-use petgraph::data::FromElements;
-use petgraph::stable_graph::{EdgeIndex, NodeIndex};
-use petgraph::visit::Dfs;
-use petgraph::{Directed, Graph};
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError, RwLock, RwLockReadGuard};
+
+use petgraph::stable_graph::{EdgeIndex, NodeIndex, StableGraph};
+use petgraph::visit::{Dfs, EdgeRef, Reversed};
+use petgraph::{Directed, Direction};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use rusvid_core::pixel::Pixel;
 use rusvid_core::plane::Plane;
 
 use crate::coordinate::Coordinate;
+use crate::diagnostic::{Diagnostic, DiagnosticKind};
+use crate::error::{GeneratorError, Result};
+use crate::group_definition::{group_revision, is_recursive};
 use crate::input_output_value::InputOutputValue;
 use crate::library::output::Output;
 use crate::link::Link;
-use crate::node::Node;
+use crate::node::{BatchInputs, BatchValue, Node, SharedNode};
+use crate::parameter::ParameterValue;
+use crate::port::{Port, Sockets, DEFAULT_INPUT, DEFAULT_OUTPUT};
 
 #[derive(Debug)]
 pub struct Generator {
-    pub(crate) internal_graph: Graph<Rc<RefCell<dyn Node>>, (), Directed>,
+    /// The weight of an edge names the output of the source node and the input of the target node it connects.
+    ///
+    /// A `StableGraph` is used so that the indices of nodes and edges stay valid after other nodes or edges got removed.
+    pub(crate) internal_graph: StableGraph<SharedNode, Sockets, Directed>,
 
-    named_links: HashMap<Link, String>,
     output_node: NodeIndex,
+
+    /// Whether the nodes make their output wrap around the borders of the image, see [`Generator::set_tileable`].
+    tileable: bool,
+
+    /// Whether the values of the nodes are kept for the next render, see [`Generator::set_caching`].
+    caching: bool,
+    cache: Mutex<RenderCache>,
+
+    /// Changes whenever the graph changes, see [`Generator::revision`].
+    revision: u64,
+}
+
+/// The last revision any generator got, revisions are never reused so that they can be compared across generators.
+static REVISION: AtomicU64 = AtomicU64::new(0);
+
+fn next_revision() -> u64 {
+    REVISION.fetch_add(1, Ordering::Relaxed) + 1
 }
 
-unsafe impl Sync for Generator {}
-unsafe impl Send for Generator {}
+/// Number of rows that are evaluated together when the cache is off, see [`Generator::set_caching`].
+const BAND_HEIGHT: u32 = 16;
+
+/// The values of the nodes from the last call of [`Generator::generate`], only used with
+/// [`Generator::set_caching`].
+#[derive(Debug, Default)]
+struct RenderCache {
+    size: Option<(u32, u32)>,
+    /// The values of every output of a node for every pixel in row-major order.
+    buffers: Buffers,
+    /// Nodes that changed since their buffer got rendered.
+    dirty: HashSet<NodeIndex>,
+    /// The [`group_revision`] of the definitions of the group nodes at the last render.
+    groups: HashMap<NodeIndex, u64>,
+}
 
 impl Generator {
     pub fn new() -> Self {
         let mut g = Generator {
-            internal_graph: Graph::new(),
+            internal_graph: StableGraph::new(),
 
-            named_links: HashMap::new(),
             // TODO maybe use an option for this because we set this value in the following lines and '0' is a fake value
             output_node: NodeIndex::new(0),
+
+            tileable: false,
+
+            caching: false,
+            cache: Mutex::new(RenderCache::default()),
+
+            revision: next_revision(),
         };
 
         g.output_node = g.add_node(Output::new());
@@ -41,157 +86,1622 @@ impl Generator {
         g
     }
 
+    /// The output node that every generator has and that [`Generator::generate`] renders.
     pub fn output_node(&self) -> NodeIndex {
         self.output_node
     }
 
+    /// Adds another output node, e.g. to render the roughness of a material next to its albedo.
+    ///
+    /// Fails if another output already uses the channel name.
+    pub fn add_output<S: Into<String>>(&mut self, channel: S) -> Result<NodeIndex> {
+        let channel = channel.into();
+        if self.output(&channel).is_some() {
+            return Err(GeneratorError::DuplicateChannel(channel));
+        }
+
+        let mut output = Output::with_channel(channel.clone());
+        output.space_info_mut().name = channel;
+
+        Ok(self.add_node(output))
+    }
+
+    /// Returns the channel name and index of every output node.
+    pub fn outputs(&self) -> Vec<(String, NodeIndex)> {
+        self.internal_graph
+            .node_indices()
+            .filter_map(|index| {
+                let node = self.internal_graph[index].read().unwrap();
+
+                node.output_channel()
+                    .map(|channel| (channel.to_string(), index))
+            })
+            .collect()
+    }
+
+    /// The ports of the group inputs in the graph, these are the inputs of a group node that wraps the graph.
+    pub fn group_inputs(&self) -> Vec<Port> {
+        self.internal_graph
+            .node_indices()
+            .filter_map(|index| self.internal_graph[index].read().unwrap().group_input())
+            .collect()
+    }
+
+    /// Returns the output node with the channel name.
+    pub fn output(&self, channel: &str) -> Option<NodeIndex> {
+        self.outputs()
+            .into_iter()
+            .find(|(name, _)| name == channel)
+            .map(|(_, index)| index)
+    }
+
     pub fn add_node<N: Node + 'static>(&mut self, node: N) -> NodeIndex {
-        self.internal_graph.add_node(Rc::new(RefCell::new(node)))
+        self.add_shared_node(Arc::new(RwLock::new(node)))
     }
 
-    pub fn add_edge(&mut self, link: Link) -> EdgeIndex {
-        self.add_edge_named(link, "_")
+    /// Adds a node that was created by a [`NodeRegistry`](crate::registry::NodeRegistry).
+    pub fn add_shared_node(&mut self, node: SharedNode) -> NodeIndex {
+        let index = self.internal_graph.add_node(node);
+        // The index could belong to a removed node that still has a buffer.
+        self.mark_dirty(index);
+
+        index
     }
 
-    pub fn add_edge_named<S: Into<String>>(&mut self, link: Link, name: S) -> EdgeIndex {
-        let name: String = name.into();
-        self.named_links.insert(link.clone(), name);
+    /// Marks the node as changed so that it and every node that depends on it get rendered again by the next call of
+    /// [`Generator::generate`].
+    ///
+    /// Changes through the methods of the generator do this on their own, this is only needed after a node was changed
+    /// through its [`SharedNode`].
+    pub fn mark_dirty(&mut self, node: NodeIndex) {
+        self.cache_mut().dirty.insert(node);
+    }
+
+    pub fn tileable(&self) -> bool {
+        self.tileable
+    }
+
+    /// Makes the output tile seamlessly, the right border continues at the left border and the bottom border at the
+    /// top border. Use [`seam_error`](crate::tiling::seam_error) to check the result.
+    ///
+    /// The nodes learn about the mode from [`BatchInputs::tileable`] while they get evaluated, groups use the mode of
+    /// the graph around them. Perlin, value and Worley noise and Voronoi cells hash their lattice modulo the number of
+    /// cells per image, the scale gets rounded to whole cells for this. The skewed lattice of the simplex algorithms
+    /// doesn't line up with the image, Simplex and OpenSimplex are sampled on a torus in 4D instead. SuperSimplex has no
+    /// 4D variant, it blends four copies of the noise shifted by one image.
+    pub fn set_tileable(&mut self, tileable: bool) {
+        self.tileable = tileable;
+        self.clear_cache();
+    }
+
+    pub fn caching(&self) -> bool {
+        self.caching
+    }
+
+    /// Keeps the values of every node for every pixel, so that the next render only evaluates the nodes again that
+    /// changed or depend on a changed node (see [`Generator::mark_dirty`]). Meant for an editor that renders the output
+    /// and the previews after every change, the cache holds one value per pixel for every output of every node.
+    ///
+    /// Without the cache, the default, the image is rendered in bands of rows and the values of a node are dropped as
+    /// soon as the last node that reads them got evaluated.
+    pub fn set_caching(&mut self, caching: bool) {
+        self.caching = caching;
+        self.clear_cache();
+    }
+
+    /// Drops the buffers of every node, the next call of [`Generator::generate`] renders everything again.
+    pub fn clear_cache(&mut self) {
+        *self.cache_mut() = RenderCache::default();
+    }
+
+    /// Every change of the graph goes through here, so this is where the revision changes.
+    fn cache_mut(&mut self) -> &mut RenderCache {
+        self.revision = next_revision();
+        self.cache.get_mut().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// A number that is larger after every change of the graph than any revision before, of this or any other generator.
+    ///
+    /// Instances of a [`Group`](crate::library::group::Group) use it to notice that their definition changed.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// A copy of the graph that shares its nodes with this generator, e.g. to edit the graph of a group while its
+    /// definition keeps a graph. Changes of the nodes themselves affect both graphs, changes of the graph don't.
+    pub fn share_nodes(&self) -> Generator {
+        Generator {
+            internal_graph: self.internal_graph.clone(),
+            output_node: self.output_node,
+            tileable: self.tileable,
+            caching: false,
+            cache: Mutex::new(RenderCache::default()),
+            revision: next_revision(),
+        }
+    }
+
+    /// Changes a parameter of the node, see [`Node::set_parameter`].
+    ///
+    /// Parameters can change the ports of the node, e.g. the operation of a [`Math`](crate::library::math::Math) node.
+    /// Edges to ports that the node doesn't have anymore are removed like in [`Generator::replace_node`] and returned.
+    ///
+    /// Fails with [`GeneratorError::DuplicateChannel`] if this would give an output node the channel of another output,
+    /// the parameter keeps its value then.
+    pub fn set_parameter(
+        &mut self,
+        node: NodeIndex,
+        name: &str,
+        value: ParameterValue,
+    ) -> Result<Vec<(Link, Sockets)>> {
+        let is_output = self
+            .node_ref(node)?
+            .read()
+            .unwrap()
+            .output_channel()
+            .is_some();
+        if !is_output {
+            self.internal_graph[node]
+                .write()
+                .unwrap()
+                .set_parameter(name, value)?;
+            self.mark_dirty(node);
+
+            return Ok(self.remove_unknown_edges(node));
+        }
+
+        let other_channels = self
+            .outputs()
+            .into_iter()
+            .filter(|(_, index)| *index != node)
+            .map(|(channel, _)| channel)
+            .collect::<Vec<_>>();
+        {
+            let mut output = self.internal_graph[node].write().unwrap();
+            let previous = output.parameter(name)?;
+            output.set_parameter(name, value)?;
+
+            if let Some(channel) = output.output_channel() {
+                if other_channels.iter().any(|other| other == channel) {
+                    let channel = channel.to_string();
+                    output.set_parameter(name, previous)?;
+                    return Err(GeneratorError::DuplicateChannel(channel));
+                }
+            }
+        }
+        self.mark_dirty(node);
+
+        Ok(self.remove_unknown_edges(node))
+    }
 
+    /// Moves the node to `position` in the editor and returns its previous position.
+    pub fn move_node(&mut self, node: NodeIndex, position: (i64, i64)) -> Result<(i64, i64)> {
+        let mut node = self.node_ref(node)?.write().unwrap();
+
+        Ok(std::mem::replace(
+            &mut node.space_info_mut().position,
+            position,
+        ))
+    }
+
+    fn node_ref(&self, index: NodeIndex) -> Result<&SharedNode> {
         self.internal_graph
-            .add_edge(link.input_node, link.output_node, ())
+            .node_weight(index)
+            .ok_or(GeneratorError::UnknownNode(index))
+    }
+
+    /// Removes the node and every edge connected to it, the output node can't be removed.
+    pub fn remove_node(&mut self, index: NodeIndex) -> Result<SharedNode> {
+        if index == self.output_node {
+            return Err(GeneratorError::OutputNodeRequired);
+        }
+
+        let targets = self
+            .internal_graph
+            .neighbors_directed(index, Direction::Outgoing)
+            .collect::<Vec<_>>();
+        let node = self
+            .internal_graph
+            .remove_node(index)
+            .ok_or(GeneratorError::UnknownNode(index))?;
+
+        let cache = self.cache_mut();
+        cache.buffers.remove(&index);
+        cache.dirty.remove(&index);
+        cache.groups.remove(&index);
+        cache.dirty.extend(targets);
+
+        Ok(node)
+    }
+
+    /// Replaces the node at `index` with `node`, the new node takes over the position of the old one.
+    ///
+    /// Edges into inputs that `node` doesn't have are removed, as well as the outgoing edges if `node` has no outputs.
+    pub fn replace_node<N: Node + 'static>(
+        &mut self,
+        index: NodeIndex,
+        mut node: N,
+    ) -> Result<SharedNode> {
+        if index == self.output_node && !node.is_output() {
+            return Err(GeneratorError::OutputNodeRequired);
+        }
+
+        {
+            let old = self.node_ref(index)?.read().unwrap();
+            node.space_info_mut().position = old.space_info().position;
+            node.space_info_mut().z_index = old.space_info().z_index;
+        }
+
+        let new: SharedNode = Arc::new(RwLock::new(node));
+        let old = std::mem::replace(&mut self.internal_graph[index], new);
+        self.mark_dirty(index);
+        self.remove_unknown_edges(index);
+
+        Ok(old)
+    }
+
+    /// Removes the edges into inputs that the node doesn't have and the edges out of outputs that it doesn't have, e.g.
+    /// after the node changed its ports, and returns them.
+    fn remove_unknown_edges(&mut self, index: NodeIndex) -> Vec<(Link, Sockets)> {
+        let (inputs, outputs) = {
+            let node = self.internal_graph[index].read().unwrap();
+            (node.inputs(), node.outputs())
+        };
+
+        let unknown_edges = self
+            .internal_graph
+            .edges_directed(index, Direction::Incoming)
+            .filter(|edge| !inputs.iter().any(|port| port.name == edge.weight().input))
+            .chain(
+                self.internal_graph
+                    .edges_directed(index, Direction::Outgoing)
+                    .filter(|edge| !outputs.iter().any(|port| port.name == edge.weight().output)),
+            )
+            .map(|edge| edge.id())
+            .collect::<Vec<_>>();
+
+        unknown_edges
+            .into_iter()
+            .filter_map(|edge| self.remove_edge(edge).ok())
+            .collect()
+    }
+
+    /// Returns every edge that starts or ends at `node` together with the sockets that the edge connects.
+    pub fn edges_of(&self, node: NodeIndex) -> Vec<(EdgeIndex, Link, Sockets)> {
+        self.internal_graph
+            .edges_directed(node, Direction::Incoming)
+            .chain(
+                self.internal_graph
+                    .edges_directed(node, Direction::Outgoing),
+            )
+            .map(|edge| {
+                (
+                    edge.id(),
+                    Link {
+                        input_node: edge.source(),
+                        output_node: edge.target(),
+                    },
+                    edge.weight().clone(),
+                )
+            })
+            .collect()
+    }
+
+    /// Connects `link` to the first input of the target node.
+    pub fn add_edge(&mut self, link: Link) -> Result<EdgeIndex> {
+        let first_input = self
+            .node_ref(link.output_node)?
+            .read()
+            .unwrap()
+            .inputs()
+            .into_iter()
+            .next();
+
+        match first_input {
+            Some(port) => self.add_edge_named(link, port.name),
+            None => Err(GeneratorError::UnknownPort {
+                node: self.internal_graph[link.output_node]
+                    .read()
+                    .unwrap()
+                    .space_info()
+                    .name
+                    .clone(),
+                port: DEFAULT_INPUT.to_string(),
+            }),
+        }
+    }
+
+    /// Connects the first output of the source node of `link` to the input `name` of the target node, fails if the
+    /// target node has no input with this name.
+    ///
+    /// An edge that is already connected to the input gets replaced.
+    pub fn add_edge_named<S: Into<String>>(&mut self, link: Link, name: S) -> Result<EdgeIndex> {
+        let output = self
+            .node_ref(link.input_node)?
+            .read()
+            .unwrap()
+            .outputs()
+            .into_iter()
+            .next()
+            .map_or_else(|| DEFAULT_OUTPUT.to_string(), |port| port.name);
+
+        self.connect(link, Sockets::new(output, name))
     }
 
-    pub fn connected_nodes_to_output(&self) -> Vec<Rc<RefCell<dyn Node>>> {
-        let mut g = self.internal_graph.clone();
-        g.reverse();
+    /// Connects the output `output` of the source node of `link` to the input `input` of the target node.
+    ///
+    /// An edge that is already connected to the input gets replaced.
+    pub fn add_edge_sockets<O: Into<String>, I: Into<String>>(
+        &mut self,
+        link: Link,
+        output: O,
+        input: I,
+    ) -> Result<EdgeIndex> {
+        let sockets = Sockets::new(output, input);
+
+        {
+            let source = self.node_ref(link.input_node)?.read().unwrap();
+            if !source
+                .outputs()
+                .iter()
+                .any(|port| port.name == sockets.output)
+            {
+                return Err(GeneratorError::UnknownPort {
+                    node: source.space_info().name.clone(),
+                    port: sockets.output,
+                });
+            }
+        }
+
+        self.connect(link, sockets)
+    }
 
-        let mut used_nodes_for_output = Vec::with_capacity(g.node_count());
-        let mut dfs = Dfs::new(&g, self.output_node());
-        while let Some(nx) = dfs.next(&g) {
-            used_nodes_for_output.push(g[nx].clone());
+    /// Like [`Generator::add_edge_sockets`] but doesn't check the output of the source node, an unknown output is
+    /// reported by [`Generator::validate`] as dangling edge.
+    pub(crate) fn connect(&mut self, link: Link, sockets: Sockets) -> Result<EdgeIndex> {
+        self.node_ref(link.input_node)?;
+        {
+            let target = self.node_ref(link.output_node)?.read().unwrap();
+            if !target
+                .inputs()
+                .iter()
+                .any(|port| port.name == sockets.input)
+            {
+                return Err(GeneratorError::UnknownPort {
+                    node: target.space_info().name.clone(),
+                    port: sockets.input,
+                });
+            }
+        }
+
+        self.disconnect_port(link.output_node, &sockets.input)?;
+        self.mark_dirty(link.output_node);
+
+        Ok(self
+            .internal_graph
+            .add_edge(link.input_node, link.output_node, sockets))
+    }
+
+    /// Removes the edge and returns the link and the sockets it connected.
+    pub fn remove_edge(&mut self, edge: EdgeIndex) -> Result<(Link, Sockets)> {
+        let (input_node, output_node) = self
+            .internal_graph
+            .edge_endpoints(edge)
+            .ok_or(GeneratorError::UnknownEdge(edge))?;
+        let sockets = self
+            .internal_graph
+            .remove_edge(edge)
+            .ok_or(GeneratorError::UnknownEdge(edge))?;
+        self.mark_dirty(output_node);
+
+        Ok((
+            Link {
+                input_node,
+                output_node,
+            },
+            sockets,
+        ))
+    }
+
+    /// Removes the edges that are connected to the input `port` of `node` and returns the nodes they came from.
+    pub fn disconnect_port(&mut self, node: NodeIndex, port: &str) -> Result<Vec<NodeIndex>> {
+        {
+            let target = self.node_ref(node)?.read().unwrap();
+            if !target.inputs().iter().any(|input| input.name == port) {
+                return Err(GeneratorError::UnknownPort {
+                    node: target.space_info().name.clone(),
+                    port: port.to_string(),
+                });
+            }
+        }
+
+        let edges = self
+            .internal_graph
+            .edges_directed(node, Direction::Incoming)
+            .filter(|edge| edge.weight().input == port)
+            .map(|edge| (edge.id(), edge.source()))
+            .collect::<Vec<_>>();
+
+        if !edges.is_empty() {
+            self.mark_dirty(node);
+        }
+
+        Ok(edges
+            .into_iter()
+            .map(|(edge, source)| {
+                self.internal_graph.remove_edge(edge);
+                source
+            })
+            .collect())
+    }
+
+    pub fn connected_nodes_to_output(&self) -> Vec<SharedNode> {
+        self.connected_indices_to_output()
+            .into_iter()
+            .map(|index| self.internal_graph[index].clone())
+            .collect()
+    }
+
+    /// Like [`Generator::connected_nodes_to_output`] but returns the indices of the nodes, includes every output.
+    pub fn connected_indices_to_output(&self) -> Vec<NodeIndex> {
+        let reversed = Reversed(&self.internal_graph);
+
+        let mut used_nodes_for_output = Vec::with_capacity(self.internal_graph.node_count());
+        let mut dfs = Dfs::new(reversed, self.output_node());
+        for (_, output) in self.outputs() {
+            dfs.move_to(output);
+            while let Some(nx) = dfs.next(reversed) {
+                used_nodes_for_output.push(nx);
+            }
         }
         used_nodes_for_output.reverse();
 
         used_nodes_for_output
     }
 
-    fn nodes_as_tree(&self) -> Option<RelationsBetweenNodes> {
-        let mst = petgraph::graph::DiGraph::<_, _>::from_elements(
-            petgraph::algo::min_spanning_tree(&self.internal_graph),
-        );
+    fn connected_to_output(&self) -> HashSet<NodeIndex> {
+        let outputs = self
+            .outputs()
+            .into_iter()
+            .map(|(_, index)| index)
+            .collect::<Vec<_>>();
 
-        let mut map: HashMap<NodeIndex, RelationsBetweenNodes> = HashMap::new();
+        self.upstream_of(&outputs)
+    }
+
+    /// Returns the `nodes` and every node that they depend on.
+    fn upstream_of(&self, nodes: &[NodeIndex]) -> HashSet<NodeIndex> {
+        let reversed = Reversed(&self.internal_graph);
+        let mut upstream = HashSet::new();
+        for node in nodes {
+            let mut dfs = Dfs::new(reversed, *node);
+            while let Some(nx) = dfs.next(reversed) {
+                upstream.insert(nx);
+            }
+        }
 
-        for edge in mst.raw_edges() {
-            let source = edge.source();
-            let target = edge.target();
+        upstream
+    }
 
-            let source_tree = match map.get(&source) {
-                Some(item) => item.clone(),
-                None => {
-                    let node = mst.node_weight(source).unwrap().clone();
+    /// Checks the graph for problems that would prevent it from being rendered, like cycles, unconnected required inputs
+    /// or inputs that can't handle the values of the connected node.
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        let connected = self.connected_to_output();
+        let name_of = |index: NodeIndex| {
+            self.internal_graph[index]
+                .read()
+                .unwrap()
+                .space_info()
+                .name
+                .clone()
+        };
 
-                    let rbn = RelationsBetweenNodes::new(node);
-                    map.insert(source, rbn.clone());
+        for component in petgraph::algo::tarjan_scc(&self.internal_graph) {
+            let is_cycle = component.len() > 1
+                || self
+                    .internal_graph
+                    .contains_edge(component[0], component[0]);
 
-                    rbn
+            if is_cycle {
+                for index in component {
+                    diagnostics.push(Diagnostic::new(
+                        DiagnosticKind::Cycle,
+                        index,
+                        name_of(index),
+                    ));
                 }
-            };
+            }
+        }
 
-            let mut target_tree = match map.get(&target) {
-                Some(item) => item.clone(),
-                None => {
-                    let node = mst.node_weight(target).unwrap().clone();
+        for index in self.internal_graph.node_indices() {
+            let node = self.internal_graph[index].read().unwrap();
+            let name = &node.space_info().name;
+            let inputs = node.inputs();
 
-                    let rbn = RelationsBetweenNodes::new(node);
-                    map.insert(target, rbn.clone());
+            if !connected.contains(&index) {
+                diagnostics.push(Diagnostic::new(
+                    DiagnosticKind::UnreachableNode,
+                    index,
+                    name,
+                ));
+            }
 
-                    rbn
+            for kind in node.diagnostics() {
+                diagnostics.push(Diagnostic::new(kind, index, name));
+            }
+            if node.group().is_some_and(is_recursive) {
+                diagnostics.push(Diagnostic::new(DiagnosticKind::RecursiveGroup, index, name));
+            }
+
+            for edge in self
+                .internal_graph
+                .edges_directed(index, Direction::Incoming)
+            {
+                let source = self.internal_graph[edge.source()].read().unwrap();
+                let source_outputs = source.outputs();
+
+                let sockets = edge.weight();
+                let port = inputs.iter().find(|port| port.name == sockets.input);
+                let socket = source_outputs
+                    .iter()
+                    .find(|port| port.name == sockets.output);
+                let (port, socket) = match (port, socket) {
+                    (Some(port), Some(socket)) => (port, socket),
+                    _ => {
+                        diagnostics.push(
+                            Diagnostic::new(DiagnosticKind::DanglingEdge, index, name)
+                                .with_port(&sockets.input),
+                        );
+                        continue;
+                    }
+                };
+
+                let found = &socket.kinds;
+                if !found.is_empty() && !found.iter().any(|kind| port.accepts(*kind)) {
+                    diagnostics.push(
+                        Diagnostic::new(
+                            DiagnosticKind::TypeMismatch {
+                                expected: port.kinds.clone(),
+                                found: found.clone(),
+                            },
+                            index,
+                            name,
+                        )
+                        .with_port(&port.name),
+                    );
                 }
-            };
+            }
 
-            target_tree.add_children(
-                source_tree,
-                self.named_links.get(&Link::new(source, target)).unwrap(),
-            );
+            // An input never has more than one edge, connecting it again replaces the edge.
+            for port in &inputs {
+                let connected_input = self
+                    .internal_graph
+                    .edges_directed(index, Direction::Incoming)
+                    .any(|edge| edge.weight().input == port.name);
+
+                if !connected_input && port.required && connected.contains(&index) {
+                    diagnostics.push(
+                        Diagnostic::new(DiagnosticKind::MissingInput, index, name)
+                            .with_port(&port.name),
+                    );
+                }
+            }
+        }
+
+        let outputs = self.outputs();
+        for (channel, index) in &outputs {
+            if outputs.iter().filter(|(other, _)| other == channel).count() > 1 {
+                diagnostics.push(Diagnostic::new(
+                    DiagnosticKind::DuplicateChannel,
+                    *index,
+                    name_of(*index),
+                ));
+            }
+        }
+
+        let group_inputs = self
+            .internal_graph
+            .node_indices()
+            .filter_map(|index| {
+                let port = self.internal_graph[index].read().unwrap().group_input()?;
+
+                Some((port.name, index))
+            })
+            .collect::<Vec<_>>();
+        for (name, index) in &group_inputs {
+            if group_inputs
+                .iter()
+                .filter(|(other, _)| other == name)
+                .count()
+                > 1
+            {
+                diagnostics.push(Diagnostic::new(
+                    DiagnosticKind::DuplicateGroupInput,
+                    *index,
+                    name_of(*index),
+                ));
+            }
+        }
+
+        diagnostics
+    }
+
+    /// Returns the `targets` and every node that they depend on in topological order, together with the nodes, the
+    /// index of their output and the input names that feed into it.
+    ///
+    /// Nodes that are only connected to a [`Node::warped_input`] aren't part of the order, they get evaluated at the
+    /// warped positions instead, see [`Generator::plan`].
+    ///
+    /// Only these nodes are sorted, a cycle in another part of the graph doesn't prevent the evaluation.
+    fn evaluation_order(&self, targets: &[NodeIndex]) -> Result<Vec<EvaluationStep>> {
+        // The number of sources of every node that aren't sorted yet and the nodes that read from every node.
+        let mut unsorted_sources = HashMap::<NodeIndex, usize>::new();
+        let mut readers = HashMap::<NodeIndex, Vec<NodeIndex>>::new();
+        let mut pending = targets.to_vec();
+        while let Some(index) = pending.pop() {
+            if unsorted_sources.contains_key(&index) {
+                continue;
+            }
+
+            let node = self.internal_graph[index].read().unwrap();
+            let warped_input = node.warped_input();
+            let sources = self
+                .internal_graph
+                .edges_directed(index, Direction::Incoming)
+                .filter(|edge| Some(edge.weight().input.as_str()) != warped_input)
+                .map(|edge| edge.source())
+                .collect::<Vec<_>>();
+            for source in &sources {
+                readers.entry(*source).or_default().push(index);
+            }
+            unsorted_sources.insert(index, sources.len());
+            pending.extend(sources);
+        }
+
+        let mut ready = unsorted_sources
+            .iter()
+            .filter(|(_, count)| **count == 0)
+            .map(|(index, _)| *index)
+            .collect::<Vec<_>>();
+        ready.sort_unstable_by(|a, b| b.cmp(a));
+
+        let mut sorted = Vec::with_capacity(unsorted_sources.len());
+        while let Some(index) = ready.pop() {
+            sorted.push(index);
+            for reader in readers.remove(&index).unwrap_or_default() {
+                let count = unsorted_sources.get_mut(&reader).unwrap();
+                *count -= 1;
+                if *count == 0 {
+                    ready.push(reader);
+                }
+            }
+        }
+
+        if sorted.len() < unsorted_sources.len() {
+            let cycle = unsorted_sources
+                .iter()
+                .filter(|(_, count)| **count > 0)
+                .map(|(index, _)| *index)
+                .min()
+                .unwrap();
+
+            return Err(GeneratorError::CycleDetected {
+                node: self.internal_graph[cycle]
+                    .read()
+                    .unwrap()
+                    .space_info()
+                    .name
+                    .clone(),
+            });
+        }
+
+        sorted
+            .into_iter()
+            .map(|index| self.evaluation_step(index))
+            .collect()
+    }
+
+    fn evaluation_step(&self, index: NodeIndex) -> Result<EvaluationStep> {
+        let node = self.internal_graph[index].read().unwrap();
+        let mut inputs = Vec::new();
+        let mut warped = None;
+        for edge in self
+            .internal_graph
+            .edges_directed(index, Direction::Incoming)
+        {
+            let sockets = edge.weight();
+            let source = self.internal_graph[edge.source()].read().unwrap();
+            let output = source
+                .outputs()
+                .iter()
+                .position(|port| port.name == sockets.output)
+                .ok_or_else(|| GeneratorError::UnknownPort {
+                    node: source.space_info().name.clone(),
+                    port: sockets.output.clone(),
+                })?;
+
+            let input = (edge.source(), output, sockets.input.clone());
+            if node.warped_input() == Some(sockets.input.as_str()) {
+                warped = Some(input);
+            } else {
+                inputs.push(input);
+            }
+        }
 
-            if target_tree.node.borrow().is_output() {
-                return Some(target_tree);
+        let defaults = node
+            .inputs()
+            .into_iter()
+            .filter(|port| !inputs.iter().any(|(_, _, name)| name == &port.name))
+            .filter(|port| !warped.iter().any(|(_, _, name)| name == &port.name))
+            .filter_map(|port| port.default.map(|default| (port.name, default)))
+            .collect();
+
+        Ok(EvaluationStep {
+            node: index,
+            inputs,
+            warped,
+            defaults,
+        })
+    }
+
+    /// Plans the evaluation of the `targets`: the steps of the nodes that they depend on and, for every node with a
+    /// connected [`Node::warped_input`], the steps of the nodes before that input.
+    fn plan(&self, targets: &[NodeIndex]) -> Result<Plan> {
+        let steps = self.evaluation_order(targets)?;
+        let mut pending = steps.iter().filter_map(Plan::warp).collect::<Vec<_>>();
+        let mut warped = HashMap::new();
+        while let Some((node, source)) = pending.pop() {
+            if warped.contains_key(&node) {
+                continue;
             }
 
-            map.insert(target, target_tree);
+            let steps = self.evaluation_order(&[source])?;
+            pending.extend(steps.iter().filter_map(Plan::warp));
+            warped.insert(node, steps);
         }
 
-        return None;
+        Ok(Plan { steps, warped })
     }
 
+    /// Locks every node of the `plan` once for the whole evaluation instead of once per sample.
+    fn lock(&self, plan: &Plan) -> HashMap<NodeIndex, RwLockReadGuard<'_, dyn Node>> {
+        plan.nodes()
+            .into_iter()
+            .map(|index| (index, self.internal_graph[index].read().unwrap()))
+            .collect()
+    }
+
+    /// Renders the graph into a plane of the given size.
+    ///
+    /// With the `parallel` feature the image is evaluated on multiple threads, the result is the same as without it.
+    /// See [`Generator::set_caching`] to render only the nodes again that changed since the last call.
     pub fn generate(&self, width: u32, height: u32) -> Result<Plane> {
+        self.generate_from(self.output_node, width, height)
+    }
+
+    /// Renders the output of any node, e.g. to preview a step in the middle of the graph. Of a node with multiple
+    /// outputs the first one is rendered.
+    ///
+    /// Only the nodes that `node` depends on are evaluated and need to be valid. With [`Generator::set_caching`] the
+    /// buffers are shared with [`Generator::generate`], so previews in the size of the output are cheap.
+    pub fn generate_from(&self, node: NodeIndex, width: u32, height: u32) -> Result<Plane> {
+        let mut planes = self.render(&[node], width, height)?;
+
+        Ok(planes.remove(0))
+    }
+
+    /// Renders every output node and returns the images by their channel name.
+    ///
+    /// Nodes that are shared between outputs are only evaluated once.
+    pub fn generate_all(&self, width: u32, height: u32) -> Result<HashMap<String, Plane>> {
+        let (channels, outputs): (Vec<_>, Vec<_>) = self.outputs().into_iter().unzip();
+        let planes = self.render(&outputs, width, height)?;
+
+        Ok(channels.into_iter().zip(planes).collect())
+    }
+
+    /// Renders the `targets` in one pass and returns their images in the same order.
+    fn render(&self, targets: &[NodeIndex], width: u32, height: u32) -> Result<Vec<Plane>> {
+        for target in targets {
+            self.node_ref(*target)?;
+        }
+
+        let upstream = self.upstream_of(targets);
+        let errors = self
+            .validate()
+            .into_iter()
+            .filter(|diagnostic| diagnostic.is_error() && upstream.contains(&diagnostic.node))
+            .collect::<Vec<_>>();
+        if !errors.is_empty() {
+            return Err(GeneratorError::InvalidGraph(errors));
+        }
+
         let size = (width, height);
-        let mut plane = Plane::new(size.0, size.1)?;
+        let plan = self.plan(targets)?;
+        let guards = self.lock(&plan);
+        let evaluator = Evaluator::new(&plan, &guards, size, self.tileable);
+        if !self.caching {
+            return evaluator.render_bands(targets);
+        }
+
+        let mut cache = self.cache.lock().unwrap_or_else(PoisonError::into_inner);
+        if cache.size != Some(size) {
+            cache.buffers.clear();
+            cache.size = Some(size);
+        }
+        // Instances of a group are outdated once its definition changed.
+        for (index, node) in &guards {
+            if let Some(group) = node.group() {
+                let revision = group_revision(group);
+                if cache.groups.insert(*index, revision) != Some(revision) {
+                    cache.dirty.insert(*index);
+                }
+            }
+        }
+
+        let mut rendered = HashSet::new();
+        let mut evaluated = HashSet::new();
+        for step in &plan.steps {
+            let warped = match step.warped {
+                Some(_) => plan.warped_nodes(step.node),
+                None => HashSet::new(),
+            };
+            let outdated = cache.dirty.contains(&step.node)
+                || !cache.buffers.contains_key(&step.node)
+                || step
+                    .inputs
+                    .iter()
+                    .any(|(source, _, _)| rendered.contains(source))
+                || warped
+                    .iter()
+                    .any(|node| cache.dirty.contains(node) || rendered.contains(node));
+            if !outdated {
+                continue;
+            }
+
+            match evaluator.render_node(step, &cache.buffers) {
+                Ok(outputs) => {
+                    cache.buffers.insert(step.node, outputs);
+                    rendered.insert(step.node);
+                    evaluated.extend(warped);
+                }
+                Err(err) => {
+                    // Some buffers could already be newer than the nodes that depend on them.
+                    cache.buffers.clear();
+                    return Err(err);
+                }
+            }
+        }
+        // Nodes that weren't part of this render stay dirty until they get rendered, the nodes after a node that got
+        // rendered again are now outdated as well. Nodes before a warped input have no buffer of their own, they are
+        // up to date once the warped node got rendered.
+        evaluated.extend(rendered);
+        cache.dirty.retain(|node| !evaluated.contains(node));
+        for index in &evaluated {
+            let outdated = self
+                .internal_graph
+                .neighbors_directed(*index, Direction::Outgoing)
+                .filter(|target| !evaluated.contains(target))
+                .collect::<Vec<_>>();
+            cache.dirty.extend(outdated);
+        }
+
+        targets
+            .iter()
+            .map(|target| {
+                let mut plane = Plane::new(size.0, size.1)?;
+                for (index, value) in cache.buffers[target][0].iter().enumerate() {
+                    let x = index as u32 % size.0;
+                    let y = index as u32 / size.0;
+                    plane.put_pixel_unchecked(x, y, value.to_common_ground()?);
+                }
 
-        let nodes_as_tree = self.nodes_as_tree().unwrap();
+                Ok(plane)
+            })
+            .collect()
+    }
+
+    /// Evaluates every output of the graph for a batch of samples, e.g. to run the graph inside of a
+    /// [`Group`](crate::library::group::Group), and returns the values by the channel of the output.
+    ///
+    /// Group inputs (see [`Node::group_input`]) take their values from `inputs`, the nodes tile like `inputs` (see
+    /// [`BatchInputs::tileable`]) no matter the mode of this generator. Nothing is cached and the graph isn't
+    /// validated, problems show up as errors of the evaluation.
+    pub fn evaluate(
+        &self,
+        positions: &[Coordinate],
+        size: &(u32, u32),
+        inputs: &BatchInputs,
+    ) -> Result<HashMap<String, Vec<InputOutputValue>>> {
+        let (channels, targets): (Vec<_>, Vec<_>) = self.outputs().into_iter().unzip();
+        let plan = self.plan(&targets)?;
+        let guards = self.lock(&plan);
+        let buffers = Evaluator::new(&plan, &guards, *size, inputs.tileable()).evaluate_steps(
+            &plan.steps,
+            positions,
+            inputs,
+            &targets,
+        )?;
 
-        for x in 0..size.0 {
-            for y in 0..size.1 {
-                let value =
-                    nodes_as_tree.generate(&Coordinate::new_xy(x as f64, y as f64), &size)?;
+        Ok(channels
+            .into_iter()
+            .zip(targets)
+            .map(|(channel, target)| (channel, buffers[&target][0].to_vec()))
+            .collect())
+    }
+}
 
-                plane.put_pixel_unchecked(x as u32, y as u32, value.to_common_ground()?);
+/// The values of every output of the nodes, by the index of the node.
+type Buffers = HashMap<NodeIndex, Vec<Arc<Vec<InputOutputValue>>>>;
+
+#[derive(Debug)]
+struct EvaluationStep {
+    node: NodeIndex,
+    /// The source node, the index of its output and the name of the input it is connected to.
+    inputs: Vec<(NodeIndex, usize, String)>,
+    /// Like `inputs` but for the [`Node::warped_input`], its source isn't evaluated at the positions of the node.
+    warped: Option<(NodeIndex, usize, String)>,
+    /// Default values of the optional inputs that aren't connected.
+    defaults: Vec<(String, InputOutputValue)>,
+}
+
+/// Every step of an evaluation, planned once before the first sample gets evaluated.
+#[derive(Debug)]
+struct Plan {
+    steps: Vec<EvaluationStep>,
+    /// The steps before the warped input of a node, by the index of the node.
+    warped: HashMap<NodeIndex, Vec<EvaluationStep>>,
+}
+
+impl Plan {
+    /// The node of the step and the source of its warped input, if it has one.
+    fn warp(step: &EvaluationStep) -> Option<(NodeIndex, NodeIndex)> {
+        step.warped
+            .as_ref()
+            .map(|(source, _, _)| (step.node, *source))
+    }
+
+    /// Every node that gets evaluated, each once.
+    fn nodes(&self) -> HashSet<NodeIndex> {
+        self.steps
+            .iter()
+            .chain(self.warped.values().flatten())
+            .map(|step| step.node)
+            .collect()
+    }
+
+    /// The nodes that get evaluated for the warped input of `node`, including the ones of nested warps.
+    fn warped_nodes(&self, node: NodeIndex) -> HashSet<NodeIndex> {
+        let mut nodes = HashSet::new();
+        let mut pending = vec![node];
+        while let Some(index) = pending.pop() {
+            for step in self.warped.get(&index).into_iter().flatten() {
+                if nodes.insert(step.node) {
+                    pending.push(step.node);
+                }
             }
         }
 
-        Ok(plane)
+        nodes
     }
 }
 
-#[derive(Debug, Clone)]
-struct RelationsBetweenNodes {
-    node: Rc<RefCell<dyn Node>>,
-    children: Vec<(RelationsBetweenNodes, String)>,
+/// Evaluates the steps of a [`Plan`] with the nodes that got locked for it.
+struct Evaluator<'a> {
+    plan: &'a Plan,
+    nodes: HashMap<NodeIndex, &'a dyn Node>,
+    size: (u32, u32),
+    tileable: bool,
 }
 
-impl RelationsBetweenNodes {
-    fn new(node: Rc<RefCell<dyn Node>>) -> Self {
-        RelationsBetweenNodes {
-            node,
-            children: Vec::new(),
+impl<'a> Evaluator<'a> {
+    fn new(
+        plan: &'a Plan,
+        guards: &'a HashMap<NodeIndex, RwLockReadGuard<'_, dyn Node>>,
+        size: (u32, u32),
+        tileable: bool,
+    ) -> Self {
+        Evaluator {
+            plan,
+            nodes: guards
+                .iter()
+                .map(|(index, guard)| (*index, &**guard as &dyn Node))
+                .collect(),
+            size,
+            tileable,
+        }
+    }
+
+    /// Evaluates the `steps` for the `positions` without the cache, group inputs take their values from `inputs`.
+    ///
+    /// Only the buffers of the nodes in `keep` are returned, the others are dropped once the last step that reads them
+    /// got evaluated.
+    fn evaluate_steps(
+        &self,
+        steps: &[EvaluationStep],
+        positions: &[Coordinate],
+        inputs: &BatchInputs,
+        keep: &[NodeIndex],
+    ) -> Result<Buffers> {
+        let mut last_reads = HashMap::new();
+        for (index, step) in steps.iter().enumerate() {
+            for (source, _, _) in &step.inputs {
+                last_reads.insert(*source, index);
+            }
+        }
+
+        let mut buffers = HashMap::new();
+        for (index, step) in steps.iter().enumerate() {
+            let node = self.nodes[&step.node];
+            let outputs = match node.group_input().and_then(|port| inputs.get(&port.name)) {
+                Some(value) => vec![Arc::new(
+                    (0..positions.len()).map(|index| value.get(index)).collect(),
+                )],
+                None if node.is_output() => self.forward_output(step, &buffers)?,
+                None => self
+                    .evaluate_step(step, &buffers, positions, 0..positions.len(), inputs)?
+                    .into_iter()
+                    .map(Arc::new)
+                    .collect(),
+            };
+            buffers.insert(step.node, outputs);
+
+            for (source, _, _) in &step.inputs {
+                if last_reads[source] == index && !keep.contains(source) {
+                    buffers.remove(source);
+                }
+            }
         }
+
+        Ok(buffers)
     }
 
-    fn add_children<S: Into<String>>(&mut self, node: RelationsBetweenNodes, name: S) {
-        self.children.push((node, name.into()))
+    /// Renders the `targets` in bands of [`BAND_HEIGHT`] rows without the cache, with the `parallel` feature the bands
+    /// are evaluated on multiple threads.
+    fn render_bands(&self, targets: &[NodeIndex]) -> Result<Vec<Plane>> {
+        let (width, height) = self.size;
+        let render_band = |band: u32| -> Result<Vec<Vec<Pixel>>> {
+            let rows = band * BAND_HEIGHT..((band + 1) * BAND_HEIGHT).min(height);
+            let positions = rows
+                .flat_map(|y| (0..width).map(move |x| Coordinate::new_xy(x as f64, y as f64)))
+                .collect::<Vec<_>>();
+            let buffers =
+                self.evaluate_steps(&self.plan.steps, &positions, &BatchInputs::new(), targets)?;
+
+            targets
+                .iter()
+                .map(|target| {
+                    buffers[target][0]
+                        .iter()
+                        .map(InputOutputValue::to_common_ground)
+                        .collect()
+                })
+                .collect()
+        };
+
+        let bands = height.div_ceil(BAND_HEIGHT);
+        #[cfg(feature = "parallel")]
+        let bands = (0..bands)
+            .into_par_iter()
+            .map(render_band)
+            .collect::<Result<Vec<_>>>()?;
+        #[cfg(not(feature = "parallel"))]
+        let bands = (0..bands).map(render_band).collect::<Result<Vec<_>>>()?;
+
+        let mut planes = Vec::with_capacity(targets.len());
+        for target in 0..targets.len() {
+            let mut plane = Plane::new(width, height)?;
+            for (band, pixels) in bands.iter().enumerate() {
+                for (index, pixel) in pixels[target].iter().enumerate() {
+                    let x = index as u32 % width;
+                    let y = band as u32 * BAND_HEIGHT + index as u32 / width;
+                    plane.put_pixel_unchecked(x, y, *pixel);
+                }
+            }
+            planes.push(plane);
+        }
+
+        Ok(planes)
     }
 
-    fn generate(&self, position: &Coordinate, size: &(u32, u32)) -> Result<InputOutputValue> {
-        let mut children_results = HashMap::new();
+    /// Evaluates every output of the node for every pixel, every node evaluates a whole row at once (see
+    /// [`Node::generate_batch_outputs`]).
+    fn render_node(
+        &self,
+        step: &EvaluationStep,
+        buffers: &Buffers,
+    ) -> Result<Vec<Arc<Vec<InputOutputValue>>>> {
+        if self.nodes[&step.node].is_output() {
+            return self.forward_output(step, buffers);
+        }
+
+        let size = self.size;
+        let render_row = |y: u32| -> Result<Vec<Vec<InputOutputValue>>> {
+            let positions = (0..size.0)
+                .map(|x| Coordinate::new_xy(x as f64, y as f64))
+                .collect::<Vec<_>>();
+            let row = (y * size.0) as usize..((y + 1) * size.0) as usize;
 
-        for (child_node, child_name) in &self.children {
-            let out = child_node.generate(position, size)?;
+            self.evaluate_step(step, buffers, &positions, row, &BatchInputs::new())
+        };
 
-            if self.node.borrow().is_output() {
-                return Ok(out);
+        #[cfg(feature = "parallel")]
+        let rows = (0..size.1)
+            .into_par_iter()
+            .map(render_row)
+            .collect::<Result<Vec<_>>>()?;
+        #[cfg(not(feature = "parallel"))]
+        let rows = (0..size.1).map(render_row).collect::<Result<Vec<_>>>()?;
+
+        let mut outputs: Vec<Vec<InputOutputValue>> = Vec::new();
+        for row in rows {
+            for (index, values) in row.into_iter().enumerate() {
+                if outputs.len() <= index {
+                    outputs.push(Vec::with_capacity((size.0 * size.1) as usize));
+                }
+                outputs[index].extend(values);
             }
+        }
 
-            children_results.insert(child_name.clone(), out);
+        Ok(outputs.into_iter().map(Arc::new).collect())
+    }
+
+    /// Output nodes pass the buffer of their source on without evaluating anything.
+    fn forward_output(
+        &self,
+        step: &EvaluationStep,
+        buffers: &Buffers,
+    ) -> Result<Vec<Arc<Vec<InputOutputValue>>>> {
+        match step.inputs.first() {
+            Some((source, output, _)) => Ok(vec![buffers[source][*output].clone()]),
+            None => Err(GeneratorError::MissingInput {
+                node: self.nodes[&step.node].space_info().name.clone(),
+                port: DEFAULT_INPUT.to_string(),
+            }),
+        }
+    }
+
+    /// Evaluates the node for the `positions`, the values of its inputs are the `samples` of the buffers of the
+    /// connected nodes.
+    ///
+    /// The nodes before the [`Node::warped_input`] get evaluated again at the positions returned by [`Node::warp`],
+    /// group inputs among them take their values from `group_inputs` which must hold the values of the `positions`.
+    fn evaluate_step(
+        &self,
+        step: &EvaluationStep,
+        buffers: &Buffers,
+        positions: &[Coordinate],
+        samples: Range<usize>,
+        group_inputs: &BatchInputs,
+    ) -> Result<Vec<Vec<InputOutputValue>>> {
+        let node = self.nodes[&step.node];
+        let warped;
+        let mut inputs = BatchInputs::new();
+        inputs.set_tileable(self.tileable);
+        for (source, output, name) in &step.inputs {
+            inputs.insert(
+                name,
+                BatchValue::Samples(&buffers[source][*output][samples.clone()]),
+            );
+        }
+        for (name, default) in &step.defaults {
+            inputs.insert(name, BatchValue::Constant(*default));
         }
 
-        self.node
-            .borrow()
-            .generate(position, size, children_results)
+        if let Some((source, output, name)) = &step.warped {
+            let warped_positions = node.warp(positions, &self.size, &inputs)?;
+            let mut buffers = self.evaluate_steps(
+                &self.plan.warped[&step.node],
+                &warped_positions,
+                group_inputs,
+                &[*source],
+            )?;
+            warped = buffers.remove(source).unwrap().swap_remove(*output);
+            inputs.insert(name, BatchValue::Samples(&warped));
+        }
+
+        node.generate_batch_outputs(positions, &self.size, &inputs)
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
     use rusvid_core::pixel::Pixel;
 
     use super::Generator;
     use crate::coordinate::Coordinate;
-    use crate::input_output_value::InputOutputValue;
+    use crate::diagnostic::{Diagnostic, DiagnosticKind, Severity};
+    use crate::error::{GeneratorError, Result};
+    use crate::input_output_value::{InputOutputKind, InputOutputValue};
+    use crate::library::map::Map;
     use crate::library::mix::Mix;
     use crate::library::noise::Noise;
+    use crate::library::pattern::Pattern;
+    use crate::library::split::Split;
     use crate::library::static_value::StaticValue;
+    use crate::library::warp::{Warp, OFFSET_INPUT};
     use crate::link::Link;
+    use crate::node::{BatchInputs, Node, SpaceInfo};
+    use crate::parameter::ParameterValue;
+    use crate::port::Sockets;
+
+    #[derive(Debug)]
+    struct CountingNode {
+        calls: Arc<AtomicUsize>,
+
+        space_info: SpaceInfo,
+    }
+
+    impl CountingNode {
+        fn new(calls: Arc<AtomicUsize>) -> Self {
+            CountingNode {
+                calls,
+
+                space_info: SpaceInfo::default(),
+            }
+        }
+    }
+
+    impl Node for CountingNode {
+        fn node_type(&self) -> &'static str {
+            "test.counting"
+        }
+
+        fn generate(
+            &self,
+            _position: &Coordinate,
+            _size: &(u32, u32),
+            _input: HashMap<String, InputOutputValue>,
+        ) -> Result<InputOutputValue> {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+
+            Ok(InputOutputValue::Float(0.5))
+        }
+
+        fn space_info(&self) -> &SpaceInfo {
+            &self.space_info
+        }
+
+        fn space_info_mut(&mut self) -> &mut SpaceInfo {
+            &mut self.space_info
+        }
+    }
+
+    #[test]
+    fn shared_nodes_are_evaluated_once_per_sample() {
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        let mut g = Generator::new();
+
+        let node_shared = g.add_node(CountingNode::new(calls.clone()));
+        let node_black = g.add_node(StaticValue::new(InputOutputValue::Pixel(Pixel::new(
+            0, 0, 0, 255,
+        ))));
+        let node_white = g.add_node(StaticValue::new(InputOutputValue::Pixel(Pixel::new(
+            255, 255, 255, 255,
+        ))));
+        let node_left = g.add_node(Mix::new());
+        let node_right = g.add_node(Mix::new());
+        let node_mix = g.add_node(Mix::new());
+        let node_output = g.output_node();
+
+        g.add_edge_named(Link::new(node_shared, node_left).unwrap(), "value")
+            .unwrap();
+        g.add_edge_named(Link::new(node_black, node_left).unwrap(), "input1")
+            .unwrap();
+        g.add_edge_named(Link::new(node_white, node_left).unwrap(), "input2")
+            .unwrap();
+
+        g.add_edge_named(Link::new(node_shared, node_right).unwrap(), "value")
+            .unwrap();
+        g.add_edge_named(Link::new(node_white, node_right).unwrap(), "input1")
+            .unwrap();
+        g.add_edge_named(Link::new(node_white, node_right).unwrap(), "input2")
+            .unwrap();
+
+        g.add_edge_named(Link::new(node_shared, node_mix).unwrap(), "value")
+            .unwrap();
+        g.add_edge_named(Link::new(node_left, node_mix).unwrap(), "input1")
+            .unwrap();
+        g.add_edge_named(Link::new(node_right, node_mix).unwrap(), "input2")
+            .unwrap();
+        g.add_edge(Link::new(node_mix, node_output).unwrap())
+            .unwrap();
+
+        let plane = g.generate(4, 3).unwrap();
+
+        assert_eq!(calls.load(Ordering::Relaxed), 4 * 3);
+        assert_eq!(
+            plane.as_data_flatten()[0..4],
+            [191, 191, 191, 255],
+            "every edge of the diamond must contribute to the output"
+        );
+    }
+
+    #[test]
+    fn nodes_before_a_warped_input_are_only_evaluated_at_the_warped_positions() {
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        let mut g = Generator::new();
+        g.set_caching(true);
+        let counting = g.add_node(CountingNode::new(calls.clone()));
+        let offset = g.add_node(StaticValue::new(InputOutputValue::Float(0.5)));
+        let warp = g.add_node(Warp::new(1.0).unwrap());
+        g.add_edge(Link::new(counting, warp).unwrap()).unwrap();
+        g.add_edge_named(Link::new(offset, warp).unwrap(), OFFSET_INPUT)
+            .unwrap();
+        g.add_edge(Link::new(warp, g.output_node()).unwrap())
+            .unwrap();
+
+        g.generate(4, 3).unwrap();
+        assert_eq!(calls.load(Ordering::Relaxed), 4 * 3);
+
+        g.generate(4, 3).unwrap();
+        assert_eq!(calls.load(Ordering::Relaxed), 4 * 3);
+
+        g.mark_dirty(counting);
+        g.generate(4, 3).unwrap();
+        assert_eq!(calls.load(Ordering::Relaxed), 2 * 4 * 3);
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallel_rendering_matches_a_single_thread() {
+        let mut g = Generator::new();
+
+        let noise = g.add_node({
+            let mut noise = Noise::new(3);
+            noise.set_scale(Coordinate::new_xy(7.0, 7.0));
+            noise
+        });
+        let input1 = g.add_node(StaticValue::new(InputOutputValue::Pixel(Pixel::new(
+            255, 0, 100, 255,
+        ))));
+        let input2 = g.add_node(StaticValue::new(InputOutputValue::Pixel(Pixel::new(
+            0, 255, 150, 255,
+        ))));
+        let mix = g.add_node(Mix::new());
+        g.add_edge_named(Link::new(noise, mix).unwrap(), "value")
+            .unwrap();
+        g.add_edge_named(Link::new(input1, mix).unwrap(), "input1")
+            .unwrap();
+        g.add_edge_named(Link::new(input2, mix).unwrap(), "input2")
+            .unwrap();
+        g.add_edge(Link::new(mix, g.output_node()).unwrap())
+            .unwrap();
+
+        let single_thread = rayon::ThreadPoolBuilder::new()
+            .num_threads(1)
+            .build()
+            .unwrap()
+            .install(|| g.generate(64, 48).unwrap());
+        let multi_thread = rayon::ThreadPoolBuilder::new()
+            .num_threads(4)
+            .build()
+            .unwrap()
+            .install(|| g.generate(64, 48).unwrap());
+
+        assert_eq!(
+            single_thread.as_data_flatten(),
+            multi_thread.as_data_flatten()
+        );
+    }
+
+    #[test]
+    fn rendering_in_bands_matches_the_cache() {
+        let mut g = Generator::new();
+        let noise = g.add_node({
+            let mut noise = Noise::new(3);
+            noise.set_scale(Coordinate::new_xy(7.0, 7.0));
+            noise
+        });
+        let offset = g.add_node(Noise::new(4));
+        let warp = g.add_node(Warp::new(2.0).unwrap());
+        g.add_edge(Link::new(noise, warp).unwrap()).unwrap();
+        g.add_edge_named(Link::new(offset, warp).unwrap(), OFFSET_INPUT)
+            .unwrap();
+        g.add_edge(Link::new(warp, g.output_node()).unwrap())
+            .unwrap();
+
+        // The height isn't a multiple of the bands.
+        let streamed = g.generate(20, 37).unwrap();
+        g.set_caching(true);
+        let cached = g.generate(20, 37).unwrap();
+
+        assert_eq!(streamed.as_data_flatten(), cached.as_data_flatten());
+    }
+
+    #[test]
+    fn unchanged_nodes_are_not_rendered_again() {
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        let mut g = Generator::new();
+        g.set_caching(true);
+        let counting = g.add_node(CountingNode::new(calls.clone()));
+        let map = g.add_node(
+            Map::new(vec![
+                (InputOutputValue::Pixel(Pixel::new(0, 0, 0, 255)), 0.0),
+                (InputOutputValue::Pixel(Pixel::new(255, 255, 255, 255)), 1.0),
+            ])
+            .unwrap(),
+        );
+        g.add_edge(Link::new(counting, map).unwrap()).unwrap();
+        g.add_edge(Link::new(map, g.output_node()).unwrap())
+            .unwrap();
+
+        let first = g.generate(4, 3).unwrap();
+        assert_eq!(calls.load(Ordering::Relaxed), 4 * 3);
+
+        g.generate(4, 3).unwrap();
+        assert_eq!(calls.load(Ordering::Relaxed), 4 * 3);
+
+        g.set_parameter(
+            map,
+            "steps",
+            ParameterValue::Gradient(vec![
+                (InputOutputValue::Pixel(Pixel::new(255, 255, 255, 255)), 0.0),
+                (InputOutputValue::Pixel(Pixel::new(0, 0, 0, 255)), 1.0),
+            ]),
+        )
+        .unwrap();
+        let inverted = g.generate(4, 3).unwrap();
+        assert_eq!(calls.load(Ordering::Relaxed), 4 * 3);
+        assert_ne!(first.as_data_flatten(), inverted.as_data_flatten());
+
+        g.mark_dirty(counting);
+        g.generate(4, 3).unwrap();
+        assert_eq!(calls.load(Ordering::Relaxed), 2 * 4 * 3);
+
+        g.generate(5, 3).unwrap();
+        assert_eq!(calls.load(Ordering::Relaxed), 2 * 4 * 3 + 5 * 3);
+    }
+
+    #[test]
+    fn any_node_can_be_previewed() {
+        let mut g = Generator::new();
+
+        let color = g.add_node(StaticValue::new(InputOutputValue::Pixel(Pixel::new(
+            255, 0, 100, 255,
+        ))));
+        let mix = g.add_node(Mix::new());
+        g.add_edge_named(Link::new(color, mix).unwrap(), "input1")
+            .unwrap();
+
+        // Neither the output nor `mix` are complete, but `color` doesn't depend on them.
+        assert!(g.generate(2, 2).is_err());
+        assert!(g.generate_from(mix, 2, 2).is_err());
+
+        let preview = g.generate_from(color, 2, 2).unwrap();
+        assert_eq!(preview.as_data_flatten(), [255, 0, 100, 255].repeat(4));
+    }
+
+    #[test]
+    fn outputs_share_their_upstream_nodes() {
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        let mut g = Generator::new();
+        let counting = g.add_node(CountingNode::new(calls.clone()));
+        let roughness = g.add_output("roughness").unwrap();
+        g.add_edge(Link::new(counting, g.output_node()).unwrap())
+            .unwrap();
+        g.add_edge(Link::new(counting, roughness).unwrap()).unwrap();
+
+        assert!(matches!(
+            g.add_output("roughness"),
+            Err(GeneratorError::DuplicateChannel(_))
+        ));
+        assert_eq!(g.output("roughness"), Some(roughness));
+        assert!(matches!(
+            g.set_parameter(
+                roughness,
+                "channel",
+                ParameterValue::Text("output".to_string())
+            ),
+            Err(GeneratorError::DuplicateChannel(_))
+        ));
+        assert_eq!(g.output("roughness"), Some(roughness));
+
+        let planes = g.generate_all(4, 3).unwrap();
+
+        assert_eq!(calls.load(Ordering::Relaxed), 4 * 3);
+        assert_eq!(planes.len(), 2);
+        assert_eq!(
+            planes["output"].as_data_flatten(),
+            planes["roughness"].as_data_flatten()
+        );
+    }
+
+    #[test]
+    fn edges_connect_specific_outputs() {
+        let mut g = Generator::new();
+
+        let color = g.add_node(StaticValue::new(InputOutputValue::Pixel(Pixel::new(
+            255, 0, 100, 255,
+        ))));
+        let split = g.add_node(Split::new());
+        g.add_edge(Link::new(color, split).unwrap()).unwrap();
+
+        assert!(matches!(
+            g.add_edge_sockets(Link::new(split, g.output_node()).unwrap(), "x", "input"),
+            Err(GeneratorError::UnknownPort { port, .. }) if port == "x"
+        ));
+
+        g.add_edge_sockets(Link::new(split, g.output_node()).unwrap(), "g", "input")
+            .unwrap();
+        let plane = g.generate(2, 2).unwrap();
+        assert_eq!(plane.as_data_flatten(), [0, 0, 0, 255].repeat(4));
+
+        g.add_edge_sockets(Link::new(split, g.output_node()).unwrap(), "r", "input")
+            .unwrap();
+        let plane = g.generate(2, 2).unwrap();
+        assert_eq!(plane.as_data_flatten(), [255, 255, 255, 255].repeat(4));
+
+        let edges = g.edges_of(g.output_node());
+        assert_eq!(edges.len(), 1);
+        assert_eq!(edges[0].2, Sockets::new("r", "input"));
+    }
+
+    #[test]
+    fn cycles_are_reported_as_error() {
+        let mut g = Generator::new();
+
+        let node_first = g.add_node(Mix::new());
+        let node_second = g.add_node(Mix::new());
+        let node_output = g.output_node();
+
+        g.add_edge_named(Link::new(node_first, node_second).unwrap(), "value")
+            .unwrap();
+        g.add_edge_named(Link::new(node_second, node_first).unwrap(), "value")
+            .unwrap();
+        g.add_edge(Link::new(node_second, node_output).unwrap())
+            .unwrap();
+
+        assert!(matches!(
+            g.generate(2, 2),
+            Err(GeneratorError::InvalidGraph(diagnostics))
+                if diagnostics.iter().any(|d| d.kind == DiagnosticKind::Cycle)
+        ));
+    }
+
+    #[test]
+    fn cycles_only_matter_for_the_nodes_that_depend_on_them() {
+        let mut g = Generator::new();
+
+        let color = g.add_node(StaticValue::new(InputOutputValue::Float(0.5)));
+        let node_first = g.add_node(Mix::new());
+        let node_second = g.add_node(Mix::new());
+        g.add_edge_named(Link::new(node_first, node_second).unwrap(), "value")
+            .unwrap();
+        g.add_edge_named(Link::new(node_second, node_first).unwrap(), "value")
+            .unwrap();
+        g.add_edge(Link::new(color, g.output_node()).unwrap())
+            .unwrap();
+
+        g.generate(2, 2).unwrap();
+        let values = g
+            .evaluate(
+                &[Coordinate::new_xy(0.0, 0.0)],
+                &(1, 1),
+                &BatchInputs::new(),
+            )
+            .unwrap();
+        assert_eq!(values["output"], [InputOutputValue::Float(0.5)]);
+
+        g.add_edge_named(Link::new(node_second, g.output_node()).unwrap(), "input")
+            .unwrap();
+        assert!(matches!(
+            g.evaluate(
+                &[Coordinate::new_xy(0.0, 0.0)],
+                &(1, 1),
+                &BatchInputs::new()
+            ),
+            Err(GeneratorError::CycleDetected { .. })
+        ));
+    }
 
     #[test]
     fn a_node_can_have_more_than_one_inputs() {
@@ -215,10 +1725,14 @@ mod tests {
         ))));
         let node_output = g.output_node();
 
-        g.add_edge_named(Link::new(node_noise, node_mix), "value");
-        g.add_edge_named(Link::new(node_input1, node_mix), "input1");
-        g.add_edge_named(Link::new(node_input2, node_mix), "input2");
-        g.add_edge(Link::new(node_mix, node_output));
+        g.add_edge_named(Link::new(node_noise, node_mix).unwrap(), "value")
+            .unwrap();
+        g.add_edge_named(Link::new(node_input1, node_mix).unwrap(), "input1")
+            .unwrap();
+        g.add_edge_named(Link::new(node_input2, node_mix).unwrap(), "input2")
+            .unwrap();
+        g.add_edge(Link::new(node_mix, node_output).unwrap())
+            .unwrap();
 
         let plane = g.generate(10, 10).unwrap();
 
@@ -252,4 +1766,200 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn edges_to_unknown_inputs_are_rejected() {
+        let mut g = Generator::new();
+
+        let node_mix = g.add_node(Mix::new());
+        let node_value = g.add_node(StaticValue::new(InputOutputValue::Float(0.5)));
+
+        assert!(matches!(
+            g.add_edge_named(Link::new(node_value, node_mix).unwrap(), "factor"),
+            Err(GeneratorError::UnknownPort { port, .. }) if port == "factor"
+        ));
+        assert!(g
+            .add_edge_named(Link::new(node_value, node_mix).unwrap(), "value")
+            .is_ok());
+        assert!(g
+            .add_edge(Link::new(g.output_node(), node_mix).unwrap())
+            .is_ok());
+        assert!(g
+            .add_edge(Link::new(node_mix, node_value).unwrap())
+            .is_err());
+    }
+
+    #[test]
+    fn unconnected_optional_inputs_use_their_default() {
+        let mut g = Generator::new();
+
+        let node_mix = g.add_node(Mix::new());
+        let node_black = g.add_node(StaticValue::new(InputOutputValue::Pixel(Pixel::new(
+            0, 0, 0, 255,
+        ))));
+        let node_white = g.add_node(StaticValue::new(InputOutputValue::Pixel(Pixel::new(
+            255, 255, 255, 255,
+        ))));
+        let node_output = g.output_node();
+
+        g.add_edge_named(Link::new(node_black, node_mix).unwrap(), "input1")
+            .unwrap();
+        g.add_edge_named(Link::new(node_white, node_mix).unwrap(), "input2")
+            .unwrap();
+        g.add_edge(Link::new(node_mix, node_output).unwrap())
+            .unwrap();
+
+        let plane = g.generate(1, 1).unwrap();
+
+        assert_eq!(plane.as_data_flatten(), vec![127, 127, 127, 255]);
+    }
+
+    #[test]
+    fn validate_reports_problems_of_the_graph() {
+        let mut g = Generator::new();
+
+        let node_mix = g.add_node(Mix::new());
+        let node_color = g.add_node(StaticValue::new(InputOutputValue::Pixel(Pixel::new(
+            255, 0, 100, 255,
+        ))));
+        let node_unused = g.add_node(Noise::new(1));
+        let node_output = g.output_node();
+
+        g.add_edge_named(Link::new(node_color, node_mix).unwrap(), "value")
+            .unwrap();
+        g.add_edge_named(Link::new(node_color, node_mix).unwrap(), "input1")
+            .unwrap();
+        g.add_edge(Link::new(node_mix, node_output).unwrap())
+            .unwrap();
+
+        let diagnostics = g.validate();
+
+        assert_eq!(diagnostics.len(), 3);
+        assert!(diagnostics.contains(&Diagnostic {
+            kind: DiagnosticKind::TypeMismatch {
+                expected: vec![
+                    InputOutputKind::Float,
+                    InputOutputKind::F64X3Array,
+                    InputOutputKind::F64X4Array
+                ],
+                found: vec![InputOutputKind::Pixel],
+            },
+            node: node_mix,
+            node_name: g.internal_graph[node_mix]
+                .read()
+                .unwrap()
+                .space_info()
+                .name
+                .clone(),
+            port: Some("value".to_string()),
+        }));
+        assert!(diagnostics.iter().any(|d| d.node == node_mix
+            && d.kind == DiagnosticKind::MissingInput
+            && d.port.as_deref() == Some("input2")));
+        assert!(diagnostics.iter().any(|d| d.node == node_unused
+            && d.kind == DiagnosticKind::UnreachableNode
+            && d.severity() == Severity::Warning));
+
+        assert!(matches!(
+            g.generate(2, 2),
+            Err(GeneratorError::InvalidGraph(errors)) if errors.len() == 2
+        ));
+    }
+
+    #[test]
+    fn unreachable_nodes_dont_prevent_rendering() {
+        let mut g = Generator::new();
+
+        let node_color = g.add_node(StaticValue::new(InputOutputValue::Float(1.0)));
+        g.add_node(Mix::new());
+        g.add_edge(Link::new(node_color, g.output_node()).unwrap())
+            .unwrap();
+
+        assert!(g.validate().iter().all(|d| !d.is_error()));
+        assert!(g.generate(2, 2).is_ok());
+    }
+
+    #[test]
+    fn node_indices_stay_valid_after_removing_nodes() {
+        let mut g = Generator::new();
+
+        let node_removed = g.add_node(StaticValue::new(InputOutputValue::Float(0.0)));
+        let node_color = g.add_node(StaticValue::new(InputOutputValue::Float(1.0)));
+        g.add_edge(Link::new(node_removed, g.output_node()).unwrap())
+            .unwrap();
+
+        g.remove_node(node_removed).unwrap();
+
+        assert!(matches!(
+            g.remove_node(node_removed),
+            Err(GeneratorError::UnknownNode(index)) if index == node_removed
+        ));
+        assert!(matches!(
+            g.remove_node(g.output_node()),
+            Err(GeneratorError::OutputNodeRequired)
+        ));
+        assert!(g.edges_of(g.output_node()).is_empty());
+
+        g.add_edge(Link::new(node_color, g.output_node()).unwrap())
+            .unwrap();
+        let plane = g.generate(1, 1).unwrap();
+
+        assert_eq!(plane.as_data_flatten(), vec![255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn connecting_an_input_twice_replaces_the_edge() {
+        let mut g = Generator::new();
+
+        let node_mix = g.add_node(Mix::new());
+        let node_first = g.add_node(StaticValue::new(InputOutputValue::Float(0.0)));
+        let node_second = g.add_node(StaticValue::new(InputOutputValue::Float(1.0)));
+
+        g.add_edge_named(Link::new(node_first, node_mix).unwrap(), "value")
+            .unwrap();
+        let edge = g
+            .add_edge_named(Link::new(node_second, node_mix).unwrap(), "value")
+            .unwrap();
+
+        let edges = g.edges_of(node_mix);
+        assert_eq!(edges.len(), 1);
+        assert_eq!(edges[0].0, edge);
+        assert_eq!(edges[0].1.input_node(), node_second);
+        assert_eq!(edges[0].2.input, "value");
+
+        assert_eq!(
+            g.disconnect_port(node_mix, "value").unwrap(),
+            vec![node_second]
+        );
+        assert!(g.edges_of(node_mix).is_empty());
+        assert!(matches!(
+            g.remove_edge(edge),
+            Err(GeneratorError::UnknownEdge(_))
+        ));
+    }
+
+    #[test]
+    fn replacing_a_node_drops_edges_to_unknown_inputs() {
+        let mut g = Generator::new();
+
+        let node_value = g.add_node(StaticValue::new(InputOutputValue::Float(0.5)));
+        let node_color = g.add_node(StaticValue::new(InputOutputValue::Float(1.0)));
+        let node_replaced = g.add_node(Mix::new());
+        g.add_edge_named(Link::new(node_value, node_replaced).unwrap(), "value")
+            .unwrap();
+        g.add_edge_named(Link::new(node_color, node_replaced).unwrap(), "input1")
+            .unwrap();
+        g.add_edge(Link::new(node_replaced, g.output_node()).unwrap())
+            .unwrap();
+
+        g.replace_node(node_replaced, Pattern::new()).unwrap();
+
+        let edges = g.edges_of(node_replaced);
+        assert_eq!(edges.len(), 1);
+        assert_eq!(edges[0].1.output_node(), g.output_node());
+
+        g.add_edge(Link::new(node_color, node_replaced).unwrap())
+            .unwrap();
+        assert!(g.generate(2, 2).is_ok());
+    }
 }
@@ -0,0 +1,88 @@
+use std::sync::{Arc, RwLock};
+
+use crate::generator::Generator;
+
+/// A named graph that can be used as a node, see [`Group`](crate::library::group::Group).
+#[derive(Debug)]
+pub struct GroupDefinition {
+    pub name: String,
+    /// The [`GroupInput`](crate::library::group::GroupInput) nodes of the graph are the inputs of the group, the output
+    /// nodes are its outputs.
+    pub generator: Generator,
+}
+
+impl GroupDefinition {
+    pub fn new<S: Into<String>>(name: S, generator: Generator) -> Self {
+        GroupDefinition {
+            name: name.into(),
+            generator,
+        }
+    }
+
+    pub fn shared(self) -> SharedGroup {
+        Arc::new(RwLock::new(self))
+    }
+}
+
+/// A definition that is shared by every instance of the group, changing it changes every instance.
+///
+/// A generator renders the instances again once the graph of their definition changed, see [`group_revision`].
+pub type SharedGroup = Arc<RwLock<GroupDefinition>>;
+
+/// The latest [`Generator::revision`] of the graph of `group` and of the groups inside of it, changes whenever one of
+/// these graphs changes.
+pub(crate) fn group_revision(group: &SharedGroup) -> u64 {
+    fn visit(group: &SharedGroup, visited: &mut Vec<SharedGroup>) -> u64 {
+        visited.push(group.clone());
+        let definition = group.read().unwrap();
+        let graph = &definition.generator.internal_graph;
+
+        graph
+            .node_indices()
+            .filter_map(|index| {
+                let node = graph[index].read().unwrap();
+                match node.group() {
+                    Some(inner) if visited.iter().any(|other| Arc::ptr_eq(other, inner)) => None,
+                    Some(inner) => Some(visit(inner, visited)),
+                    None => None,
+                }
+            })
+            .fold(definition.generator.revision(), u64::max)
+    }
+
+    visit(group, &mut Vec::new())
+}
+
+/// Whether the graph of `group` contains an instance of a group that is already being evaluated, i.e. of `group`
+/// itself or of a group around it, directly or inside of other groups. Such a group can't be evaluated, see
+/// [`DiagnosticKind::RecursiveGroup`](crate::diagnostic::DiagnosticKind::RecursiveGroup).
+///
+/// Every definition is locked at most once, the check doesn't lock a definition again while it is locked.
+pub(crate) fn is_recursive(group: &SharedGroup) -> bool {
+    /// `path` holds the definitions that are locked, `checked` the ones without recursion.
+    fn visit(
+        group: &SharedGroup,
+        path: &mut Vec<SharedGroup>,
+        checked: &mut Vec<SharedGroup>,
+    ) -> bool {
+        path.push(group.clone());
+        let definition = group.read().unwrap();
+        let graph = &definition.generator.internal_graph;
+
+        let recursive = graph.node_indices().any(|index| {
+            let node = graph[index].read().unwrap();
+            match node.group() {
+                Some(inner) if path.iter().any(|other| Arc::ptr_eq(other, inner)) => true,
+                Some(inner) if checked.iter().any(|other| Arc::ptr_eq(other, inner)) => false,
+                Some(inner) => visit(inner, path, checked),
+                None => false,
+            }
+        });
+
+        path.pop();
+        checked.push(group.clone());
+        recursive
+    }
+
+    visit(group, &mut Vec::new(), &mut Vec::new())
+}
@@ -0,0 +1,579 @@
+use petgraph::stable_graph::{EdgeIndex, NodeIndex};
+
+use crate::error::{GeneratorError, Result};
+use crate::generator::Generator;
+use crate::link::Link;
+use crate::node::SharedNode;
+use crate::parameter::ParameterValue;
+use crate::port::Sockets;
+
+/// A reversible edit of a [`Generator`], holds everything that is needed to apply the edit again after it got undone.
+#[derive(Debug)]
+enum Command {
+    MoveNode {
+        node: NodeIndex,
+        from: (i64, i64),
+        to: (i64, i64),
+    },
+    AddNode {
+        node: NodeIndex,
+        shared: SharedNode,
+    },
+    RemoveNode {
+        node: NodeIndex,
+        shared: SharedNode,
+        edges: Vec<(Link, Sockets)>,
+    },
+    Connect {
+        link: Link,
+        sockets: Sockets,
+        /// The edge that was connected to the input before.
+        replaced: Option<(Link, Sockets)>,
+    },
+    Disconnect {
+        link: Link,
+        sockets: Sockets,
+    },
+    SetParameter {
+        node: NodeIndex,
+        name: String,
+        old: ParameterValue,
+        new: ParameterValue,
+        /// The edges to ports that the node didn't have anymore with the new value.
+        removed: Vec<(Link, Sockets)>,
+    },
+}
+
+impl Command {
+    /// Either the whole edit gets applied or, if it fails, none of it. The same goes for [`Command::revert`].
+    fn apply(&self, generator: &mut Generator) -> Result<()> {
+        match self {
+            Command::MoveNode { node, to, .. } => {
+                generator.move_node(*node, *to)?;
+            }
+            Command::AddNode { node, shared } => restore_node(generator, *node, shared)?,
+            Command::RemoveNode { node, .. } => {
+                generator.remove_node(*node)?;
+            }
+            Command::Connect { link, sockets, .. } => {
+                generator.connect(link.clone(), sockets.clone())?;
+            }
+            Command::Disconnect { link, sockets } => {
+                let edge = find_edge(generator, link, sockets)?;
+                generator.remove_edge(edge)?;
+            }
+            Command::SetParameter {
+                node, name, new, ..
+            } => {
+                generator.set_parameter(*node, name, new.clone())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn revert(&self, generator: &mut Generator) -> Result<()> {
+        match self {
+            Command::MoveNode { node, from, .. } => {
+                generator.move_node(*node, *from)?;
+            }
+            Command::AddNode { node, .. } => {
+                generator.remove_node(*node)?;
+            }
+            Command::RemoveNode {
+                node,
+                shared,
+                edges,
+            } => {
+                restore_node(generator, *node, shared)?;
+                if let Err(err) = connect_all(generator, edges) {
+                    generator.remove_node(*node)?;
+                    return Err(err);
+                }
+            }
+            Command::Connect {
+                link,
+                sockets,
+                replaced,
+            } => {
+                let edge = find_edge(generator, link, sockets)?;
+                generator.remove_edge(edge)?;
+                if let Some((replaced_link, replaced_sockets)) = replaced {
+                    if let Err(err) =
+                        generator.connect(replaced_link.clone(), replaced_sockets.clone())
+                    {
+                        generator.connect(link.clone(), sockets.clone())?;
+                        return Err(err);
+                    }
+                }
+            }
+            Command::Disconnect { link, sockets } => {
+                generator.connect(link.clone(), sockets.clone())?;
+            }
+            Command::SetParameter {
+                node,
+                name,
+                old,
+                new,
+                removed,
+            } => {
+                generator.set_parameter(*node, name, old.clone())?;
+                if let Err(err) = connect_all(generator, removed) {
+                    generator.set_parameter(*node, name, new.clone())?;
+                    return Err(err);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Adds a removed node again, it must get its old index back so that the other commands stay valid.
+///
+/// The indices of removed nodes are reused in reverse order, as long as the commands are undone and redone in order
+/// the node gets the same index.
+fn restore_node(generator: &mut Generator, node: NodeIndex, shared: &SharedNode) -> Result<()> {
+    let index = generator.add_shared_node(shared.clone());
+    if index != node {
+        generator.remove_node(index)?;
+        return Err(GeneratorError::HistoryOutOfSync);
+    }
+
+    Ok(())
+}
+
+/// Connects every edge or, if one of them fails, none of them.
+fn connect_all(generator: &mut Generator, edges: &[(Link, Sockets)]) -> Result<()> {
+    let mut connected = Vec::new();
+    for (link, sockets) in edges {
+        match generator.connect(link.clone(), sockets.clone()) {
+            Ok(edge) => connected.push(edge),
+            Err(err) => {
+                for edge in connected {
+                    generator.remove_edge(edge)?;
+                }
+
+                return Err(err);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn find_edge(generator: &Generator, link: &Link, sockets: &Sockets) -> Result<EdgeIndex> {
+    generator
+        .edges_of(link.output_node())
+        .into_iter()
+        .find(|(_, edge_link, edge_sockets)| edge_link == link && edge_sockets == sockets)
+        .map(|(edge, _, _)| edge)
+        .ok_or(GeneratorError::HistoryOutOfSync)
+}
+
+/// Records the edits of a [`Generator`] so that they can be undone and redone.
+///
+/// Every edit goes through one of the methods of the history instead of the method of the generator with the same
+/// name. Edits that fail are not recorded.
+#[derive(Debug, Default)]
+pub struct History {
+    undo: Vec<Command>,
+    redo: Vec<Command>,
+    /// Whether the next move of the node of the last command continues the same drag, see [`History::finish_move`].
+    moving: bool,
+}
+
+impl History {
+    pub const fn new() -> Self {
+        History {
+            undo: Vec::new(),
+            redo: Vec::new(),
+            moving: false,
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Reverts the last edit, returns `false` if there is nothing to undo.
+    ///
+    /// An edit that can't be reverted, e.g. because the graph was changed outside of the history, stays the last edit.
+    pub fn undo(&mut self, generator: &mut Generator) -> Result<bool> {
+        self.moving = false;
+
+        let command = match self.undo.pop() {
+            Some(command) => command,
+            None => return Ok(false),
+        };
+        if let Err(err) = command.revert(generator) {
+            self.undo.push(command);
+            return Err(err);
+        }
+        self.redo.push(command);
+
+        Ok(true)
+    }
+
+    /// Applies the last undone edit again, returns `false` if there is nothing to redo.
+    ///
+    /// Like [`History::undo`], an edit that can't be applied stays the next one to redo.
+    pub fn redo(&mut self, generator: &mut Generator) -> Result<bool> {
+        self.moving = false;
+
+        let command = match self.redo.pop() {
+            Some(command) => command,
+            None => return Ok(false),
+        };
+        if let Err(err) = command.apply(generator) {
+            self.redo.push(command);
+            return Err(err);
+        }
+        self.undo.push(command);
+
+        Ok(true)
+    }
+
+    fn record(&mut self, command: Command) {
+        self.moving = matches!(command, Command::MoveNode { .. });
+        self.undo.push(command);
+        self.redo.clear();
+    }
+
+    /// Moves the node, consecutive moves of the same node are undone as a single edit until
+    /// [`History::finish_move`] gets called, e.g. at the end of a drag.
+    pub fn move_node(
+        &mut self,
+        generator: &mut Generator,
+        node: NodeIndex,
+        position: (i64, i64),
+    ) -> Result<()> {
+        let from = generator.move_node(node, position)?;
+
+        if self.moving {
+            if let Some(Command::MoveNode {
+                node: last_node,
+                to,
+                ..
+            }) = self.undo.last_mut()
+            {
+                if *last_node == node {
+                    *to = position;
+                    self.redo.clear();
+
+                    return Ok(());
+                }
+            }
+        }
+
+        self.record(Command::MoveNode {
+            node,
+            from,
+            to: position,
+        });
+
+        Ok(())
+    }
+
+    /// Ends the current drag, the next move becomes a separate edit.
+    pub fn finish_move(&mut self) {
+        self.moving = false;
+    }
+
+    pub fn add_node(&mut self, generator: &mut Generator, shared: SharedNode) -> NodeIndex {
+        let node = generator.add_shared_node(shared.clone());
+        self.record(Command::AddNode { node, shared });
+
+        node
+    }
+
+    pub fn remove_node(&mut self, generator: &mut Generator, node: NodeIndex) -> Result<()> {
+        let edges = generator
+            .edges_of(node)
+            .into_iter()
+            .map(|(_, link, sockets)| (link, sockets))
+            .collect();
+        let shared = generator.remove_node(node)?;
+        self.record(Command::RemoveNode {
+            node,
+            shared,
+            edges,
+        });
+
+        Ok(())
+    }
+
+    /// Connects the output `output` of the source node with the input `input` of the target node, see
+    /// [`Generator::add_edge_sockets`].
+    pub fn connect<O: Into<String>, I: Into<String>>(
+        &mut self,
+        generator: &mut Generator,
+        link: Link,
+        output: O,
+        input: I,
+    ) -> Result<EdgeIndex> {
+        let sockets = Sockets::new(output, input);
+        let replaced = generator
+            .edges_of(link.output_node())
+            .into_iter()
+            .find(|(_, edge_link, edge_sockets)| {
+                edge_link.output_node() == link.output_node() && edge_sockets.input == sockets.input
+            })
+            .map(|(_, link, sockets)| (link, sockets));
+
+        let edge = generator.add_edge_sockets(link.clone(), &sockets.output, &sockets.input)?;
+        self.record(Command::Connect {
+            link,
+            sockets,
+            replaced,
+        });
+
+        Ok(edge)
+    }
+
+    pub fn disconnect(&mut self, generator: &mut Generator, edge: EdgeIndex) -> Result<()> {
+        let (link, sockets) = generator.remove_edge(edge)?;
+        self.record(Command::Disconnect { link, sockets });
+
+        Ok(())
+    }
+
+    pub fn set_parameter(
+        &mut self,
+        generator: &mut Generator,
+        node: NodeIndex,
+        name: &str,
+        value: ParameterValue,
+    ) -> Result<()> {
+        let old = generator
+            .internal_graph
+            .node_weight(node)
+            .ok_or(GeneratorError::UnknownNode(node))?
+            .read()
+            .unwrap()
+            .parameter(name)?;
+        let removed = generator.set_parameter(node, name, value.clone())?;
+        self.record(Command::SetParameter {
+            node,
+            name: name.to_string(),
+            old,
+            new: value,
+            removed,
+        });
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, RwLock};
+
+    use rusvid_core::pixel::Pixel;
+
+    use super::History;
+    use crate::error::GeneratorError;
+    use crate::generator::Generator;
+    use crate::input_output_value::InputOutputValue;
+    use crate::library::math::{Math, MathOperation};
+    use crate::library::mix::Mix;
+    use crate::library::static_value::StaticValue;
+    use crate::link::Link;
+    use crate::parameter::ParameterValue;
+
+    /// Everything an edit can change, edges are sorted because their indices can change on undo.
+    fn snapshot(g: &Generator) -> String {
+        let nodes = g
+            .internal_graph
+            .node_indices()
+            .map(|index| {
+                let node = g.internal_graph[index].read().unwrap();
+                (index, node.space_info().position, node.parameters())
+            })
+            .collect::<Vec<_>>();
+        let mut edges = g
+            .internal_graph
+            .node_indices()
+            .flat_map(|index| g.edges_of(index))
+            .map(|(_, link, sockets)| {
+                (
+                    link.input_node().index(),
+                    link.output_node().index(),
+                    sockets.output,
+                    sockets.input,
+                )
+            })
+            .collect::<Vec<_>>();
+        edges.sort();
+        edges.dedup();
+
+        format!("{nodes:?} {edges:?}")
+    }
+
+    #[test]
+    fn every_edit_can_be_undone_and_redone() {
+        let mut g = Generator::new();
+        let mut history = History::new();
+        let mut snapshots = vec![snapshot(&g)];
+
+        let color = history.add_node(
+            &mut g,
+            Arc::new(RwLock::new(StaticValue::new(InputOutputValue::Pixel(
+                Pixel::new(255, 0, 100, 255),
+            )))),
+        );
+        snapshots.push(snapshot(&g));
+        let mix = history.add_node(&mut g, Arc::new(RwLock::new(Mix::new())));
+        snapshots.push(snapshot(&g));
+        history
+            .connect(&mut g, Link::new(color, mix).unwrap(), "output", "input1")
+            .unwrap();
+        snapshots.push(snapshot(&g));
+        let output = g.output_node();
+        let edge = history
+            .connect(&mut g, Link::new(mix, output).unwrap(), "output", "input")
+            .unwrap();
+        snapshots.push(snapshot(&g));
+        history
+            .connect(&mut g, Link::new(color, output).unwrap(), "output", "input")
+            .unwrap();
+        snapshots.push(snapshot(&g));
+        history
+            .set_parameter(
+                &mut g,
+                color,
+                "value",
+                ParameterValue::Value(InputOutputValue::Float(0.5)),
+            )
+            .unwrap();
+        snapshots.push(snapshot(&g));
+        history.move_node(&mut g, mix, (10, 20)).unwrap();
+        snapshots.push(snapshot(&g));
+        history.remove_node(&mut g, color).unwrap();
+        snapshots.push(snapshot(&g));
+
+        // Failed edits are not recorded.
+        assert!(history.disconnect(&mut g, edge).is_err());
+
+        for expected in snapshots.iter().rev().skip(1) {
+            assert!(history.undo(&mut g).unwrap());
+            assert_eq!(&snapshot(&g), expected);
+        }
+        assert!(!history.undo(&mut g).unwrap());
+
+        for expected in snapshots.iter().skip(1) {
+            assert!(history.redo(&mut g).unwrap());
+            assert_eq!(&snapshot(&g), expected);
+        }
+        assert!(!history.redo(&mut g).unwrap());
+    }
+
+    #[test]
+    fn moves_of_a_drag_are_undone_at_once() {
+        let mut g = Generator::new();
+        let mut history = History::new();
+
+        let node = history.add_node(&mut g, Arc::new(RwLock::new(Mix::new())));
+        let start = g.internal_graph[node].read().unwrap().space_info().position;
+
+        for x in 0..10 {
+            history.move_node(&mut g, node, (x, 0)).unwrap();
+        }
+        history.finish_move();
+        history.move_node(&mut g, node, (50, 50)).unwrap();
+
+        let position = |g: &Generator| g.internal_graph[node].read().unwrap().space_info().position;
+
+        history.undo(&mut g).unwrap();
+        assert_eq!(position(&g), (9, 0));
+        history.undo(&mut g).unwrap();
+        assert_eq!(position(&g), start);
+        history.redo(&mut g).unwrap();
+        assert_eq!(position(&g), (9, 0));
+    }
+
+    #[test]
+    fn failed_undos_change_nothing() {
+        let mut g = Generator::new();
+        let mut history = History::new();
+
+        let color = g.add_node(StaticValue::new(InputOutputValue::Float(0.5)));
+        let math = g.add_node(Math::new(MathOperation::Add));
+        let mix = history.add_node(&mut g, Arc::new(RwLock::new(Mix::new())));
+        history
+            .connect(&mut g, Link::new(color, mix).unwrap(), "output", "input1")
+            .unwrap();
+        history
+            .connect(&mut g, Link::new(mix, math).unwrap(), "output", "operand")
+            .unwrap();
+        history.remove_node(&mut g, mix).unwrap();
+
+        // The input of an edge of the removed node is gone, restoring the node fails half way.
+        let operation = |g: &mut Generator, name: &str| {
+            g.set_parameter(math, "operation", ParameterValue::Enum(name.to_string()))
+                .unwrap();
+        };
+        operation(&mut g, "abs");
+        let before = snapshot(&g);
+        assert!(matches!(
+            history.undo(&mut g),
+            Err(GeneratorError::UnknownPort { port, .. }) if port == "operand"
+        ));
+        assert_eq!(snapshot(&g), before);
+        assert!(history.can_undo());
+        assert!(!history.can_redo());
+
+        // The edit can be undone once the graph is back in sync.
+        operation(&mut g, "add");
+        assert!(history.undo(&mut g).unwrap());
+        assert_eq!(g.edges_of(mix).len(), 2);
+    }
+
+    #[test]
+    fn edges_to_inputs_that_a_parameter_removes_come_back_on_undo() {
+        let mut g = Generator::new();
+        let mut history = History::new();
+
+        let value = g.add_node(StaticValue::new(InputOutputValue::Float(-0.5)));
+        let operand = g.add_node(StaticValue::new(InputOutputValue::Float(0.25)));
+        let math = g.add_node(Math::new(MathOperation::Add));
+        g.add_edge_sockets(Link::new(value, math).unwrap(), "output", "input")
+            .unwrap();
+        g.add_edge_sockets(Link::new(operand, math).unwrap(), "output", "operand")
+            .unwrap();
+        g.add_edge(Link::new(math, g.output_node()).unwrap())
+            .unwrap();
+
+        let inputs = |g: &Generator| {
+            let mut inputs = g
+                .edges_of(math)
+                .into_iter()
+                .filter(|(_, link, _)| link.output_node() == math)
+                .map(|(_, _, sockets)| sockets.input)
+                .collect::<Vec<_>>();
+            inputs.sort();
+            inputs
+        };
+
+        history
+            .set_parameter(
+                &mut g,
+                math,
+                "operation",
+                ParameterValue::Enum("abs".to_string()),
+            )
+            .unwrap();
+        assert_eq!(inputs(&g), ["input"]);
+        assert!(g.validate().iter().all(|d| !d.is_error()));
+        assert!(g.generate(4, 4).is_ok());
+
+        history.undo(&mut g).unwrap();
+        assert_eq!(inputs(&g), ["input", "operand"]);
+        history.redo(&mut g).unwrap();
+        assert_eq!(inputs(&g), ["input"]);
+    }
+}
@@ -1,5 +1,9 @@
-use anyhow::Result;
+use std::fmt::Display;
+
 use rusvid_core::pixel::Pixel;
+use serde::{Deserialize, Serialize};
+
+use crate::error::Result;
 
 macro_rules! impl_from_t {
     ((), $value:expr) => {
@@ -18,11 +22,28 @@ macro_rules! impl_from_t {
     };
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InputOutputKind {
+    Nothing,
+    Float,
+    Pixel,
+    U8X3Array,
+    U8X4Array,
+    F64X3Array,
+    F64X4Array,
+}
+
+impl Display for InputOutputKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self, f)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum InputOutputValue {
     Nothing,
     Float(f64),
-    Pixel(Pixel),
+    Pixel(#[serde(with = "crate::serialization::pixel")] Pixel),
     U8X3Array([u8; 3]),
     U8X4Array([u8; 4]),
     F64X3Array([f64; 3]),
@@ -30,6 +51,18 @@ pub enum InputOutputValue {
 }
 
 impl InputOutputValue {
+    pub fn kind(&self) -> InputOutputKind {
+        match self {
+            InputOutputValue::Nothing => InputOutputKind::Nothing,
+            InputOutputValue::Float(_) => InputOutputKind::Float,
+            InputOutputValue::Pixel(_) => InputOutputKind::Pixel,
+            InputOutputValue::U8X3Array(_) => InputOutputKind::U8X3Array,
+            InputOutputValue::U8X4Array(_) => InputOutputKind::U8X4Array,
+            InputOutputValue::F64X3Array(_) => InputOutputKind::F64X3Array,
+            InputOutputValue::F64X4Array(_) => InputOutputKind::F64X4Array,
+        }
+    }
+
     pub fn to_common_ground(&self) -> Result<Pixel> {
         match self {
             InputOutputValue::Nothing => Ok(Pixel::ZERO),
@@ -1,23 +1,33 @@
 mod bitmap;
 pub mod coordinate;
+pub mod diagnostic;
+pub mod error;
+pub mod formula;
 pub mod generator;
+pub mod group_definition;
+pub mod history;
 pub mod input_output_value;
 pub mod library;
 pub mod link;
 pub mod node;
+pub mod parameter;
+pub mod port;
+pub mod registry;
+pub mod serialization;
+pub mod tiling;
 pub(crate) mod utils;
 
 #[cfg(target_arch = "wasm32")]
-use std::sync::{Mutex, MutexGuard};
+use std::sync::{Arc, Mutex, MutexGuard, RwLock};
 
 #[cfg(target_arch = "wasm32")]
 use itertools::Itertools;
 #[cfg(target_arch = "wasm32")]
-use library::output::Output;
-#[cfg(target_arch = "wasm32")]
 use once_cell::sync::OnceCell;
 #[cfg(target_arch = "wasm32")]
-use petgraph::stable_graph::NodeIndex;
+use petgraph::stable_graph::{EdgeIndex, NodeIndex};
+#[cfg(target_arch = "wasm32")]
+use petgraph::visit::{EdgeRef, IntoEdgeReferences};
 #[cfg(target_arch = "wasm32")]
 use rusvid_core::prelude::{Pixel, Plane};
 #[cfg(target_arch = "wasm32")]
@@ -32,8 +42,12 @@ use crate::coordinate::Coordinate;
 #[cfg(target_arch = "wasm32")]
 use crate::generator::Generator;
 #[cfg(target_arch = "wasm32")]
+use crate::history::History;
+#[cfg(target_arch = "wasm32")]
 use crate::input_output_value::InputOutputValue;
 #[cfg(target_arch = "wasm32")]
+use crate::library::group::{Group, GroupDefinition, SharedGroup};
+#[cfg(target_arch = "wasm32")]
 use crate::library::map::Map;
 #[cfg(target_arch = "wasm32")]
 use crate::library::mix::Mix;
@@ -46,12 +60,26 @@ use crate::link::Link;
 #[cfg(target_arch = "wasm32")]
 use crate::node::Node;
 #[cfg(target_arch = "wasm32")]
+use crate::parameter::ParameterValue;
+#[cfg(target_arch = "wasm32")]
+use crate::registry::NodeRegistry;
+#[cfg(target_arch = "wasm32")]
 use crate::utils::{draw_circle, draw_line, render_square};
 
 #[cfg(target_arch = "wasm32")]
 static GENERATOR: OnceCell<Mutex<Generator>> = OnceCell::new();
 #[cfg(target_arch = "wasm32")]
-static CACHED_GENERATOR_OUTPUT: OnceCell<Plane> = OnceCell::new();
+static CACHED_GENERATOR_OUTPUT: Mutex<Option<Plane>> = Mutex::new(None);
+#[cfg(target_arch = "wasm32")]
+static PREVIEWS: Mutex<Vec<(NodeIndex, Plane)>> = Mutex::new(Vec::new());
+#[cfg(target_arch = "wasm32")]
+static REGISTRY: OnceCell<NodeRegistry> = OnceCell::new();
+/// The graphs around the group that is edited together with the group, the innermost last. See [`enter_group`].
+#[cfg(target_arch = "wasm32")]
+static OUTER_GRAPHS: Mutex<Vec<(Generator, SharedGroup, History)>> = Mutex::new(Vec::new());
+/// The edits of the graph that is currently edited.
+#[cfg(target_arch = "wasm32")]
+static HISTORY: Mutex<History> = Mutex::new(History::new());
 #[cfg(target_arch = "wasm32")]
 static SELECTED_NODE: Mutex<Option<NodeIndex>> = Mutex::new(None);
 
@@ -91,6 +119,8 @@ macro_rules! dbg {
 fn generator_mutex() -> &'static Mutex<Generator> {
     GENERATOR.get_or_init(|| {
         let mut generator = Generator::new();
+        // The editor renders the output and every preview after each change.
+        generator.set_caching(true);
 
         let node_mix = generator.add_node({
             let mut m = Mix::new();
@@ -117,10 +147,18 @@ fn generator_mutex() -> &'static Mutex<Generator> {
 
         println!("Created nodes");
 
-        generator.add_edge_named(Link::new(node_noise, node_mix), "value");
-        generator.add_edge_named(Link::new(node_input1, node_mix), "input1");
-        generator.add_edge_named(Link::new(node_input2, node_mix), "input2");
-        generator.add_edge(Link::new(node_mix, node_output));
+        generator
+            .add_edge_named(Link::new(node_noise, node_mix).unwrap(), "value")
+            .unwrap();
+        generator
+            .add_edge_named(Link::new(node_input1, node_mix).unwrap(), "input1")
+            .unwrap();
+        generator
+            .add_edge_named(Link::new(node_input2, node_mix).unwrap(), "input2")
+            .unwrap();
+        generator
+            .add_edge(Link::new(node_mix, node_output).unwrap())
+            .unwrap();
 
         println!("Added links");
 
@@ -151,18 +189,26 @@ pub fn move_node_to(node: usize, position_x: i64, position_y: i64) -> Result<(),
     println!("Move node {:?} to {:?}", &node, (position_x, position_y));
 
     let mut generator = get_generator();
-
-    if let Some(node) = generator
-        .internal_graph
-        .node_weight_mut(NodeIndex::new(node))
-    {
-        let mut n = node.borrow_mut();
-        n.space_info_mut().position = (position_x, position_y);
+    let index = NodeIndex::new(node);
+
+    if generator.internal_graph.contains_node(index) {
+        HISTORY
+            .lock()
+            .unwrap()
+            .move_node(&mut generator, index, (position_x, position_y))
+            .map_err(|err| JsValue::from_str(&err.to_string()))?;
     }
 
     Ok(())
 }
 
+/// Ends a drag, the moves since the start of the drag are undone at once.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn finish_move() {
+    HISTORY.lock().unwrap().finish_move();
+}
+
 #[cfg(target_arch = "wasm32")]
 #[wasm_bindgen]
 pub fn move_node(node: usize, delta_x: i64, delta_y: i64) -> Result<(), JsValue> {
@@ -170,7 +216,7 @@ pub fn move_node(node: usize, delta_x: i64, delta_y: i64) -> Result<(), JsValue>
 
     let generator = get_generator();
     if let Some(n) = generator.internal_graph.node_weight(NodeIndex::new(node)) {
-        let position = n.borrow().space_info().position;
+        let position = n.read().unwrap().space_info().position;
 
         drop(generator);
 
@@ -194,7 +240,7 @@ pub fn nodes(ctx: &CanvasRenderingContext2d, width: u32, height: u32) -> Result<
 
     println!("Draw edges:");
 
-    for edge in generator.internal_graph.raw_edges() {
+    for edge in generator.internal_graph.edge_references() {
         println!("edge: {edge:?}");
         let source_index = edge.source();
         let target_index = edge.target();
@@ -202,11 +248,11 @@ pub fn nodes(ctx: &CanvasRenderingContext2d, width: u32, height: u32) -> Result<
         let source = generator
             .internal_graph
             .node_weight(source_index)
-            .map(|item| item.borrow());
+            .map(|item| item.read().unwrap());
         let target = generator
             .internal_graph
             .node_weight(target_index)
-            .map(|item| item.borrow());
+            .map(|item| item.read().unwrap());
 
         if let (Some(source), Some(target)) = (source, target) {
             if source.space_info().position == target.space_info().position {
@@ -235,22 +281,19 @@ pub fn nodes(ctx: &CanvasRenderingContext2d, width: u32, height: u32) -> Result<
         }
     }
 
-    for node in generator.connected_nodes_to_output() {
-        let node_borrowed = node.borrow();
+    let previews = PREVIEWS.lock().unwrap();
+    for index in generator.connected_indices_to_output() {
+        let node_borrowed = generator.internal_graph[index].read().unwrap();
 
         println!("Rendering node: {:?}", &node_borrowed,);
         node_borrowed.render(&mut plane).unwrap();
 
         if node_borrowed.is_output() {
-            // TODO There must be a better way to cast a 'dyn Node' to a '&Output'.
-            if let Some(output_ref) = unsafe { (node.as_ref().as_ptr() as *mut Output).as_ref() } {
-                output_ref
-                    .draw_generated_output_into_node(
-                        &mut plane,
-                        CACHED_GENERATOR_OUTPUT.get().unwrap(),
-                    )
-                    .unwrap()
+            if let Some(generated) = CACHED_GENERATOR_OUTPUT.lock().unwrap().as_ref() {
+                node_borrowed.render_preview(&mut plane, generated).unwrap()
             }
+        } else if let Some((_, preview)) = previews.iter().find(|(node, _)| *node == index) {
+            node_borrowed.render_preview(&mut plane, preview).unwrap()
         }
     }
 
@@ -283,7 +326,7 @@ pub fn canvas_click(
             let node = generator.internal_graph.node_weight(id);
 
             match node {
-                Some(node) => Some((id, node.borrow())),
+                Some(node) => Some((id, node.read().unwrap())),
                 None => None,
             }
         })
@@ -338,7 +381,306 @@ pub fn init() {
 
     let generator = get_generator();
 
-    CACHED_GENERATOR_OUTPUT
-        .set(generator.generate(200, 200).unwrap())
-        .unwrap();
+    render_generator(&generator).unwrap();
+}
+
+/// Renders the output and the previews of the other nodes, unchanged nodes are taken from the cache of the generator.
+///
+/// While a group is edited, its definition gets the edited graph first so that the other instances of the group see
+/// the edit as well.
+#[cfg(target_arch = "wasm32")]
+fn render_generator(generator: &Generator) -> Result<(), JsValue> {
+    if let Some((_, group, _)) = OUTER_GRAPHS.lock().unwrap().last() {
+        group.write().unwrap().generator = generator.share_nodes();
+    }
+
+    let generated = generator
+        .generate(200, 200)
+        .map_err(|err| JsValue::from_str(&err.to_string()))?;
+    let previews = generator
+        .connected_indices_to_output()
+        .into_iter()
+        .filter(|index| *index != generator.output_node())
+        .filter_map(|index| {
+            generator
+                .generate_from(index, 200, 200)
+                .ok()
+                .map(|preview| (index, preview))
+        })
+        .collect();
+
+    *CACHED_GENERATOR_OUTPUT.lock().unwrap() = Some(generated);
+    *PREVIEWS.lock().unwrap() = previews;
+
+    Ok(())
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn save_graph() -> Result<String, JsValue> {
+    if !OUTER_GRAPHS.lock().unwrap().is_empty() {
+        return Err(JsValue::from_str("leave the group before saving"));
+    }
+
+    get_generator()
+        .to_json()
+        .map_err(|err| JsValue::from_str(&err.to_string()))
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn load_graph(json: &str) -> Result<(), JsValue> {
+    let mut generator =
+        Generator::from_json(json).map_err(|err| JsValue::from_str(&err.to_string()))?;
+    generator.set_caching(true);
+    render_generator(&generator)?;
+
+    *get_generator() = generator;
+    OUTER_GRAPHS.lock().unwrap().clear();
+    *HISTORY.lock().unwrap() = History::new();
+    set_selected_node(None);
+
+    Ok(())
+}
+
+/// Switches the seamless tiling of the output on or off, see [`Generator::set_tileable`].
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn set_tileable(tileable: bool) -> Result<(), JsValue> {
+    if !OUTER_GRAPHS.lock().unwrap().is_empty() {
+        return Err(JsValue::from_str(
+            "leave the group before changing the tiling",
+        ));
+    }
+
+    let mut generator = get_generator();
+    generator.set_tileable(tileable);
+    render_generator(&generator)
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn available_node_types() -> Box<[JsValue]> {
+    REGISTRY
+        .get_or_init(NodeRegistry::default)
+        .node_types()
+        .map(JsValue::from_str)
+        .collect()
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn add_node(node_type: &str) -> Result<usize, JsValue> {
+    let node = REGISTRY
+        .get_or_init(NodeRegistry::default)
+        .create(node_type)
+        .map_err(|err| JsValue::from_str(&err.to_string()))?;
+
+    let index = HISTORY.lock().unwrap().add_node(&mut get_generator(), node);
+
+    Ok(index.index())
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn add_group(name: &str) -> Result<usize, JsValue> {
+    let definition = GroupDefinition::new(name, Generator::new()).shared();
+    let index = HISTORY.lock().unwrap().add_node(
+        &mut get_generator(),
+        Arc::new(RwLock::new(Group::new(definition))),
+    );
+
+    Ok(index.index())
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn remove_node(node: usize) -> Result<(), JsValue> {
+    let mut generator = get_generator();
+    HISTORY
+        .lock()
+        .unwrap()
+        .remove_node(&mut generator, NodeIndex::new(node))
+        .map_err(|err| JsValue::from_str(&err.to_string()))?;
+    set_selected_node(None);
+
+    render_generator(&generator)
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn connect_nodes(
+    source: usize,
+    target: usize,
+    output: &str,
+    input: &str,
+) -> Result<usize, JsValue> {
+    let to_js = |err: error::GeneratorError| JsValue::from_str(&err.to_string());
+
+    let mut generator = get_generator();
+    let link = Link::new(NodeIndex::new(source), NodeIndex::new(target)).map_err(to_js)?;
+    let edge = HISTORY
+        .lock()
+        .unwrap()
+        .connect(&mut generator, link, output, input)
+        .map_err(to_js)?;
+    // The graph can be incomplete while it gets edited, it is rendered again once it is valid.
+    let _ = render_generator(&generator);
+
+    Ok(edge.index())
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn disconnect_edge(edge: usize) -> Result<(), JsValue> {
+    let mut generator = get_generator();
+    HISTORY
+        .lock()
+        .unwrap()
+        .disconnect(&mut generator, EdgeIndex::new(edge))
+        .map_err(|err| JsValue::from_str(&err.to_string()))?;
+    let _ = render_generator(&generator);
+
+    Ok(())
+}
+
+/// Reverts the last edit of the current graph, returns `false` if there is nothing to undo.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn undo() -> Result<bool, JsValue> {
+    let mut generator = get_generator();
+    let undone = HISTORY
+        .lock()
+        .unwrap()
+        .undo(&mut generator)
+        .map_err(|err| JsValue::from_str(&err.to_string()))?;
+    set_selected_node(None);
+    let _ = render_generator(&generator);
+
+    Ok(undone)
+}
+
+/// Applies the last undone edit of the current graph again, returns `false` if there is nothing to redo.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn redo() -> Result<bool, JsValue> {
+    let mut generator = get_generator();
+    let redone = HISTORY
+        .lock()
+        .unwrap()
+        .redo(&mut generator)
+        .map_err(|err| JsValue::from_str(&err.to_string()))?;
+    set_selected_node(None);
+    let _ = render_generator(&generator);
+
+    Ok(redone)
+}
+
+/// Edits the graph of the group `node` instead of the current graph until [`leave_group`] gets called.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn enter_group(node: usize) -> Result<(), JsValue> {
+    let mut generator = get_generator();
+    let group = generator
+        .internal_graph
+        .node_weight(NodeIndex::new(node))
+        .ok_or_else(|| JsValue::from_str("unknown node"))?
+        .read()
+        .unwrap()
+        .group()
+        .cloned()
+        .ok_or_else(|| JsValue::from_str("the node isn't a group"))?;
+
+    // The definition keeps its graph while it is edited, see `render_generator`.
+    let mut inner = group.read().unwrap().generator.share_nodes();
+    inner.set_caching(true);
+
+    let outer = std::mem::replace(&mut *generator, inner);
+    let history = std::mem::take(&mut *HISTORY.lock().unwrap());
+    OUTER_GRAPHS.lock().unwrap().push((outer, group, history));
+    set_selected_node(None);
+
+    render_generator(&generator)
+}
+
+/// Stores the edited graph in its group and goes back to the graph around it.
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn leave_group() -> Result<(), JsValue> {
+    let (outer, group, history) = OUTER_GRAPHS
+        .lock()
+        .unwrap()
+        .pop()
+        .ok_or_else(|| JsValue::from_str("not inside of a group"))?;
+
+    let mut generator = get_generator();
+    let mut inner = std::mem::replace(&mut *generator, outer);
+    inner.set_caching(false);
+    group.write().unwrap().generator = inner;
+    *HISTORY.lock().unwrap() = history;
+    // The selection belongs to the graph of the group, it has to go even if the graph around it can't be rendered.
+    set_selected_node(None);
+    // The generator renders the instances of the group again on its own.
+    render_generator(&generator)
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn node_parameters(node: usize) -> Result<String, JsValue> {
+    let generator = get_generator();
+    let node = generator
+        .internal_graph
+        .node_weight(NodeIndex::new(node))
+        .ok_or_else(|| JsValue::from_str("unknown node"))?;
+
+    let parameters = node
+        .read()
+        .unwrap()
+        .parameters()
+        .into_iter()
+        .map(|parameter| {
+            Ok(serde_json::json!({
+                "name": parameter.spec.name,
+                "kind": parameter.spec.kind,
+                "value": parameter.value.to_json()?,
+            }))
+        })
+        .collect::<error::Result<Vec<_>>>()
+        .map_err(|err| JsValue::from_str(&err.to_string()))?;
+
+    Ok(serde_json::Value::Array(parameters).to_string())
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+pub fn set_node_parameter(node: usize, name: &str, value: &str) -> Result<(), JsValue> {
+    let to_js = |err: error::GeneratorError| JsValue::from_str(&err.to_string());
+
+    let mut generator = get_generator();
+    let index = NodeIndex::new(node);
+    let kind = generator
+        .internal_graph
+        .node_weight(index)
+        .ok_or_else(|| JsValue::from_str("unknown node"))?
+        .read()
+        .unwrap()
+        .parameters()
+        .into_iter()
+        .find(|parameter| parameter.spec.name == name)
+        .map(|parameter| parameter.spec.kind)
+        .ok_or_else(|| JsValue::from_str("unknown parameter"))?;
+
+    let value = serde_json::from_str(value)
+        .map_err(|err| to_js(err.into()))
+        .and_then(|value| ParameterValue::from_json(&kind, value).map_err(to_js))?;
+    HISTORY
+        .lock()
+        .unwrap()
+        .set_parameter(&mut generator, index, name, value)
+        .map_err(to_js)?;
+
+    // Only the changed node and the nodes after it get rendered again.
+    render_generator(&generator)?;
+
+    Ok(())
 }
@@ -0,0 +1,346 @@
+use std::collections::HashMap;
+
+use crate::coordinate::Coordinate;
+use crate::diagnostic::DiagnosticKind;
+use crate::error::{GeneratorError, Result};
+use crate::formula::{Formula, ParseError};
+use crate::input_output_value::{InputOutputKind, InputOutputValue};
+use crate::node::{BatchInputs, BatchValue, Node, SpaceInfo};
+use crate::parameter::{unsupported_parameter, Parameter, ParameterKind, ParameterValue};
+use crate::port::{Port, DEFAULT_OUTPUT};
+
+/// A variable of the formula that the node provides itself instead of an input.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Builtin {
+    X,
+    Y,
+    Z,
+    U,
+    V,
+    Width,
+    Height,
+    Constant(f64),
+}
+
+impl Builtin {
+    fn from_name(name: &str) -> Option<Builtin> {
+        let builtin = match name {
+            "x" => Builtin::X,
+            "y" => Builtin::Y,
+            "z" => Builtin::Z,
+            "u" => Builtin::U,
+            "v" => Builtin::V,
+            "width" => Builtin::Width,
+            "height" => Builtin::Height,
+            "pi" => Builtin::Constant(std::f64::consts::PI),
+            "e" => Builtin::Constant(std::f64::consts::E),
+            _ => return None,
+        };
+
+        Some(builtin)
+    }
+
+    fn value(&self, position: &Coordinate, size: &(u32, u32)) -> f64 {
+        let relative = |value: f64, length: u32| {
+            if length > 1 {
+                value / (length - 1) as f64
+            } else {
+                0.0
+            }
+        };
+
+        match self {
+            Builtin::X => position.x(),
+            Builtin::Y => position.y(),
+            Builtin::Z => position.z(),
+            Builtin::U => relative(position.x(), size.0),
+            Builtin::V => relative(position.y(), size.1),
+            Builtin::Width => size.0 as f64,
+            Builtin::Height => size.1 as f64,
+            Builtin::Constant(value) => *value,
+        }
+    }
+}
+
+/// Where [`Expression::generate_batch`] takes the value of a variable from.
+enum Source<'a> {
+    Builtin(Builtin),
+    Input(&'a str, BatchValue<'a>),
+}
+
+/// Calculates a [`Formula`] for every sample, e.g. `sin(x * 10 + a) * 0.5 + 0.5`.
+///
+/// The formula can use the position of the sample in pixels as `x`, `y` and `z`, the position relative to the image
+/// size from `0.0` to `1.0` as `u` and `v`, the image size as `width` and `height` and the constants `pi` and `e`. Every
+/// other variable becomes a float input of the node.
+///
+/// A formula that can't be parsed is kept so that it can be fixed, it's reported by [`Node::diagnostics`]. The node keeps
+/// the inputs of the last formula that could be parsed until then, so a typo doesn't cut its edges.
+#[derive(Debug)]
+pub struct Expression {
+    source: String,
+    /// The last formula that could be parsed.
+    formula: Option<Formula>,
+    error: Option<ParseError>,
+
+    space_info: SpaceInfo,
+}
+
+impl Expression {
+    pub fn new<S: Into<String>>(source: S) -> Self {
+        let mut expression = Expression {
+            source: String::new(),
+            formula: None,
+            error: None,
+
+            space_info: SpaceInfo::default(),
+        };
+        expression.set_expression(source);
+
+        expression
+    }
+
+    pub fn expression(&self) -> &str {
+        &self.source
+    }
+
+    pub fn set_expression<S: Into<String>>(&mut self, source: S) {
+        self.source = source.into();
+        match Formula::parse(&self.source) {
+            Ok(formula) => {
+                self.formula = Some(formula);
+                self.error = None;
+            }
+            Err(error) => self.error = Some(error),
+        }
+    }
+
+    /// Why the current expression can't be parsed.
+    pub fn error(&self) -> Option<&ParseError> {
+        self.error.as_ref()
+    }
+
+    fn formula(&self) -> Result<&Formula> {
+        match (&self.formula, &self.error) {
+            (Some(formula), None) => Ok(formula),
+            (_, error) => Err(GeneratorError::InvalidParameter {
+                parameter: "expression".to_string(),
+                reason: error.as_ref().map(ToString::to_string).unwrap_or_default(),
+            }),
+        }
+    }
+
+    fn float(&self, port: &str, value: InputOutputValue) -> Result<f64> {
+        match value {
+            InputOutputValue::Float(value) => Ok(value),
+            value => Err(GeneratorError::TypeMismatch {
+                node: self.space_info.name.clone(),
+                port: port.to_string(),
+                expected: vec![InputOutputKind::Float],
+                found: value.kind(),
+            }),
+        }
+    }
+}
+
+impl Node for Expression {
+    fn node_type(&self) -> &'static str {
+        "expression"
+    }
+
+    fn generate(
+        &self,
+        position: &Coordinate,
+        size: &(u32, u32),
+        input: HashMap<String, InputOutputValue>,
+    ) -> Result<InputOutputValue> {
+        let formula = self.formula()?;
+
+        let variables = formula
+            .variables()
+            .iter()
+            .map(|name| match Builtin::from_name(name) {
+                Some(builtin) => Ok(builtin.value(position, size)),
+                None => self.float(name, self.input_value(&input, name)?),
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(InputOutputValue::Float(formula.evaluate(&variables)))
+    }
+
+    fn generate_batch(
+        &self,
+        positions: &[Coordinate],
+        size: &(u32, u32),
+        inputs: &BatchInputs,
+    ) -> Result<Vec<InputOutputValue>> {
+        let formula = self.formula()?;
+
+        // The inputs are looked up once for the whole batch.
+        let sources = formula
+            .variables()
+            .iter()
+            .map(|name| match Builtin::from_name(name) {
+                Some(builtin) => Ok(Source::Builtin(builtin)),
+                None => Ok(Source::Input(name, self.batch_input(inputs, name)?)),
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut variables = vec![0.0; sources.len()];
+        positions
+            .iter()
+            .enumerate()
+            .map(|(index, position)| {
+                for (variable, source) in variables.iter_mut().zip(&sources) {
+                    *variable = match source {
+                        Source::Builtin(builtin) => builtin.value(position, size),
+                        Source::Input(name, input) => self.float(name, input.get(index))?,
+                    };
+                }
+
+                Ok(InputOutputValue::Float(formula.evaluate(&variables)))
+            })
+            .collect()
+    }
+
+    fn inputs(&self) -> Vec<Port> {
+        match &self.formula {
+            Some(formula) => formula
+                .variables()
+                .iter()
+                .filter(|name| Builtin::from_name(name).is_none())
+                .map(|name| Port::new(name).with_kinds(&[InputOutputKind::Float]))
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    fn outputs(&self) -> Vec<Port> {
+        vec![Port::new(DEFAULT_OUTPUT).with_kinds(&[InputOutputKind::Float])]
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![Parameter::new(
+            "expression",
+            ParameterKind::Text,
+            ParameterValue::Text(self.source.clone()),
+        )]
+    }
+
+    fn set_parameter(&mut self, name: &str, value: ParameterValue) -> Result<()> {
+        match (name, value) {
+            ("expression", ParameterValue::Text(source)) => {
+                self.set_expression(source);
+                Ok(())
+            }
+            (name, value) => Err(unsupported_parameter(self, name, &value)),
+        }
+    }
+
+    fn diagnostics(&self) -> Vec<DiagnosticKind> {
+        match &self.error {
+            None => Vec::new(),
+            Some(error) => vec![DiagnosticKind::InvalidParameter {
+                parameter: "expression".to_string(),
+                reason: error.to_string(),
+            }],
+        }
+    }
+
+    fn space_info(&self) -> &SpaceInfo {
+        &self.space_info
+    }
+
+    fn space_info_mut(&mut self) -> &mut SpaceInfo {
+        &mut self.space_info
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Expression;
+    use crate::coordinate::Coordinate;
+    use crate::diagnostic::DiagnosticKind;
+    use crate::error::GeneratorError;
+    use crate::generator::Generator;
+    use crate::input_output_value::InputOutputValue;
+    use crate::library::static_value::StaticValue;
+    use crate::link::Link;
+    use crate::node::{BatchInputs, BatchValue, Node};
+    use crate::parameter::ParameterValue;
+
+    #[test]
+    fn variables_become_inputs() {
+        let expression = Expression::new("sin(x * 10 + a) * 0.5 + 0.5 + b * u");
+        let inputs = expression.inputs();
+        assert_eq!(
+            inputs
+                .iter()
+                .map(|port| port.name.as_str())
+                .collect::<Vec<_>>(),
+            ["a", "b"]
+        );
+
+        let positions = [Coordinate::new_xy(0.0, 0.0), Coordinate::new_xy(3.0, 1.0)];
+        let a = [InputOutputValue::Float(1.0), InputOutputValue::Float(2.0)];
+        let mut batch_inputs = BatchInputs::new();
+        batch_inputs.insert("a", BatchValue::Samples(&a));
+        batch_inputs.insert("b", BatchValue::Constant(InputOutputValue::Float(4.0)));
+
+        let batch = expression
+            .generate_batch(&positions, &(4, 2), &batch_inputs)
+            .unwrap();
+        assert_eq!(
+            batch,
+            [
+                InputOutputValue::Float(1.0f64.sin() * 0.5 + 0.5),
+                InputOutputValue::Float(32.0f64.sin() * 0.5 + 0.5 + 4.0),
+            ]
+        );
+        for (index, position) in positions.iter().enumerate() {
+            let single = expression
+                .generate(position, &(4, 2), batch_inputs.sample(index))
+                .unwrap();
+            assert_eq!(batch[index], single);
+        }
+    }
+
+    #[test]
+    fn parse_errors_are_diagnostics() {
+        let mut g = Generator::new();
+        let value = g.add_node(StaticValue::new(InputOutputValue::Float(0.5)));
+        let expression = g.add_node(Expression::new("a * (u + v"));
+        g.add_edge(Link::new(expression, g.output_node()).unwrap())
+            .unwrap();
+
+        let diagnostics = g.validate();
+        assert!(diagnostics.iter().any(|d| d.node == expression
+            && matches!(&d.kind, DiagnosticKind::InvalidParameter { parameter, .. } if parameter == "expression")));
+        assert!(matches!(
+            g.generate(4, 4),
+            Err(GeneratorError::InvalidGraph(_))
+        ));
+
+        g.set_parameter(
+            expression,
+            "expression",
+            ParameterValue::Text("a * (u + v)".to_string()),
+        )
+        .unwrap();
+        g.add_edge_named(Link::new(value, expression).unwrap(), "a")
+            .unwrap();
+        assert!(g.validate().is_empty());
+        let _ = g.generate(4, 4).unwrap();
+
+        // A typo keeps the edges of the formula before it.
+        let removed = g
+            .set_parameter(
+                expression,
+                "expression",
+                ParameterValue::Text("a * (u +".to_string()),
+            )
+            .unwrap();
+        assert!(removed.is_empty());
+        assert!(!g.validate().is_empty());
+    }
+}
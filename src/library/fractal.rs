@@ -0,0 +1,471 @@
+use std::collections::HashMap;
+
+use rusvid_core::pixel::Pixel;
+use rusvid_core::plane::Plane;
+
+use crate::bitmap::BitmapChar;
+use crate::coordinate::Coordinate;
+use crate::error::{GeneratorError, Result};
+use crate::input_output_value::{InputOutputKind, InputOutputValue};
+use crate::library::noise::{NoiseAlgorithm, NoisePoint, NoiseSource};
+use crate::node::{BatchInputs, Node, SpaceInfo};
+use crate::parameter::{
+    check_range, unsupported_parameter, Parameter, ParameterKind, ParameterValue,
+};
+use crate::port::{Port, DEFAULT_OUTPUT};
+use crate::utils::render_square;
+
+pub const MAX_OCTAVES: usize = 16;
+
+/// How the octaves of a [`Fractal`] get combined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FractalKind {
+    /// Fractal Brownian motion, the plain sum of the octaves.
+    Fbm,
+    /// Ridged multifractal, sharp ridges where the noise crosses zero. Rough octaves only show up on the ridges.
+    Ridged,
+    /// Sum of the absolute values of the octaves, results in puffy, cloud like shapes.
+    Billow,
+    /// Like [`FractalKind::Billow`] but between `0.0` and `1.0`.
+    Turbulence,
+}
+
+impl FractalKind {
+    pub const ALL: [FractalKind; 4] = [
+        FractalKind::Fbm,
+        FractalKind::Ridged,
+        FractalKind::Billow,
+        FractalKind::Turbulence,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            FractalKind::Fbm => "fbm",
+            FractalKind::Ridged => "ridged",
+            FractalKind::Billow => "billow",
+            FractalKind::Turbulence => "turbulence",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<FractalKind> {
+        FractalKind::ALL
+            .into_iter()
+            .find(|kind| kind.name() == name)
+    }
+}
+
+/// Sums multiple octaves of noise, every octave has a higher frequency and a lower amplitude than the one
+/// before.
+///
+/// Every octave uses its own seed, `seed + octave * octave_seed_offset`, so that the octaves don't line up.
+#[derive(Debug)]
+pub struct Fractal {
+    kind: FractalKind,
+    algorithm: NoiseAlgorithm,
+    /// One noise function per octave.
+    octaves: Vec<NoiseSource>,
+
+    seed: u32,
+    octave_seed_offset: u32,
+    offset: Coordinate,
+    scale: Coordinate,
+    /// Factor of the frequency from one octave to the next.
+    lacunarity: f64,
+    /// Factor of the amplitude from one octave to the next, also called gain.
+    persistence: f64,
+
+    space_info: SpaceInfo,
+}
+
+impl Fractal {
+    pub fn new(kind: FractalKind, seed: u32) -> Self {
+        let mut fractal = Fractal {
+            kind,
+            algorithm: NoiseAlgorithm::Perlin,
+            octaves: Vec::new(),
+            seed,
+            octave_seed_offset: 1,
+            offset: Coordinate::new(0.0, 0.0, 0.0),
+            scale: Coordinate::new(1.0, 1.0, 1.0),
+            lacunarity: 2.0,
+            persistence: 0.5,
+
+            space_info: {
+                let mut si = SpaceInfo::default();
+
+                si.size = (250, 110);
+
+                si
+            },
+        };
+        fractal.build_octaves(4);
+
+        fractal
+    }
+
+    fn build_octaves(&mut self, count: usize) {
+        self.octaves = (0..count as u32)
+            .map(|octave| {
+                NoiseSource::new(
+                    self.algorithm,
+                    self.seed
+                        .wrapping_add(octave.wrapping_mul(self.octave_seed_offset)),
+                )
+            })
+            .collect();
+    }
+
+    pub fn kind(&self) -> FractalKind {
+        self.kind
+    }
+
+    pub fn set_kind(&mut self, kind: FractalKind) {
+        self.kind = kind;
+    }
+
+    pub fn algorithm(&self) -> NoiseAlgorithm {
+        self.algorithm
+    }
+
+    pub fn set_algorithm(&mut self, algorithm: NoiseAlgorithm) {
+        self.algorithm = algorithm;
+        self.build_octaves(self.octaves.len());
+    }
+
+    pub fn seed(&self) -> u32 {
+        self.seed
+    }
+
+    pub fn set_seed(&mut self, seed: u32) {
+        self.seed = seed;
+        self.build_octaves(self.octaves.len());
+    }
+
+    pub fn octave_seed_offset(&self) -> u32 {
+        self.octave_seed_offset
+    }
+
+    pub fn set_octave_seed_offset(&mut self, octave_seed_offset: u32) {
+        self.octave_seed_offset = octave_seed_offset;
+        self.build_octaves(self.octaves.len());
+    }
+
+    pub fn octaves(&self) -> usize {
+        self.octaves.len()
+    }
+
+    /// Fails unless `octaves` is between `1` and [`MAX_OCTAVES`].
+    pub fn set_octaves(&mut self, octaves: usize) -> Result<()> {
+        if !(1..=MAX_OCTAVES).contains(&octaves) {
+            return Err(GeneratorError::InvalidParameter {
+                parameter: "octaves".to_string(),
+                reason: format!("expected a value between 1 and {MAX_OCTAVES} but got {octaves}"),
+            });
+        }
+
+        self.build_octaves(octaves);
+
+        Ok(())
+    }
+
+    pub fn offset(&self) -> Coordinate {
+        self.offset
+    }
+
+    pub fn set_offset(&mut self, offset: Coordinate) {
+        self.offset = offset;
+    }
+
+    pub fn scale(&self) -> Coordinate {
+        self.scale
+    }
+
+    pub fn set_scale(&mut self, scale: Coordinate) {
+        self.scale = scale;
+    }
+
+    pub fn lacunarity(&self) -> f64 {
+        self.lacunarity
+    }
+
+    pub fn set_lacunarity(&mut self, lacunarity: f64) -> Result<()> {
+        self.lacunarity = check_range("lacunarity", lacunarity, 1.0, 8.0)?;
+
+        Ok(())
+    }
+
+    pub fn persistence(&self) -> f64 {
+        self.persistence
+    }
+
+    pub fn set_persistence(&mut self, persistence: f64) -> Result<()> {
+        self.persistence = check_range("persistence", persistence, 0.0, 1.0)?;
+
+        Ok(())
+    }
+
+    /// Returns a value between `-1.0` and `1.0`, or between `0.0` and `1.0` for [`FractalKind::Turbulence`].
+    fn sample(&self, position: &Coordinate, size: &(u32, u32), tileable: bool) -> f64 {
+        let point = NoisePoint::new(position, size, &self.offset, &self.scale, tileable);
+
+        let mut frequency = 1.0;
+        let mut amplitude = 1.0;
+        let mut total = 0.0;
+        let mut value = 0.0;
+        // Ridged multifractal: octaves only add detail where the previous octaves formed a ridge.
+        let mut weight = 1.0;
+
+        for source in &self.octaves {
+            let noise = source.get(point.scaled(frequency));
+
+            value += amplitude
+                * match self.kind {
+                    FractalKind::Fbm => noise,
+                    FractalKind::Ridged => {
+                        let ridge = (1.0 - noise.abs()).powi(2) * weight;
+                        weight = (ridge * 2.0).clamp(0.0, 1.0);
+
+                        ridge
+                    }
+                    FractalKind::Billow | FractalKind::Turbulence => noise.abs(),
+                };
+            total += amplitude;
+
+            frequency *= self.lacunarity;
+            amplitude *= self.persistence;
+        }
+
+        let value = value / total;
+        match self.kind {
+            FractalKind::Fbm => value,
+            FractalKind::Ridged | FractalKind::Billow => value * 2.0 - 1.0,
+            FractalKind::Turbulence => value,
+        }
+    }
+}
+
+impl Node for Fractal {
+    fn node_type(&self) -> &'static str {
+        "noise.fractal"
+    }
+
+    fn generate(
+        &self,
+        position: &Coordinate,
+        size: &(u32, u32),
+        _input: HashMap<String, InputOutputValue>,
+    ) -> Result<InputOutputValue> {
+        Ok(InputOutputValue::Float(self.sample(position, size, false)))
+    }
+
+    fn generate_batch(
+        &self,
+        positions: &[Coordinate],
+        size: &(u32, u32),
+        inputs: &BatchInputs,
+    ) -> Result<Vec<InputOutputValue>> {
+        Ok(positions
+            .iter()
+            .map(|position| InputOutputValue::Float(self.sample(position, size, inputs.tileable())))
+            .collect())
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![
+            Parameter::new(
+                "kind",
+                ParameterKind::Enum {
+                    variants: FractalKind::ALL
+                        .iter()
+                        .map(|kind| kind.name().to_string())
+                        .collect(),
+                },
+                ParameterValue::Enum(self.kind.name().to_string()),
+            ),
+            self.algorithm.parameter(),
+            Parameter::new("seed", ParameterKind::Seed, ParameterValue::Seed(self.seed)),
+            Parameter::new(
+                "octave_seed_offset",
+                ParameterKind::Seed,
+                ParameterValue::Seed(self.octave_seed_offset),
+            ),
+            Parameter::new(
+                "offset",
+                ParameterKind::Coordinate,
+                ParameterValue::Coordinate(self.offset),
+            ),
+            Parameter::new(
+                "scale",
+                ParameterKind::Coordinate,
+                ParameterValue::Coordinate(self.scale),
+            ),
+            Parameter::new(
+                "octaves",
+                ParameterKind::Int {
+                    min: 1,
+                    max: MAX_OCTAVES as i64,
+                },
+                ParameterValue::Int(self.octaves() as i64),
+            ),
+            Parameter::new(
+                "lacunarity",
+                ParameterKind::Float { min: 1.0, max: 8.0 },
+                ParameterValue::Float(self.lacunarity),
+            ),
+            Parameter::new(
+                "persistence",
+                ParameterKind::Float { min: 0.0, max: 1.0 },
+                ParameterValue::Float(self.persistence),
+            ),
+        ]
+    }
+
+    fn set_parameter(&mut self, name: &str, value: ParameterValue) -> Result<()> {
+        match (name, value) {
+            ("kind", ParameterValue::Enum(kind)) => {
+                let kind = FractalKind::from_name(&kind).ok_or_else(|| {
+                    GeneratorError::InvalidParameter {
+                        parameter: name.to_string(),
+                        reason: format!("unknown kind {kind:?}"),
+                    }
+                })?;
+                self.set_kind(kind);
+            }
+            ("algorithm", ParameterValue::Enum(algorithm)) => {
+                self.set_algorithm(NoiseAlgorithm::from_parameter(name, &algorithm)?)
+            }
+            ("seed", ParameterValue::Seed(seed)) => self.set_seed(seed),
+            ("octave_seed_offset", ParameterValue::Seed(offset)) => {
+                self.set_octave_seed_offset(offset)
+            }
+            ("offset", ParameterValue::Coordinate(offset)) => self.set_offset(offset),
+            ("scale", ParameterValue::Coordinate(scale)) => self.set_scale(scale),
+            ("octaves", ParameterValue::Int(octaves)) => {
+                self.set_octaves(usize::try_from(octaves).unwrap_or(0))?
+            }
+            ("lacunarity", ParameterValue::Float(lacunarity)) => self.set_lacunarity(lacunarity)?,
+            ("persistence", ParameterValue::Float(persistence)) => {
+                self.set_persistence(persistence)?
+            }
+            (name, value) => return Err(unsupported_parameter(self, name, &value)),
+        }
+
+        Ok(())
+    }
+
+    fn outputs(&self) -> Vec<Port> {
+        vec![Port::new(DEFAULT_OUTPUT).with_kinds(&[InputOutputKind::Float])]
+    }
+
+    fn render(&self, plane: &mut Plane) -> Result<()> {
+        let space_info = self.space_info();
+
+        render_square(
+            plane,
+            space_info.position,
+            space_info.size,
+            space_info.color,
+        )?;
+
+        let (_, mut delta_y) = BitmapChar::render_multiple_with_scale(
+            plane,
+            (space_info.position.0, space_info.position.1 + 5),
+            &space_info.name,
+            Pixel::new(0, 0, 0, 255),
+            2,
+        )?;
+
+        let lines = [
+            format!("Kind: {}", self.kind.name()),
+            format!("Algorithm: {}", self.algorithm.label()),
+            format!("Seed: {}", self.seed),
+            format!("Offset: {}", self.offset),
+            format!("Scale: {}", self.scale),
+            format!("Octaves: {}", self.octaves()),
+            format!("Lacunarity: {}", self.lacunarity),
+            format!("Persistence: {}", self.persistence),
+        ];
+        for line in lines {
+            let (_, delta) = BitmapChar::render_multiple_with_scale(
+                plane,
+                (
+                    space_info.position.0,
+                    space_info.position.1 + 5 + delta_y as i64,
+                ),
+                line,
+                Pixel::new(0, 0, 0, 255),
+                1,
+            )?;
+            delta_y += delta;
+        }
+
+        Ok(())
+    }
+
+    fn space_info(&self) -> &SpaceInfo {
+        &self.space_info
+    }
+
+    fn space_info_mut(&mut self) -> &mut SpaceInfo {
+        &mut self.space_info
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{Fractal, FractalKind};
+    use crate::coordinate::Coordinate;
+    use crate::input_output_value::InputOutputValue;
+    use crate::library::noise::Noise;
+    use crate::node::Node;
+
+    #[test]
+    fn a_single_octave_is_plain_perlin_noise() {
+        let mut fractal = Fractal::new(FractalKind::Fbm, 3);
+        fractal.set_octaves(1).unwrap();
+        fractal.set_scale(Coordinate::new_xy(4.0, 4.0));
+        let mut noise = Noise::new(3);
+        noise.set_scale(Coordinate::new_xy(4.0, 4.0));
+
+        for position in [Coordinate::new_xy(3.0, 7.0), Coordinate::new_xy(12.0, 1.0)] {
+            assert_eq!(
+                fractal
+                    .generate(&position, &(16, 16), HashMap::new())
+                    .unwrap(),
+                noise
+                    .generate(&position, &(16, 16), HashMap::new())
+                    .unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn every_kind_stays_in_its_range() {
+        for kind in FractalKind::ALL {
+            let mut fractal = Fractal::new(kind, 11);
+            fractal.set_octaves(6).unwrap();
+            fractal.set_scale(Coordinate::new_xy(8.0, 8.0));
+
+            let min = if kind == FractalKind::Turbulence {
+                0.0
+            } else {
+                -1.0
+            };
+            for y in 0..16 {
+                for x in 0..16 {
+                    let position = Coordinate::new_xy(x as f64, y as f64);
+                    let value = fractal
+                        .generate(&position, &(16, 16), HashMap::new())
+                        .unwrap();
+
+                    assert!(
+                        matches!(value, InputOutputValue::Float(value) if (min..=1.0).contains(&value)),
+                        "{kind:?} at {position}: {value:?}"
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,418 @@
+use std::collections::HashMap;
+
+use rusvid_core::prelude::Pixel;
+
+use crate::coordinate::Coordinate;
+use crate::error::{GeneratorError, Result};
+use crate::group_definition::is_recursive;
+pub use crate::group_definition::{GroupDefinition, SharedGroup};
+use crate::input_output_value::InputOutputValue;
+use crate::node::{BatchInputs, BatchValue, Node, SpaceInfo};
+use crate::parameter::{unsupported_parameter, Parameter, ParameterKind, ParameterValue};
+use crate::port::{Port, DEFAULT_INPUT, DEFAULT_OUTPUT};
+
+/// The [`Node::node_type`] of [`Group`], groups are created from their definition instead of the
+/// [`NodeRegistry`](crate::registry::NodeRegistry).
+pub const NODE_TYPE: &str = "group";
+
+/// Runs the graph of a [`GroupDefinition`] as a single node.
+///
+/// The inputs of the node are the [`GroupInput`] nodes of the graph, the outputs are the channels of its output nodes.
+#[derive(Debug)]
+pub struct Group {
+    definition: SharedGroup,
+
+    space_info: SpaceInfo,
+}
+
+impl Group {
+    pub fn new(definition: SharedGroup) -> Self {
+        let mut space_info = SpaceInfo::default();
+        space_info.name = definition.read().unwrap().name.clone();
+        space_info.color = Pixel::new(100, 180, 255, 255);
+
+        Group {
+            definition,
+            space_info,
+        }
+    }
+
+    pub fn definition(&self) -> &SharedGroup {
+        &self.definition
+    }
+}
+
+impl Node for Group {
+    fn node_type(&self) -> &'static str {
+        NODE_TYPE
+    }
+
+    fn generate(
+        &self,
+        position: &Coordinate,
+        size: &(u32, u32),
+        input: HashMap<String, InputOutputValue>,
+    ) -> Result<InputOutputValue> {
+        self.generate_outputs(position, size, input)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| GeneratorError::UnknownPort {
+                node: self.space_info.name.clone(),
+                port: DEFAULT_OUTPUT.to_string(),
+            })
+    }
+
+    fn generate_outputs(
+        &self,
+        position: &Coordinate,
+        size: &(u32, u32),
+        input: HashMap<String, InputOutputValue>,
+    ) -> Result<Vec<InputOutputValue>> {
+        let mut inputs = BatchInputs::new();
+        for (port, value) in &input {
+            inputs.insert(port, BatchValue::Constant(*value));
+        }
+
+        Ok(self
+            .generate_batch_outputs(std::slice::from_ref(position), size, &inputs)?
+            .into_iter()
+            .map(|mut values| values.remove(0))
+            .collect())
+    }
+
+    fn generate_batch_outputs(
+        &self,
+        positions: &[Coordinate],
+        size: &(u32, u32),
+        inputs: &BatchInputs,
+    ) -> Result<Vec<Vec<InputOutputValue>>> {
+        // The graph isn't validated by `evaluate`, a recursive group would never stop.
+        if is_recursive(&self.definition) {
+            return Err(GeneratorError::RecursiveGroup(
+                self.definition.read().unwrap().name.clone(),
+            ));
+        }
+
+        let definition = self.definition.read().unwrap();
+        let mut values = definition.generator.evaluate(positions, size, inputs)?;
+
+        Ok(definition
+            .generator
+            .outputs()
+            .into_iter()
+            .map(|(channel, _)| values.remove(&channel).unwrap_or_default())
+            .collect())
+    }
+
+    fn inputs(&self) -> Vec<Port> {
+        self.definition.read().unwrap().generator.group_inputs()
+    }
+
+    fn outputs(&self) -> Vec<Port> {
+        self.definition
+            .read()
+            .unwrap()
+            .generator
+            .outputs()
+            .into_iter()
+            .map(|(channel, _)| Port::new(channel))
+            .collect()
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![Parameter::new(
+            "group",
+            ParameterKind::Text,
+            ParameterValue::Text(self.definition.read().unwrap().name.clone()),
+        )]
+    }
+
+    /// Renaming the group renames the definition of every instance.
+    fn set_parameter(&mut self, name: &str, value: ParameterValue) -> Result<()> {
+        match (name, value) {
+            ("group", ParameterValue::Text(group)) => self.definition.write().unwrap().name = group,
+            (name, value) => return Err(unsupported_parameter(self, name, &value)),
+        }
+
+        Ok(())
+    }
+
+    fn group(&self) -> Option<&SharedGroup> {
+        Some(&self.definition)
+    }
+
+    fn space_info(&self) -> &SpaceInfo {
+        &self.space_info
+    }
+
+    fn space_info_mut(&mut self) -> &mut SpaceInfo {
+        &mut self.space_info
+    }
+}
+
+/// An input of a group, outputs the value that the [`Group`] receives on the input `name`.
+///
+/// Outside of a group, e.g. while the graph of a group gets edited, the node outputs its default value.
+#[derive(Debug)]
+pub struct GroupInput {
+    name: String,
+    default: InputOutputValue,
+
+    space_info: SpaceInfo,
+}
+
+impl GroupInput {
+    pub fn new<S: Into<String>>(name: S, default: InputOutputValue) -> Self {
+        GroupInput {
+            name: name.into(),
+            default,
+
+            space_info: SpaceInfo::default(),
+        }
+    }
+}
+
+impl Default for GroupInput {
+    fn default() -> Self {
+        GroupInput::new(DEFAULT_INPUT, InputOutputValue::Float(0.0))
+    }
+}
+
+impl Node for GroupInput {
+    fn node_type(&self) -> &'static str {
+        "group.input"
+    }
+
+    fn generate(
+        &self,
+        _position: &Coordinate,
+        _size: &(u32, u32),
+        _input: HashMap<String, InputOutputValue>,
+    ) -> Result<InputOutputValue> {
+        Ok(self.default)
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![
+            Parameter::new(
+                "name",
+                ParameterKind::Text,
+                ParameterValue::Text(self.name.clone()),
+            ),
+            Parameter::new(
+                "default",
+                ParameterKind::Value,
+                ParameterValue::Value(self.default),
+            ),
+        ]
+    }
+
+    fn set_parameter(&mut self, name: &str, value: ParameterValue) -> Result<()> {
+        match (name, value) {
+            ("name", ParameterValue::Text(input)) => self.name = input,
+            ("default", ParameterValue::Value(default)) => self.default = default,
+            (name, value) => return Err(unsupported_parameter(self, name, &value)),
+        }
+
+        Ok(())
+    }
+
+    fn group_input(&self) -> Option<Port> {
+        Some(Port::new(&self.name).optional(self.default))
+    }
+
+    fn space_info(&self) -> &SpaceInfo {
+        &self.space_info
+    }
+
+    fn space_info_mut(&mut self) -> &mut SpaceInfo {
+        &mut self.space_info
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use rusvid_core::pixel::Pixel;
+
+    use super::{Group, GroupDefinition, GroupInput, SharedGroup};
+    use crate::coordinate::Coordinate;
+    use crate::diagnostic::DiagnosticKind;
+    use crate::error::GeneratorError;
+    use crate::generator::Generator;
+    use crate::input_output_value::InputOutputValue;
+    use crate::library::noise::Noise;
+    use crate::library::split::Split;
+    use crate::library::static_value::StaticValue;
+    use crate::link::Link;
+
+    pub(crate) fn example_group() -> SharedGroup {
+        let mut inner = Generator::new();
+
+        let input = inner.add_node(GroupInput::new(
+            "color",
+            InputOutputValue::Pixel(Pixel::new(0, 0, 255, 255)),
+        ));
+        let split = inner.add_node(Split::new());
+        let green = inner.add_output("green").unwrap();
+        inner
+            .add_edge(Link::new(input, inner.output_node()).unwrap())
+            .unwrap();
+        inner.add_edge(Link::new(input, split).unwrap()).unwrap();
+        inner
+            .add_edge_sockets(Link::new(split, green).unwrap(), "g", "input")
+            .unwrap();
+
+        GroupDefinition::new("passthrough", inner).shared()
+    }
+
+    #[test]
+    fn every_instance_runs_the_graph_of_the_group() {
+        let group = example_group();
+
+        let mut g = Generator::new();
+        let color = g.add_node(StaticValue::new(InputOutputValue::Pixel(Pixel::new(
+            255, 0, 100, 255,
+        ))));
+        let first = g.add_node(Group::new(group.clone()));
+        let second = g.add_node(Group::new(group));
+        g.add_edge_named(Link::new(color, first).unwrap(), "color")
+            .unwrap();
+        g.add_edge(Link::new(first, g.output_node()).unwrap())
+            .unwrap();
+
+        let plane = g.generate(2, 2).unwrap();
+        assert_eq!(plane.as_data_flatten(), [255, 0, 100, 255].repeat(4));
+
+        // The input of the second instance isn't connected and uses the default of the group input.
+        let plane = g.generate_from(second, 2, 2).unwrap();
+        assert_eq!(plane.as_data_flatten(), [0, 0, 255, 255].repeat(4));
+
+        g.add_edge_sockets(
+            Link::new(second, g.output_node()).unwrap(),
+            "green",
+            "input",
+        )
+        .unwrap();
+        let plane = g.generate(2, 2).unwrap();
+        assert_eq!(plane.as_data_flatten(), [0, 0, 0, 255].repeat(4));
+    }
+
+    #[test]
+    fn groups_tile_like_the_graph_around_them() {
+        let mut inner = Generator::new();
+        let noise = inner.add_node({
+            let mut noise = Noise::new(2);
+            noise.set_scale(Coordinate::new_xy(3.0, 3.0));
+            noise
+        });
+        inner
+            .add_edge(Link::new(noise, inner.output_node()).unwrap())
+            .unwrap();
+        let group = GroupDefinition::new("noise", inner).shared();
+
+        let mut g = Generator::new();
+        let instance = g.add_node(Group::new(group.clone()));
+        g.add_edge(Link::new(instance, g.output_node()).unwrap())
+            .unwrap();
+        let plain = g.generate(16, 16).unwrap();
+        g.set_tileable(true);
+        let tiled = g.generate(16, 16).unwrap();
+
+        // The definition is shared with other graphs and keeps its own mode.
+        let mut definition = group.write().unwrap();
+        assert!(!definition.generator.tileable());
+        definition.generator.set_tileable(true);
+        let expected = definition.generator.generate(16, 16).unwrap();
+        assert_eq!(tiled.as_data_flatten(), expected.as_data_flatten());
+        assert_ne!(tiled.as_data_flatten(), plain.as_data_flatten());
+    }
+
+    #[test]
+    fn a_group_inside_of_itself_is_reported() {
+        let group = example_group();
+        let instance = Group::new(group.clone());
+        group.write().unwrap().generator.add_node(instance);
+
+        let mut g = Generator::new();
+        let outer = g.add_node(Group::new(group));
+        g.add_edge(Link::new(outer, g.output_node()).unwrap())
+            .unwrap();
+
+        assert!(g
+            .validate()
+            .iter()
+            .any(|d| d.node == outer && d.kind == DiagnosticKind::RecursiveGroup));
+        assert!(matches!(
+            g.generate(2, 2),
+            Err(GeneratorError::InvalidGraph(_))
+        ));
+
+        // Evaluating the group on its own doesn't validate the graph.
+        let instance = g.internal_graph[outer].read().unwrap();
+        assert!(matches!(
+            instance.generate(&Coordinate::new_xy(0.0, 0.0), &(2, 2), Default::default()),
+            Err(GeneratorError::RecursiveGroup(name)) if name == "passthrough"
+        ));
+    }
+
+    #[test]
+    fn instances_are_rendered_again_after_their_definition_changed() {
+        let group = example_group();
+        let mut wrapper = Generator::new();
+        let inner = wrapper.add_node(Group::new(group.clone()));
+        wrapper
+            .add_edge(Link::new(inner, wrapper.output_node()).unwrap())
+            .unwrap();
+        let wrapper = GroupDefinition::new("wrapper", wrapper).shared();
+
+        let mut g = Generator::new();
+        g.set_caching(true);
+        let direct = g.add_node(Group::new(group.clone()));
+        let nested = g.add_node(Group::new(wrapper));
+        g.add_edge(Link::new(direct, g.output_node()).unwrap())
+            .unwrap();
+        assert_eq!(
+            g.generate_from(direct, 1, 1).unwrap().as_data_flatten(),
+            [0, 0, 255, 255]
+        );
+        assert_eq!(
+            g.generate_from(nested, 1, 1).unwrap().as_data_flatten(),
+            [0, 0, 255, 255]
+        );
+
+        {
+            let mut definition = group.write().unwrap();
+            let inner = &mut definition.generator;
+            let green = inner.add_node(StaticValue::new(InputOutputValue::Pixel(Pixel::new(
+                0, 255, 0, 255,
+            ))));
+            inner
+                .add_edge(Link::new(green, inner.output_node()).unwrap())
+                .unwrap();
+        }
+
+        assert_eq!(
+            g.generate_from(direct, 1, 1).unwrap().as_data_flatten(),
+            [0, 255, 0, 255]
+        );
+        assert_eq!(
+            g.generate_from(nested, 1, 1).unwrap().as_data_flatten(),
+            [0, 255, 0, 255]
+        );
+    }
+
+    #[test]
+    fn group_inputs_need_unique_names() {
+        let mut g = Generator::new();
+        let first = g.add_node(GroupInput::new("color", InputOutputValue::Float(0.0)));
+        let second = g.add_node(GroupInput::new("color", InputOutputValue::Float(1.0)));
+
+        let duplicates = g
+            .validate()
+            .into_iter()
+            .filter(|d| d.kind == DiagnosticKind::DuplicateGroupInput)
+            .map(|d| d.node)
+            .collect::<Vec<_>>();
+        assert_eq!(duplicates, [first, second]);
+    }
+}
@@ -1,14 +1,16 @@
 use std::collections::HashMap;
 
-use anyhow::Result;
 use itertools::Itertools;
 use rusvid_core::pixel::Pixel;
 use rusvid_core::plane::Plane;
 
 use crate::bitmap::BitmapChar;
 use crate::coordinate::Coordinate;
-use crate::input_output_value::InputOutputValue;
-use crate::node::{Node, SpaceInfo};
+use crate::error::{GeneratorError, Result};
+use crate::input_output_value::{InputOutputKind, InputOutputValue};
+use crate::node::{BatchInputs, Node, SpaceInfo};
+use crate::parameter::{unsupported_parameter, Parameter, ParameterKind, ParameterValue};
+use crate::port::{Port, DEFAULT_INPUT, DEFAULT_OUTPUT};
 use crate::utils::render_square;
 
 #[derive(Debug)]
@@ -20,30 +22,43 @@ pub struct Map {
 }
 
 impl Map {
-    pub fn new(steps: Vec<(InputOutputValue, f64)>) -> Self {
-        assert!(steps.len() >= 2);
+    pub fn new(steps: Vec<(InputOutputValue, f64)>) -> Result<Self> {
+        Map::check_steps(&steps)?;
 
-        Map {
+        Ok(Map {
             steps,
 
             space_info: SpaceInfo::default(),
+        })
+    }
+
+    fn check_steps(steps: &[(InputOutputValue, f64)]) -> Result<()> {
+        if steps.len() < 2 {
+            return Err(GeneratorError::InvalidParameter {
+                parameter: "steps".to_string(),
+                reason: format!("expected at least 2 steps but got {}", steps.len()),
+            });
         }
+
+        Ok(())
     }
-}
 
-impl Node for Map {
-    fn generate(
-        &self,
-        _position: &Coordinate,
-        _size: &(u32, u32),
-        input: HashMap<String, InputOutputValue>,
-    ) -> Result<InputOutputValue> {
-        let (_, first_input) = input.iter().next().unwrap();
+    pub fn steps(&self) -> &[(InputOutputValue, f64)] {
+        &self.steps
+    }
+
+    pub fn set_steps(&mut self, steps: Vec<(InputOutputValue, f64)>) -> Result<()> {
+        Map::check_steps(&steps)?;
+        self.steps = steps;
+
+        Ok(())
+    }
 
+    fn map(&self, input: InputOutputValue) -> Result<InputOutputValue> {
         // TODO copy method for the windows of the `self.steps`` from `render`
-        let r = first_input.r_percentage()?;
-        let g = first_input.g_percentage()?;
-        let b = first_input.b_percentage()?;
+        let r = input.r_percentage()?;
+        let g = input.g_percentage()?;
+        let b = input.b_percentage()?;
         let avg = (r + g + b) / 3.0;
 
         // TODO the following functions should always return at least something because of `len(self.steps) >= 2`
@@ -96,6 +111,51 @@ impl Node for Map {
             )))
         }
     }
+}
+
+impl Node for Map {
+    fn node_type(&self) -> &'static str {
+        "map"
+    }
+
+    fn generate(
+        &self,
+        _position: &Coordinate,
+        _size: &(u32, u32),
+        input: HashMap<String, InputOutputValue>,
+    ) -> Result<InputOutputValue> {
+        let first_input = self.input_value(&input, DEFAULT_INPUT)?;
+
+        self.map(first_input)
+    }
+
+    fn generate_batch(
+        &self,
+        positions: &[Coordinate],
+        _size: &(u32, u32),
+        inputs: &BatchInputs,
+    ) -> Result<Vec<InputOutputValue>> {
+        let input = self.batch_input(inputs, DEFAULT_INPUT)?;
+
+        (0..positions.len())
+            .map(|index| self.map(input.get(index)))
+            .collect()
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![Parameter::new(
+            "steps",
+            ParameterKind::Gradient,
+            ParameterValue::Gradient(self.steps.clone()),
+        )]
+    }
+
+    fn set_parameter(&mut self, name: &str, value: ParameterValue) -> Result<()> {
+        match (name, value) {
+            ("steps", ParameterValue::Gradient(steps)) => self.set_steps(steps),
+            (name, value) => Err(unsupported_parameter(self, name, &value)),
+        }
+    }
 
     fn render(&self, plane: &mut Plane) -> Result<()> {
         let space_info = self.space_info();
@@ -211,6 +271,14 @@ impl Node for Map {
         Ok(())
     }
 
+    fn inputs(&self) -> Vec<Port> {
+        vec![Port::new(DEFAULT_INPUT)]
+    }
+
+    fn outputs(&self) -> Vec<Port> {
+        vec![Port::new(DEFAULT_OUTPUT).with_kinds(&[InputOutputKind::Pixel])]
+    }
+
     fn space_info(&self) -> &SpaceInfo {
         &self.space_info
     }
@@ -219,3 +287,23 @@ impl Node for Map {
         &mut self.space_info
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::Map;
+    use crate::error::GeneratorError;
+    use crate::input_output_value::InputOutputValue;
+
+    #[test]
+    fn needs_at_least_two_steps() {
+        assert!(matches!(
+            Map::new(vec![(InputOutputValue::Float(0.0), 0.0)]),
+            Err(GeneratorError::InvalidParameter { parameter, .. }) if parameter == "steps"
+        ));
+        assert!(Map::new(vec![
+            (InputOutputValue::Float(0.0), 0.0),
+            (InputOutputValue::Float(1.0), 1.0)
+        ])
+        .is_ok());
+    }
+}
@@ -0,0 +1,610 @@
+use std::collections::HashMap;
+
+use crate::coordinate::Coordinate;
+use crate::error::{GeneratorError, Result};
+use crate::input_output_value::{InputOutputKind, InputOutputValue};
+use crate::node::{BatchInputs, Node, SpaceInfo};
+use crate::parameter::{unsupported_parameter, Parameter, ParameterKind, ParameterValue};
+use crate::port::{Port, DEFAULT_INPUT, DEFAULT_OUTPUT};
+
+/// The kinds of values the output of a [`Math`] node can have.
+const KINDS: [InputOutputKind; 3] = [
+    InputOutputKind::Float,
+    InputOutputKind::F64X3Array,
+    InputOutputKind::F64X4Array,
+];
+
+/// The kinds of values the inputs of a [`Math`] node can handle, the components of byte arrays and the channels of
+/// pixels are converted to floats from `0.0` to `1.0`.
+const INPUT_KINDS: [InputOutputKind; 6] = [
+    InputOutputKind::Float,
+    InputOutputKind::F64X3Array,
+    InputOutputKind::F64X4Array,
+    InputOutputKind::U8X3Array,
+    InputOutputKind::U8X4Array,
+    InputOutputKind::Pixel,
+];
+
+/// The most inputs any [`MathOperation`] has.
+const MAX_OPERANDS: usize = 5;
+
+/// The arithmetic of a [`Math`] node, every operation has its own inputs, see [`MathOperation::inputs`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MathOperation {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Power,
+    Min,
+    Max,
+    Abs,
+    /// The remainder of the division, with the sign of the divisor like `x - y * floor(x / y)`.
+    Modulo,
+    Floor,
+    /// The part after the decimal point, `x - floor(x)`.
+    Fract,
+    /// Sine of an angle in radians.
+    Sin,
+    /// Cosine of an angle in radians.
+    Cos,
+    Clamp,
+    /// Linear interpolation from `input` at `t = 0.0` to `target` at `t = 1.0`.
+    Lerp,
+    /// Smooth Hermite interpolation from `0.0` at `edge0` to `1.0` at `edge1`.
+    Smoothstep,
+    /// `0.0` below `edge` and `1.0` from there on.
+    Step,
+    /// Maps the range from `from_min` to `from_max` linearly onto the range from `to_min` to `to_max`.
+    Remap,
+}
+
+impl MathOperation {
+    pub const ALL: [MathOperation; 18] = [
+        MathOperation::Add,
+        MathOperation::Subtract,
+        MathOperation::Multiply,
+        MathOperation::Divide,
+        MathOperation::Power,
+        MathOperation::Min,
+        MathOperation::Max,
+        MathOperation::Abs,
+        MathOperation::Modulo,
+        MathOperation::Floor,
+        MathOperation::Fract,
+        MathOperation::Sin,
+        MathOperation::Cos,
+        MathOperation::Clamp,
+        MathOperation::Lerp,
+        MathOperation::Smoothstep,
+        MathOperation::Step,
+        MathOperation::Remap,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            MathOperation::Add => "add",
+            MathOperation::Subtract => "subtract",
+            MathOperation::Multiply => "multiply",
+            MathOperation::Divide => "divide",
+            MathOperation::Power => "power",
+            MathOperation::Min => "min",
+            MathOperation::Max => "max",
+            MathOperation::Abs => "abs",
+            MathOperation::Modulo => "modulo",
+            MathOperation::Floor => "floor",
+            MathOperation::Fract => "fract",
+            MathOperation::Sin => "sin",
+            MathOperation::Cos => "cos",
+            MathOperation::Clamp => "clamp",
+            MathOperation::Lerp => "lerp",
+            MathOperation::Smoothstep => "smoothstep",
+            MathOperation::Step => "step",
+            MathOperation::Remap => "remap",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<MathOperation> {
+        MathOperation::ALL
+            .into_iter()
+            .find(|operation| operation.name() == name)
+    }
+
+    /// The [`Node::node_type`] of a [`Math`] node with this operation.
+    pub fn node_type(&self) -> &'static str {
+        match self {
+            MathOperation::Add => "math.add",
+            MathOperation::Subtract => "math.subtract",
+            MathOperation::Multiply => "math.multiply",
+            MathOperation::Divide => "math.divide",
+            MathOperation::Power => "math.power",
+            MathOperation::Min => "math.min",
+            MathOperation::Max => "math.max",
+            MathOperation::Abs => "math.abs",
+            MathOperation::Modulo => "math.modulo",
+            MathOperation::Floor => "math.floor",
+            MathOperation::Fract => "math.fract",
+            MathOperation::Sin => "math.sin",
+            MathOperation::Cos => "math.cos",
+            MathOperation::Clamp => "math.clamp",
+            MathOperation::Lerp => "math.lerp",
+            MathOperation::Smoothstep => "math.smoothstep",
+            MathOperation::Step => "math.step",
+            MathOperation::Remap => "math.remap",
+        }
+    }
+
+    /// Human readable name, e.g. for the node registry.
+    pub fn label(&self) -> &'static str {
+        match self {
+            MathOperation::Add => "Add",
+            MathOperation::Subtract => "Subtract",
+            MathOperation::Multiply => "Multiply",
+            MathOperation::Divide => "Divide",
+            MathOperation::Power => "Power",
+            MathOperation::Min => "Minimum",
+            MathOperation::Max => "Maximum",
+            MathOperation::Abs => "Absolute",
+            MathOperation::Modulo => "Modulo",
+            MathOperation::Floor => "Floor",
+            MathOperation::Fract => "Fraction",
+            MathOperation::Sin => "Sine",
+            MathOperation::Cos => "Cosine",
+            MathOperation::Clamp => "Clamp",
+            MathOperation::Lerp => "Lerp",
+            MathOperation::Smoothstep => "Smoothstep",
+            MathOperation::Step => "Step",
+            MathOperation::Remap => "Remap Range",
+        }
+    }
+
+    /// The operands in the order [`MathOperation::apply`] expects them. The first one is always [`DEFAULT_INPUT`] and
+    /// required, the others fall back to a neutral value.
+    pub fn inputs(&self) -> Vec<Port> {
+        let port = |name: &str| Port::new(name).with_kinds(&INPUT_KINDS);
+        let optional =
+            |name: &str, default: f64| port(name).optional(InputOutputValue::Float(default));
+
+        match self {
+            MathOperation::Add
+            | MathOperation::Subtract
+            | MathOperation::Min
+            | MathOperation::Max => {
+                vec![port(DEFAULT_INPUT), optional("operand", 0.0)]
+            }
+            MathOperation::Multiply
+            | MathOperation::Divide
+            | MathOperation::Power
+            | MathOperation::Modulo => vec![port(DEFAULT_INPUT), optional("operand", 1.0)],
+            MathOperation::Abs
+            | MathOperation::Floor
+            | MathOperation::Fract
+            | MathOperation::Sin
+            | MathOperation::Cos => vec![port(DEFAULT_INPUT)],
+            MathOperation::Clamp => vec![
+                port(DEFAULT_INPUT),
+                optional("min", 0.0),
+                optional("max", 1.0),
+            ],
+            MathOperation::Lerp => vec![
+                port(DEFAULT_INPUT),
+                optional("target", 1.0),
+                optional("t", 0.5),
+            ],
+            MathOperation::Smoothstep => vec![
+                port(DEFAULT_INPUT),
+                optional("edge0", 0.0),
+                optional("edge1", 1.0),
+            ],
+            MathOperation::Step => vec![port(DEFAULT_INPUT), optional("edge", 0.5)],
+            MathOperation::Remap => vec![
+                port(DEFAULT_INPUT),
+                optional("from_min", -1.0),
+                optional("from_max", 1.0),
+                optional("to_min", 0.0),
+                optional("to_max", 1.0),
+            ],
+        }
+    }
+
+    /// Calculates the operation for one component, `operands` are ordered like [`MathOperation::inputs`].
+    ///
+    /// Results that aren't finite, e.g. of a division by zero, are `0.0`.
+    pub fn apply(&self, operands: &[f64]) -> f64 {
+        let operand = |index: usize| operands.get(index).copied().unwrap_or(0.0);
+        let (x, y) = (operand(0), operand(1));
+
+        let value = match self {
+            MathOperation::Add => x + y,
+            MathOperation::Subtract => x - y,
+            MathOperation::Multiply => x * y,
+            MathOperation::Divide => x / y,
+            MathOperation::Power => x.powf(y),
+            MathOperation::Min => x.min(y),
+            MathOperation::Max => x.max(y),
+            MathOperation::Abs => x.abs(),
+            MathOperation::Modulo => x - y * (x / y).floor(),
+            MathOperation::Floor => x.floor(),
+            MathOperation::Fract => x - x.floor(),
+            MathOperation::Sin => x.sin(),
+            MathOperation::Cos => x.cos(),
+            MathOperation::Clamp => x.max(y).min(operand(2)),
+            MathOperation::Lerp => x + (y - x) * operand(2),
+            MathOperation::Smoothstep => {
+                let (edge0, edge1) = (y, operand(2));
+                if edge0 == edge1 {
+                    if x < edge0 {
+                        0.0
+                    } else {
+                        1.0
+                    }
+                } else {
+                    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+                    t * t * (3.0 - 2.0 * t)
+                }
+            }
+            MathOperation::Step => {
+                if x < y {
+                    0.0
+                } else {
+                    1.0
+                }
+            }
+            MathOperation::Remap => {
+                let (from_min, from_max) = (y, operand(2));
+                let (to_min, to_max) = (operand(3), operand(4));
+
+                to_min + (x - from_min) / (from_max - from_min) * (to_max - to_min)
+            }
+        };
+
+        if value.is_finite() {
+            value
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Applies a [`MathOperation`] to floats, [`InputOutputValue::F64X3Array`] and [`InputOutputValue::F64X4Array`].
+///
+/// Arrays are calculated component-wise. A float operand is used for every component of the other operands, but
+/// arrays of different lengths can't be combined. Byte arrays and pixels are converted to floats from `0.0` to `1.0`
+/// first, the result is always made of floats.
+#[derive(Debug)]
+pub struct Math {
+    operation: MathOperation,
+
+    space_info: SpaceInfo,
+}
+
+impl Math {
+    pub fn new(operation: MathOperation) -> Self {
+        Math {
+            operation,
+
+            space_info: SpaceInfo::default(),
+        }
+    }
+
+    pub fn operation(&self) -> MathOperation {
+        self.operation
+    }
+
+    /// Changes the operation.
+    ///
+    /// The inputs change with the operation, [`Generator::set_parameter`](crate::generator::Generator::set_parameter)
+    /// removes the edges to inputs that the new operation doesn't have.
+    pub fn set_operation(&mut self, operation: MathOperation) {
+        self.operation = operation;
+    }
+
+    fn components(&self, port: &str, value: InputOutputValue) -> Result<Components> {
+        let bytes = |values: &[u8]| {
+            let mut components = Components::new(value.kind(), values.len());
+            for (component, value) in components.values.iter_mut().zip(values) {
+                *component = *value as f64 / 255.0;
+            }
+            components
+        };
+
+        match value {
+            InputOutputValue::Float(value) => {
+                Ok(Components::from_slice(InputOutputKind::Float, &[value]))
+            }
+            InputOutputValue::F64X3Array(values) => {
+                Ok(Components::from_slice(InputOutputKind::F64X3Array, &values))
+            }
+            InputOutputValue::F64X4Array(values) => {
+                Ok(Components::from_slice(InputOutputKind::F64X4Array, &values))
+            }
+            InputOutputValue::U8X3Array(values) => Ok(bytes(&values)),
+            InputOutputValue::U8X4Array(values) => Ok(bytes(&values)),
+            InputOutputValue::Pixel(pixel) => Ok(bytes(&pixel.to_raw())),
+            InputOutputValue::Nothing => Err(GeneratorError::TypeMismatch {
+                node: self.space_info.name.clone(),
+                port: port.to_string(),
+                expected: INPUT_KINDS.to_vec(),
+                found: value.kind(),
+            }),
+        }
+    }
+
+    /// `operands` are the values of the inputs in the order of [`MathOperation::inputs`].
+    fn calculate(&self, operands: &[(&str, InputOutputValue)]) -> Result<InputOutputValue> {
+        let mut components = [Components::default(); MAX_OPERANDS];
+        for (operand, (port, value)) in components.iter_mut().zip(operands) {
+            *operand = self.components(port, *value)?;
+        }
+        let components = &components[..operands.len()];
+
+        let width = components
+            .iter()
+            .map(|operand| operand.len)
+            .max()
+            .unwrap_or(1);
+        let kind = |width: usize| match width {
+            1 => InputOutputKind::Float,
+            3 => InputOutputKind::F64X3Array,
+            _ => InputOutputKind::F64X4Array,
+        };
+        if let Some(((port, _), operand)) = operands
+            .iter()
+            .zip(components)
+            .find(|(_, operand)| operand.len != 1 && operand.len != width)
+        {
+            return Err(GeneratorError::TypeMismatch {
+                node: self.space_info.name.clone(),
+                port: port.to_string(),
+                expected: vec![InputOutputKind::Float, kind(width)],
+                found: operand.kind,
+            });
+        }
+
+        let mut values = [0.0; 4];
+        for (index, value) in values.iter_mut().enumerate().take(width) {
+            let mut arguments = [0.0; MAX_OPERANDS];
+            for (argument, operand) in arguments.iter_mut().zip(components) {
+                *argument = operand.values[index.min(operand.len - 1)];
+            }
+
+            *value = self.operation.apply(&arguments[..components.len()]);
+        }
+
+        Ok(match width {
+            1 => InputOutputValue::Float(values[0]),
+            3 => InputOutputValue::F64X3Array([values[0], values[1], values[2]]),
+            _ => InputOutputValue::F64X4Array([values[0], values[1], values[2], values[3]]),
+        })
+    }
+}
+
+/// The components of one operand, a float has a single one.
+#[derive(Debug, Clone, Copy)]
+struct Components {
+    /// The kind of the value the components came from.
+    kind: InputOutputKind,
+    values: [f64; 4],
+    len: usize,
+}
+
+impl Components {
+    fn new(kind: InputOutputKind, len: usize) -> Self {
+        Components {
+            kind,
+            values: [0.0; 4],
+            len,
+        }
+    }
+
+    fn from_slice(kind: InputOutputKind, values: &[f64]) -> Self {
+        let mut components = Components::new(kind, values.len());
+        components.values[..values.len()].copy_from_slice(values);
+        components
+    }
+}
+
+impl Default for Components {
+    fn default() -> Self {
+        Components::new(InputOutputKind::Float, 1)
+    }
+}
+
+impl Node for Math {
+    fn node_type(&self) -> &'static str {
+        self.operation.node_type()
+    }
+
+    fn generate(
+        &self,
+        _position: &Coordinate,
+        _size: &(u32, u32),
+        input: HashMap<String, InputOutputValue>,
+    ) -> Result<InputOutputValue> {
+        let ports = self.inputs();
+        let operands = ports
+            .iter()
+            .map(|port| Ok((port.name.as_str(), self.input_value(&input, &port.name)?)))
+            .collect::<Result<Vec<_>>>()?;
+
+        self.calculate(&operands)
+    }
+
+    fn generate_batch(
+        &self,
+        positions: &[Coordinate],
+        _size: &(u32, u32),
+        inputs: &BatchInputs,
+    ) -> Result<Vec<InputOutputValue>> {
+        // The inputs are looked up once, the samples only copy their values.
+        let ports = self.inputs();
+        let values = ports
+            .iter()
+            .map(|port| Ok((port.name.as_str(), self.batch_input(inputs, &port.name)?)))
+            .collect::<Result<Vec<_>>>()?;
+
+        (0..positions.len())
+            .map(|index| {
+                let mut operands = [("", InputOutputValue::Nothing); MAX_OPERANDS];
+                for (operand, (port, value)) in operands.iter_mut().zip(&values) {
+                    *operand = (port, value.get(index));
+                }
+
+                self.calculate(&operands[..values.len()])
+            })
+            .collect()
+    }
+
+    fn inputs(&self) -> Vec<Port> {
+        self.operation.inputs()
+    }
+
+    fn outputs(&self) -> Vec<Port> {
+        vec![Port::new(DEFAULT_OUTPUT).with_kinds(&KINDS)]
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![Parameter::new(
+            "operation",
+            ParameterKind::Enum {
+                variants: MathOperation::ALL
+                    .iter()
+                    .map(|operation| operation.name().to_string())
+                    .collect(),
+            },
+            ParameterValue::Enum(self.operation.name().to_string()),
+        )]
+    }
+
+    fn set_parameter(&mut self, name: &str, value: ParameterValue) -> Result<()> {
+        match (name, value) {
+            ("operation", ParameterValue::Enum(operation)) => {
+                let operation = MathOperation::from_name(&operation).ok_or_else(|| {
+                    GeneratorError::InvalidParameter {
+                        parameter: name.to_string(),
+                        reason: format!("unknown operation {operation:?}"),
+                    }
+                })?;
+                self.set_operation(operation);
+
+                Ok(())
+            }
+            (name, value) => Err(unsupported_parameter(self, name, &value)),
+        }
+    }
+
+    fn space_info(&self) -> &SpaceInfo {
+        &self.space_info
+    }
+
+    fn space_info_mut(&mut self) -> &mut SpaceInfo {
+        &mut self.space_info
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rusvid_core::pixel::Pixel;
+
+    use super::{Math, MathOperation};
+    use crate::error::GeneratorError;
+    use crate::input_output_value::{InputOutputKind, InputOutputValue};
+    use crate::port::DEFAULT_INPUT;
+
+    #[test]
+    fn operations_calculate_per_component() {
+        let cases = [
+            (MathOperation::Add, vec![2.0, 3.0], 5.0),
+            (MathOperation::Subtract, vec![2.0, 3.0], -1.0),
+            (MathOperation::Multiply, vec![2.0, 3.0], 6.0),
+            (MathOperation::Divide, vec![3.0, 2.0], 1.5),
+            (MathOperation::Divide, vec![3.0, 0.0], 0.0),
+            (MathOperation::Power, vec![2.0, 3.0], 8.0),
+            (MathOperation::Min, vec![2.0, 3.0], 2.0),
+            (MathOperation::Max, vec![2.0, 3.0], 3.0),
+            (MathOperation::Abs, vec![-2.5], 2.5),
+            (MathOperation::Modulo, vec![-1.0, 3.0], 2.0),
+            (MathOperation::Floor, vec![-1.5], -2.0),
+            (MathOperation::Fract, vec![-1.25], 0.75),
+            (MathOperation::Sin, vec![std::f64::consts::FRAC_PI_2], 1.0),
+            (MathOperation::Cos, vec![std::f64::consts::PI], -1.0),
+            (MathOperation::Clamp, vec![1.5, 0.0, 1.0], 1.0),
+            (MathOperation::Lerp, vec![2.0, 4.0, 0.25], 2.5),
+            (MathOperation::Smoothstep, vec![0.5, 0.0, 2.0], 0.15625),
+            (MathOperation::Step, vec![0.4, 0.5], 0.0),
+            (MathOperation::Remap, vec![0.0, -1.0, 1.0, 0.0, 10.0], 5.0),
+        ];
+
+        for (operation, operands, expected) in cases {
+            let actual = operation.apply(&operands);
+            assert!((actual - expected).abs() < 1e-9, "{operation:?}: {actual}");
+        }
+    }
+
+    #[test]
+    fn floats_are_broadcast_to_arrays() {
+        let math = Math::new(MathOperation::Multiply);
+        let operands = [
+            (DEFAULT_INPUT, InputOutputValue::F64X3Array([1.0, 2.0, 3.0])),
+            ("operand", InputOutputValue::Float(2.0)),
+        ];
+        assert_eq!(
+            math.calculate(&operands).unwrap(),
+            InputOutputValue::F64X3Array([2.0, 4.0, 6.0])
+        );
+
+        let mismatched = [
+            (DEFAULT_INPUT, InputOutputValue::F64X3Array([1.0, 2.0, 3.0])),
+            (
+                "operand",
+                InputOutputValue::F64X4Array([1.0, 2.0, 3.0, 4.0]),
+            ),
+        ];
+        assert!(matches!(
+            math.calculate(&mismatched),
+            Err(GeneratorError::TypeMismatch { port, .. }) if port == DEFAULT_INPUT
+        ));
+    }
+
+    #[test]
+    fn bytes_are_converted_to_floats() {
+        let math = Math::new(MathOperation::Multiply);
+        let operands = [
+            (DEFAULT_INPUT, InputOutputValue::U8X3Array([0, 51, 255])),
+            ("operand", InputOutputValue::Float(2.0)),
+        ];
+        assert_eq!(
+            math.calculate(&operands).unwrap(),
+            InputOutputValue::F64X3Array([0.0, 0.4, 2.0])
+        );
+    }
+
+    #[test]
+    fn pixels_are_calculated_per_channel() {
+        let math = Math::new(MathOperation::Multiply);
+        let operands = [
+            (
+                DEFAULT_INPUT,
+                InputOutputValue::Pixel(Pixel::new(255, 0, 51, 255)),
+            ),
+            ("operand", InputOutputValue::Float(2.0)),
+        ];
+        assert_eq!(
+            math.calculate(&operands).unwrap(),
+            InputOutputValue::F64X4Array([2.0, 0.0, 0.4, 2.0])
+        );
+
+        let mismatched = [
+            (
+                DEFAULT_INPUT,
+                InputOutputValue::Pixel(Pixel::new(255, 0, 51, 255)),
+            ),
+            ("operand", InputOutputValue::F64X3Array([1.0, 2.0, 3.0])),
+        ];
+        assert!(matches!(
+            math.calculate(&mismatched),
+            Err(GeneratorError::TypeMismatch { port, found: InputOutputKind::F64X3Array, .. })
+                if port == "operand"
+        ));
+    }
+}
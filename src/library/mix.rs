@@ -1,68 +1,341 @@
 use std::collections::HashMap;
 
-use anyhow::Result;
 use rusvid_core::prelude::Pixel;
 
 use crate::coordinate::Coordinate;
-use crate::input_output_value::InputOutputValue;
-use crate::node::{Node, SpaceInfo};
+use crate::error::{GeneratorError, Result};
+use crate::input_output_value::{InputOutputKind, InputOutputValue};
+use crate::node::{BatchInputs, Node, SpaceInfo};
+use crate::parameter::{unsupported_parameter, Parameter, ParameterKind, ParameterValue};
+use crate::port::{Port, DEFAULT_OUTPUT};
 
+/// How a [`Mix`] node combines the layer `input1` with the base `input2`.
+///
+/// The formulas work on channels between `0.0` and `1.0`, the alpha channel is always interpolated linearly except for
+/// [`BlendMode::AlphaOver`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlendMode {
+    /// The layer replaces the base, the plain linear interpolation.
+    Normal,
+    Multiply,
+    Screen,
+    /// [`BlendMode::Multiply`] for dark and [`BlendMode::Screen`] for light parts of the base.
+    Overlay,
+    SoftLight,
+    /// Like [`BlendMode::Overlay`] with the layer deciding between multiply and screen.
+    HardLight,
+    Add,
+    /// Subtracts the layer from the base.
+    Subtract,
+    Difference,
+    Darken,
+    Lighten,
+    ColorDodge,
+    ColorBurn,
+    /// Puts the layer on top of the base according to the alpha channels of both.
+    AlphaOver,
+}
+
+impl BlendMode {
+    pub const ALL: [BlendMode; 14] = [
+        BlendMode::Normal,
+        BlendMode::Multiply,
+        BlendMode::Screen,
+        BlendMode::Overlay,
+        BlendMode::SoftLight,
+        BlendMode::HardLight,
+        BlendMode::Add,
+        BlendMode::Subtract,
+        BlendMode::Difference,
+        BlendMode::Darken,
+        BlendMode::Lighten,
+        BlendMode::ColorDodge,
+        BlendMode::ColorBurn,
+        BlendMode::AlphaOver,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BlendMode::Normal => "normal",
+            BlendMode::Multiply => "multiply",
+            BlendMode::Screen => "screen",
+            BlendMode::Overlay => "overlay",
+            BlendMode::SoftLight => "soft_light",
+            BlendMode::HardLight => "hard_light",
+            BlendMode::Add => "add",
+            BlendMode::Subtract => "subtract",
+            BlendMode::Difference => "difference",
+            BlendMode::Darken => "darken",
+            BlendMode::Lighten => "lighten",
+            BlendMode::ColorDodge => "color_dodge",
+            BlendMode::ColorBurn => "color_burn",
+            BlendMode::AlphaOver => "alpha_over",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<BlendMode> {
+        BlendMode::ALL.into_iter().find(|mode| mode.name() == name)
+    }
+
+    /// Blends a color channel of the layer into the same channel of the base, both between `0.0` and `1.0`.
+    pub fn blend(&self, base: f64, layer: f64) -> f64 {
+        let value = match self {
+            BlendMode::Normal | BlendMode::AlphaOver => layer,
+            BlendMode::Multiply => base * layer,
+            BlendMode::Screen => screen(base, layer),
+            BlendMode::Overlay => hard_light(layer, base),
+            BlendMode::SoftLight => {
+                if layer <= 0.5 {
+                    base - (1.0 - 2.0 * layer) * base * (1.0 - base)
+                } else {
+                    let lightened = if base <= 0.25 {
+                        ((16.0 * base - 12.0) * base + 4.0) * base
+                    } else {
+                        base.sqrt()
+                    };
+                    base + (2.0 * layer - 1.0) * (lightened - base)
+                }
+            }
+            BlendMode::HardLight => hard_light(base, layer),
+            BlendMode::Add => base + layer,
+            BlendMode::Subtract => base - layer,
+            BlendMode::Difference => (base - layer).abs(),
+            BlendMode::Darken => base.min(layer),
+            BlendMode::Lighten => base.max(layer),
+            BlendMode::ColorDodge => {
+                if base <= 0.0 {
+                    0.0
+                } else if layer >= 1.0 {
+                    1.0
+                } else {
+                    base / (1.0 - layer)
+                }
+            }
+            BlendMode::ColorBurn => {
+                if base >= 1.0 {
+                    1.0
+                } else if layer <= 0.0 {
+                    0.0
+                } else {
+                    1.0 - (1.0 - base) / layer
+                }
+            }
+        };
+
+        value.clamp(0.0, 1.0)
+    }
+}
+
+fn screen(base: f64, layer: f64) -> f64 {
+    1.0 - (1.0 - base) * (1.0 - layer)
+}
+
+fn hard_light(base: f64, layer: f64) -> f64 {
+    if layer <= 0.5 {
+        base * 2.0 * layer
+    } else {
+        screen(base, 2.0 * layer - 1.0)
+    }
+}
+
+/// Blends `input1` as a layer over the base `input2` with a [`BlendMode`].
+///
+/// `value` is the opacity of the layer, `1.0` shows the blended colors and `0.0` only the base. It can be given per
+/// channel.
 #[derive(Debug)]
 pub struct Mix {
+    mode: BlendMode,
+
     space_info: SpaceInfo,
 }
 
 impl Mix {
     pub fn new() -> Self {
+        Mix::with_mode(BlendMode::Normal)
+    }
+
+    pub fn with_mode(mode: BlendMode) -> Self {
         Mix {
+            mode,
+
             space_info: SpaceInfo::default(),
         }
     }
-}
 
-impl Node for Mix {
-    fn generate(
-        &self,
-        _position: &Coordinate,
-        _size: &(u32, u32),
-        input: HashMap<String, InputOutputValue>,
-    ) -> Result<InputOutputValue> {
-        assert_eq!(input.len(), 3);
+    pub fn mode(&self) -> BlendMode {
+        self.mode
+    }
 
-        let value = input.get("value").unwrap().clone();
-        let input1 = input.get("input1").unwrap().clone();
-        let input2 = input.get("input2").unwrap().clone();
+    pub fn set_mode(&mut self, mode: BlendMode) {
+        self.mode = mode;
+    }
 
+    fn mix(
+        &self,
+        value: InputOutputValue,
+        input1: InputOutputValue,
+        input2: InputOutputValue,
+    ) -> Result<InputOutputValue> {
         // `delta` must be from type `InputOutputValue::Float`
         let deltas = match value {
             InputOutputValue::Float(value) => [value, value, value, value],
             InputOutputValue::Nothing
             | InputOutputValue::Pixel(_)
             | InputOutputValue::U8X3Array(_)
-            | InputOutputValue::U8X4Array(_) => todo!(),
+            | InputOutputValue::U8X4Array(_) => {
+                return Err(GeneratorError::TypeMismatch {
+                    node: self.space_info.name.clone(),
+                    port: "value".to_string(),
+                    expected: vec![
+                        InputOutputKind::Float,
+                        InputOutputKind::F64X3Array,
+                        InputOutputKind::F64X4Array,
+                    ],
+                    found: value.kind(),
+                })
+            }
             InputOutputValue::F64X3Array(values) => [values[0], values[1], values[2], 1.0],
             InputOutputValue::F64X4Array(values) => [values[0], values[1], values[2], values[3]],
         };
 
-        let values = input1
+        let layer = input1
+            .to_common_ground()?
+            .to_raw_float()
+            .map(|item| item as f64)
+            .collect::<Vec<_>>();
+        let base = input2
             .to_common_ground()?
             .to_raw_float()
             .map(|item| item as f64)
-            .zip(
-                input2
-                    .to_common_ground()?
-                    .to_raw_float()
-                    .map(|item| item as f64),
-            )
-            .zip(deltas)
-            .map(|((first, second), delta)| (first * delta + second * (1.0 - delta)) as u8)
             .collect::<Vec<_>>();
 
+        let values = match self.mode {
+            BlendMode::AlphaOver => Mix::alpha_over(&layer, &base, deltas),
+            mode => (0..4)
+                .map(|channel| {
+                    let blended = if channel == 3 || mode == BlendMode::Normal {
+                        layer[channel]
+                    } else {
+                        mode.blend(base[channel] / 255.0, layer[channel] / 255.0) * 255.0
+                    };
+
+                    blended * deltas[channel] + base[channel] * (1.0 - deltas[channel])
+                })
+                .collect::<Vec<_>>(),
+        };
+
         Ok(InputOutputValue::Pixel(Pixel::new(
-            values[0], values[1], values[2], values[3],
+            values[0] as u8,
+            values[1] as u8,
+            values[2] as u8,
+            values[3] as u8,
         )))
     }
 
+    /// The "over" operator with the alpha of the layer scaled by the opacities, channels are between `0` and `255`.
+    fn alpha_over(layer: &[f64], base: &[f64], deltas: [f64; 4]) -> Vec<f64> {
+        let base_alpha = base[3] / 255.0;
+        let over = |layer_alpha: f64| layer_alpha + base_alpha * (1.0 - layer_alpha);
+
+        let mut values = (0..3)
+            .map(|channel| {
+                let layer_alpha = layer[3] / 255.0 * deltas[channel];
+                let alpha = over(layer_alpha);
+                if alpha <= 0.0 {
+                    return 0.0;
+                }
+
+                (layer[channel] * layer_alpha + base[channel] * base_alpha * (1.0 - layer_alpha))
+                    / alpha
+            })
+            .collect::<Vec<_>>();
+        values.push(over(layer[3] / 255.0 * deltas[3]) * 255.0);
+
+        values
+    }
+}
+
+impl Node for Mix {
+    fn node_type(&self) -> &'static str {
+        "mix"
+    }
+
+    fn generate(
+        &self,
+        _position: &Coordinate,
+        _size: &(u32, u32),
+        input: HashMap<String, InputOutputValue>,
+    ) -> Result<InputOutputValue> {
+        let value = self.input_value(&input, "value")?;
+        let input1 = self.input_value(&input, "input1")?;
+        let input2 = self.input_value(&input, "input2")?;
+
+        self.mix(value, input1, input2)
+    }
+
+    fn generate_batch(
+        &self,
+        positions: &[Coordinate],
+        _size: &(u32, u32),
+        inputs: &BatchInputs,
+    ) -> Result<Vec<InputOutputValue>> {
+        let value = self.batch_input(inputs, "value")?;
+        let input1 = self.batch_input(inputs, "input1")?;
+        let input2 = self.batch_input(inputs, "input2")?;
+
+        (0..positions.len())
+            .map(|index| self.mix(value.get(index), input1.get(index), input2.get(index)))
+            .collect()
+    }
+
+    fn inputs(&self) -> Vec<Port> {
+        vec![
+            Port::new("value")
+                .with_kinds(&[
+                    InputOutputKind::Float,
+                    InputOutputKind::F64X3Array,
+                    InputOutputKind::F64X4Array,
+                ])
+                .optional(InputOutputValue::Float(0.5)),
+            Port::new("input1"),
+            Port::new("input2"),
+        ]
+    }
+
+    fn outputs(&self) -> Vec<Port> {
+        vec![Port::new(DEFAULT_OUTPUT).with_kinds(&[InputOutputKind::Pixel])]
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![Parameter::new(
+            "mode",
+            ParameterKind::Enum {
+                variants: BlendMode::ALL
+                    .iter()
+                    .map(|mode| mode.name().to_string())
+                    .collect(),
+            },
+            ParameterValue::Enum(self.mode.name().to_string()),
+        )]
+    }
+
+    fn set_parameter(&mut self, name: &str, value: ParameterValue) -> Result<()> {
+        match (name, value) {
+            ("mode", ParameterValue::Enum(mode)) => {
+                let mode = BlendMode::from_name(&mode).ok_or_else(|| {
+                    GeneratorError::InvalidParameter {
+                        parameter: name.to_string(),
+                        reason: format!("unknown blend mode {mode:?}"),
+                    }
+                })?;
+                self.set_mode(mode);
+
+                Ok(())
+            }
+            (name, value) => Err(unsupported_parameter(self, name, &value)),
+        }
+    }
+
     fn space_info(&self) -> &SpaceInfo {
         &self.space_info
     }
@@ -76,12 +349,14 @@ impl Node for Mix {
 mod tests {
     use rusvid_core::prelude::Pixel;
 
-    use super::Mix;
+    use super::{BlendMode, Mix};
+    use crate::coordinate::Coordinate;
     use crate::generator::Generator;
     use crate::input_output_value::InputOutputValue;
     use crate::library::noise::Noise;
     use crate::library::static_value::StaticValue;
     use crate::link::Link;
+    use crate::node::{BatchInputs, BatchValue, Node};
 
     #[test]
     fn just_works() {
@@ -96,11 +371,131 @@ mod tests {
         let id_noise = generator.add_node(Noise::new(1));
         let id_output = generator.output_node();
 
-        generator.add_edge_named(Link::new(id_static_mix_factor, id_mix), "value");
-        generator.add_edge_named(Link::new(id_static_color, id_mix), "input1");
-        generator.add_edge_named(Link::new(id_noise, id_mix), "input2");
-        generator.add_edge(Link::new(id_mix, id_output));
+        generator
+            .add_edge_named(Link::new(id_static_mix_factor, id_mix).unwrap(), "value")
+            .unwrap();
+        generator
+            .add_edge_named(Link::new(id_static_color, id_mix).unwrap(), "input1")
+            .unwrap();
+        generator
+            .add_edge_named(Link::new(id_noise, id_mix).unwrap(), "input2")
+            .unwrap();
+        generator
+            .add_edge(Link::new(id_mix, id_output).unwrap())
+            .unwrap();
 
         let _ = generator.generate(100, 100).unwrap();
     }
+
+    #[test]
+    fn batch_matches_single_samples() {
+        let mix = Mix::new();
+
+        let positions = (0..4)
+            .map(|x| Coordinate::new_xy(x as f64, 0.0))
+            .collect::<Vec<_>>();
+        let values = [0.0, 0.25, 0.5, 1.0].map(InputOutputValue::Float);
+        let colors = [
+            InputOutputValue::Pixel(Pixel::new(255, 0, 100, 255)),
+            InputOutputValue::Float(0.3),
+            InputOutputValue::U8X3Array([1, 2, 3]),
+            InputOutputValue::Pixel(Pixel::new(0, 255, 150, 255)),
+        ];
+
+        let mut inputs = BatchInputs::new();
+        inputs.insert("value", BatchValue::Samples(&values));
+        inputs.insert("input1", BatchValue::Samples(&colors));
+        inputs.insert(
+            "input2",
+            BatchValue::Constant(InputOutputValue::Pixel(Pixel::new(0, 0, 0, 255))),
+        );
+
+        let batch = mix.generate_batch(&positions, &(4, 1), &inputs).unwrap();
+
+        for (index, position) in positions.iter().enumerate() {
+            let single = mix
+                .generate(position, &(4, 1), inputs.sample(index))
+                .unwrap();
+            assert_eq!(batch[index], single);
+        }
+    }
+
+    fn blend(mode: BlendMode, value: f64, layer: Pixel, base: Pixel) -> Pixel {
+        let mix = Mix::with_mode(mode);
+        match mix
+            .mix(
+                InputOutputValue::Float(value),
+                InputOutputValue::Pixel(layer),
+                InputOutputValue::Pixel(base),
+            )
+            .unwrap()
+        {
+            InputOutputValue::Pixel(pixel) => pixel,
+            other => panic!("expected a pixel, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn blend_modes_combine_layer_and_base() {
+        let layer = Pixel::new(255, 128, 0, 255);
+        let base = Pixel::new(128, 128, 128, 255);
+
+        let expectations: [(BlendMode, [u8; 4]); 10] = [
+            (BlendMode::Normal, [255, 128, 0, 255]),
+            (BlendMode::Multiply, [128, 64, 0, 255]),
+            (BlendMode::Screen, [255, 191, 128, 255]),
+            (BlendMode::Add, [255, 255, 128, 255]),
+            (BlendMode::Subtract, [0, 0, 128, 255]),
+            (BlendMode::Difference, [127, 0, 128, 255]),
+            (BlendMode::Darken, [128, 128, 0, 255]),
+            (BlendMode::Lighten, [255, 128, 128, 255]),
+            (BlendMode::ColorDodge, [255, 255, 128, 255]),
+            (BlendMode::ColorBurn, [128, 1, 0, 255]),
+        ];
+        for (mode, expected) in expectations {
+            let actual = blend(mode, 1.0, layer, base).to_raw();
+            for (expected, actual) in expected.iter().zip(actual) {
+                assert!(expected.abs_diff(actual) <= 1, "{mode:?}: {actual}");
+            }
+        }
+
+        // Overlay, soft and hard light keep black and white of the deciding input.
+        let black = Pixel::new(0, 0, 0, 255);
+        let white = Pixel::new(255, 255, 255, 255);
+        assert_eq!(blend(BlendMode::Overlay, 1.0, layer, black), black);
+        assert_eq!(blend(BlendMode::SoftLight, 1.0, layer, white), white);
+        assert_eq!(blend(BlendMode::HardLight, 1.0, black, layer), black);
+    }
+
+    #[test]
+    fn the_value_is_the_opacity_of_the_layer() {
+        let layer = Pixel::new(0, 0, 0, 255);
+        let base = Pixel::new(200, 100, 50, 255);
+
+        for mode in BlendMode::ALL {
+            assert_eq!(blend(mode, 0.0, layer, base), base, "{mode:?}");
+        }
+        assert_eq!(
+            blend(BlendMode::Multiply, 0.5, layer, base),
+            Pixel::new(100, 50, 25, 255)
+        );
+    }
+
+    #[test]
+    fn alpha_over_respects_transparency() {
+        let base = Pixel::new(0, 0, 255, 255);
+
+        let transparent = Pixel::new(255, 0, 0, 0);
+        assert_eq!(blend(BlendMode::AlphaOver, 1.0, transparent, base), base);
+
+        let half = Pixel::new(255, 0, 0, 128);
+        let over = blend(BlendMode::AlphaOver, 1.0, half, base).to_raw();
+        assert_eq!(over[3], 255);
+        assert!(over[0].abs_diff(128) <= 1 && over[2].abs_diff(127) <= 1);
+
+        assert_eq!(
+            blend(BlendMode::AlphaOver, 1.0, half, Pixel::new(0, 0, 0, 0)),
+            Pixel::new(255, 0, 0, 128)
+        );
+    }
 }
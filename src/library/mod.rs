@@ -1,7 +1,15 @@
+pub mod expression;
+pub mod fractal;
+pub mod group;
 pub mod map;
+pub mod math;
 pub mod mix;
 pub mod noise;
 pub mod normalize;
 pub(crate) mod output;
 pub mod pattern;
+pub mod split;
 pub mod static_value;
+pub mod transform;
+pub mod voronoi;
+pub mod warp;
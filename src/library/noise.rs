@@ -1,19 +1,280 @@
 use std::collections::HashMap;
+use std::f64::consts::TAU;
 
-use anyhow::Result;
-use noise::{NoiseFn, Perlin};
+use noise::core::perlin::perlin_3d;
+use noise::core::value::value_3d;
+use noise::core::worley::{distance_functions, worley_3d, ReturnType};
+use noise::permutationtable::{NoiseHasher, PermutationTable};
+use noise::{NoiseFn, OpenSimplex, Simplex, SuperSimplex};
 use rusvid_core::pixel::Pixel;
 use rusvid_core::plane::Plane;
 
 use crate::bitmap::BitmapChar;
 use crate::coordinate::Coordinate;
-use crate::input_output_value::InputOutputValue;
-use crate::node::{Node, SpaceInfo};
+use crate::error::{GeneratorError, Result};
+use crate::input_output_value::{InputOutputKind, InputOutputValue};
+use crate::node::{BatchInputs, Node, SpaceInfo};
+use crate::parameter::{unsupported_parameter, Parameter, ParameterKind, ParameterValue};
+use crate::port::{Port, DEFAULT_OUTPUT};
 use crate::utils::render_square;
 
+/// The noise function of a [`Noise`] node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoiseAlgorithm {
+    Perlin,
+    Simplex,
+    OpenSimplex,
+    SuperSimplex,
+    /// Interpolated random values on a grid.
+    Value,
+    /// Distance to the nearest of randomly placed points, also called cellular noise. `-1.0` is at a point, `1.0` would
+    /// be the largest possible distance.
+    Worley,
+}
+
+impl NoiseAlgorithm {
+    pub const ALL: [NoiseAlgorithm; 6] = [
+        NoiseAlgorithm::Perlin,
+        NoiseAlgorithm::Simplex,
+        NoiseAlgorithm::OpenSimplex,
+        NoiseAlgorithm::SuperSimplex,
+        NoiseAlgorithm::Value,
+        NoiseAlgorithm::Worley,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            NoiseAlgorithm::Perlin => "perlin",
+            NoiseAlgorithm::Simplex => "simplex",
+            NoiseAlgorithm::OpenSimplex => "open_simplex",
+            NoiseAlgorithm::SuperSimplex => "super_simplex",
+            NoiseAlgorithm::Value => "value",
+            NoiseAlgorithm::Worley => "worley",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<NoiseAlgorithm> {
+        NoiseAlgorithm::ALL
+            .into_iter()
+            .find(|algorithm| algorithm.name() == name)
+    }
+
+    /// The [`Node::node_type`] of a [`Noise`] node with this algorithm.
+    pub fn node_type(&self) -> &'static str {
+        match self {
+            NoiseAlgorithm::Perlin => "noise.perlin",
+            NoiseAlgorithm::Simplex => "noise.simplex",
+            NoiseAlgorithm::OpenSimplex => "noise.open_simplex",
+            NoiseAlgorithm::SuperSimplex => "noise.super_simplex",
+            NoiseAlgorithm::Value => "noise.value",
+            NoiseAlgorithm::Worley => "noise.worley",
+        }
+    }
+
+    /// Human readable name, e.g. for the node registry.
+    pub fn label(&self) -> &'static str {
+        match self {
+            NoiseAlgorithm::Perlin => "Perlin",
+            NoiseAlgorithm::Simplex => "Simplex",
+            NoiseAlgorithm::OpenSimplex => "OpenSimplex",
+            NoiseAlgorithm::SuperSimplex => "SuperSimplex",
+            NoiseAlgorithm::Value => "Value",
+            NoiseAlgorithm::Worley => "Worley",
+        }
+    }
+
+    pub(crate) fn parameter(&self) -> Parameter {
+        Parameter::new(
+            "algorithm",
+            ParameterKind::Enum {
+                variants: NoiseAlgorithm::ALL
+                    .iter()
+                    .map(|algorithm| algorithm.name().to_string())
+                    .collect(),
+            },
+            ParameterValue::Enum(self.name().to_string()),
+        )
+    }
+
+    pub(crate) fn from_parameter(name: &str, value: &str) -> Result<NoiseAlgorithm> {
+        NoiseAlgorithm::from_name(value).ok_or_else(|| GeneratorError::InvalidParameter {
+            parameter: name.to_string(),
+            reason: format!("unknown algorithm {value:?}"),
+        })
+    }
+}
+
+/// The largest magnitude of 3D [`Simplex`] noise, measured with a margin since the noise crate doesn't scale it to
+/// `1.0`.
+const SIMPLEX_3D_MAX: f64 = 0.27;
+/// The largest magnitude of 3D [`OpenSimplex`] noise, measured like [`SIMPLEX_3D_MAX`].
+const OPEN_SIMPLEX_3D_MAX: f64 = 0.55;
+/// The largest magnitude of 4D [`Simplex`] noise, measured like [`SIMPLEX_3D_MAX`].
+const SIMPLEX_4D_MAX: f64 = 0.64;
+/// The largest magnitude of 4D [`OpenSimplex`] noise, measured like [`SIMPLEX_3D_MAX`].
+const OPEN_SIMPLEX_4D_MAX: f64 = 1.12;
+/// The farthest a point can be from the nearest feature point of Worley noise: a feature point lies at most `0.5` from
+/// the corner of its cell along an axis or a face diagonal, the point at most half the body diagonal from that corner.
+const WORLEY_MAX_DISTANCE: f64 = 1.306_562_964_876_376_6;
+
+/// A seeded noise function of any [`NoiseAlgorithm`].
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum NoiseSource {
+    Perlin(PermutationTable),
+    Simplex(Simplex),
+    OpenSimplex(OpenSimplex),
+    SuperSimplex(SuperSimplex),
+    Value(PermutationTable),
+    /// `noise::Worley` can't be shared between threads, the node calls the function behind it directly.
+    Worley(PermutationTable),
+}
+
+impl NoiseSource {
+    pub(crate) fn new(algorithm: NoiseAlgorithm, seed: u32) -> Self {
+        match algorithm {
+            NoiseAlgorithm::Perlin => NoiseSource::Perlin(PermutationTable::new(seed)),
+            NoiseAlgorithm::Simplex => NoiseSource::Simplex(Simplex::new(seed)),
+            NoiseAlgorithm::OpenSimplex => NoiseSource::OpenSimplex(OpenSimplex::new(seed)),
+            NoiseAlgorithm::SuperSimplex => NoiseSource::SuperSimplex(SuperSimplex::new(seed)),
+            NoiseAlgorithm::Value => NoiseSource::Value(PermutationTable::new(seed)),
+            NoiseAlgorithm::Worley => NoiseSource::Worley(PermutationTable::new(seed)),
+        }
+    }
+
+    pub(crate) fn algorithm(&self) -> NoiseAlgorithm {
+        match self {
+            NoiseSource::Perlin(_) => NoiseAlgorithm::Perlin,
+            NoiseSource::Simplex(_) => NoiseAlgorithm::Simplex,
+            NoiseSource::OpenSimplex(_) => NoiseAlgorithm::OpenSimplex,
+            NoiseSource::SuperSimplex(_) => NoiseAlgorithm::SuperSimplex,
+            NoiseSource::Value(_) => NoiseAlgorithm::Value,
+            NoiseSource::Worley(_) => NoiseAlgorithm::Worley,
+        }
+    }
+
+    /// Samples the noise function, the result is between `-1.0` and `1.0` for every algorithm.
+    pub(crate) fn get(&self, point: NoisePoint) -> f64 {
+        match point {
+            NoisePoint::Space(point) => self.sample(point, None),
+            NoisePoint::Tiled { point, period } => match self.table() {
+                // Grid based noise repeats when the grid does.
+                Some(table) => self.sample(point, Some(&PeriodicHasher { table, period })),
+                // The skewed grid of the simplex algorithms doesn't line up with the borders of the image.
+                None => self.sample_simplex_tiled(point, period),
+            },
+        }
+    }
+
+    /// Samples a simplex algorithm so that it repeats every `period` cells along `x` and `y`.
+    ///
+    /// `x` and `y` each go around a circle that is one period long, together they go around a torus in 4D noise. `z`
+    /// moves the torus through the noise. SuperSimplex has no 4D variant, the image fades into copies of the noise
+    /// shifted by one period instead.
+    fn sample_simplex_tiled(&self, point: [f64; 3], period: [i64; 2]) -> f64 {
+        let circle = |value: f64, cells: i64| {
+            let radius = cells as f64 / TAU;
+            let angle = value / radius;
+            [radius * angle.cos(), radius * angle.sin()]
+        };
+        let [x0, x1] = circle(point[0], period[0]);
+        let [y0, y1] = circle(point[1], period[1]);
+        // Moves the torus by `z`, across the diagonal that the simplex grid is skewed along.
+        let shift = point[2] / 2.0;
+        let torus = [x0 + shift, x1 - shift, y0 + shift, y1 - shift];
+
+        match self {
+            NoiseSource::Simplex(noise) => noise.get(torus) / SIMPLEX_4D_MAX,
+            NoiseSource::OpenSimplex(noise) => noise.get(torus) / OPEN_SIMPLEX_4D_MAX,
+            _ => {
+                let [width, height] = period.map(|cells| cells as f64);
+                let x = point[0].rem_euclid(width);
+                let y = point[1].rem_euclid(height);
+                let copies = [
+                    (0.0, 0.0, (width - x) * (height - y)),
+                    (width, 0.0, x * (height - y)),
+                    (0.0, height, (width - x) * y),
+                    (width, height, x * y),
+                ];
+
+                // The copies are independent, dividing the bilinear blend by the length of the weights keeps the
+                // contrast the same all over the image. This can overshoot the range in rare spots.
+                let length = copies
+                    .iter()
+                    .map(|(_, _, weight)| weight * weight)
+                    .sum::<f64>()
+                    .sqrt();
+                let value = copies
+                    .iter()
+                    .map(|(dx, dy, weight)| self.sample([x - dx, y - dy, point[2]], None) * weight)
+                    .sum::<f64>();
+
+                (value / length).clamp(-1.0, 1.0)
+            }
+        }
+    }
+
+    /// The permutation table of the grid based algorithms.
+    fn table(&self) -> Option<&PermutationTable> {
+        match self {
+            NoiseSource::Perlin(table) | NoiseSource::Value(table) | NoiseSource::Worley(table) => {
+                Some(table)
+            }
+            NoiseSource::Simplex(_)
+            | NoiseSource::OpenSimplex(_)
+            | NoiseSource::SuperSimplex(_) => None,
+        }
+    }
+
+    /// Samples the noise function at a point in space, grid based algorithms hash the corners of the grid with
+    /// `hasher` instead of their table if one is given.
+    ///
+    /// The algorithms of the noise crate have different ranges, every one is scaled to its own range here.
+    fn sample(&self, point: [f64; 3], hasher: Option<&dyn NoiseHasher>) -> f64 {
+        match self {
+            NoiseSource::Perlin(table) => perlin_3d(point, hasher.unwrap_or(table)),
+            NoiseSource::Simplex(noise) => noise.get(point) / SIMPLEX_3D_MAX,
+            NoiseSource::OpenSimplex(noise) => noise.get(point) / OPEN_SIMPLEX_3D_MAX,
+            NoiseSource::SuperSimplex(noise) => noise.get(point),
+            NoiseSource::Value(table) => value_3d(point, hasher.unwrap_or(table)),
+            NoiseSource::Worley(table) => {
+                // The noise crate maps a distance `d` to `2d - 1`.
+                let distance = (worley_3d(
+                    hasher.unwrap_or(table),
+                    distance_functions::euclidean,
+                    ReturnType::Distance,
+                    point,
+                ) + 1.0)
+                    / 2.0;
+
+                distance / WORLEY_MAX_DISTANCE * 2.0 - 1.0
+            }
+        }
+    }
+}
+
+/// Hashes the corners of the grid modulo the period so that grid based noise repeats after `period` cells.
+struct PeriodicHasher<'a> {
+    table: &'a PermutationTable,
+    period: [i64; 2],
+}
+
+impl NoiseHasher for PeriodicHasher<'_> {
+    fn hash(&self, to_hash: &[isize]) -> usize {
+        let mut wrapped = [0; 3];
+        for (index, (wrapped, value)) in wrapped.iter_mut().zip(to_hash).enumerate() {
+            *wrapped = match self.period.get(index) {
+                Some(period) => value.rem_euclid(*period as isize),
+                None => *value,
+            };
+        }
+
+        self.table.hash(&wrapped[..to_hash.len()])
+    }
+}
+
 #[derive(Debug)]
 pub struct Noise {
-    perlin: Perlin,
+    source: NoiseSource,
 
     seed: u32,
     offset: Coordinate,
@@ -23,9 +284,14 @@ pub struct Noise {
 }
 
 impl Noise {
+    /// Creates Perlin noise, see [`Noise::with_algorithm`] for the other algorithms.
     pub fn new(seed: u32) -> Self {
+        Noise::with_algorithm(NoiseAlgorithm::Perlin, seed)
+    }
+
+    pub fn with_algorithm(algorithm: NoiseAlgorithm, seed: u32) -> Self {
         Noise {
-            perlin: Perlin::new(seed),
+            source: NoiseSource::new(algorithm, seed),
             seed,
             offset: Coordinate::new(0.0, 0.0, 0.0),
             scale: Coordinate::new(1.0, 1.0, 1.0),
@@ -33,36 +299,196 @@ impl Noise {
             space_info: {
                 let mut si = SpaceInfo::default();
 
-                si.size = (250, 50);
+                si.size = (250, 60);
 
                 si
             },
         }
     }
 
+    pub fn algorithm(&self) -> NoiseAlgorithm {
+        self.source.algorithm()
+    }
+
+    pub fn set_algorithm(&mut self, algorithm: NoiseAlgorithm) {
+        self.source = NoiseSource::new(algorithm, self.seed);
+    }
+
+    pub fn seed(&self) -> u32 {
+        self.seed
+    }
+
+    pub fn set_seed(&mut self, seed: u32) {
+        self.seed = seed;
+        self.source = NoiseSource::new(self.algorithm(), seed);
+    }
+
+    pub fn offset(&self) -> Coordinate {
+        self.offset
+    }
+
     pub fn set_offset(&mut self, offset: Coordinate) {
         self.offset = offset;
     }
 
+    pub fn scale(&self) -> Coordinate {
+        self.scale
+    }
+
     pub fn set_scale(&mut self, scale: Coordinate) {
         self.scale = scale;
     }
+
+    fn sample(&self, position: &Coordinate, size: &(u32, u32), tileable: bool) -> f64 {
+        self.source.get(NoisePoint::new(
+            position,
+            size,
+            &self.offset,
+            &self.scale,
+            tileable,
+        ))
+    }
+}
+
+/// Where a noise function gets sampled.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum NoisePoint {
+    Space([f64; 3]),
+    /// A point of tileable output, the noise repeats every `period` cells along `x` and `y`, see
+    /// [`Generator::set_tileable`](crate::generator::Generator::set_tileable).
+    Tiled {
+        point: [f64; 3],
+        period: [i64; 2],
+    },
+}
+
+impl NoisePoint {
+    pub(crate) fn new(
+        position: &Coordinate,
+        size: &(u32, u32),
+        offset: &Coordinate,
+        scale: &Coordinate,
+        tileable: bool,
+    ) -> Self {
+        if !tileable {
+            return NoisePoint::Space(noise_point(position, size, offset, scale));
+        }
+
+        // The right border already belongs to the next tile, one image is `period` cells long.
+        let period = tile_period(scale);
+        NoisePoint::Tiled {
+            point: [
+                (position.x() / size.0 as f64 + offset.x()) * period[0] as f64,
+                (position.y() / size.1 as f64 + offset.y()) * period[1] as f64,
+                (position.z() + offset.z()) * scale.z(),
+            ],
+            period,
+        }
+    }
+
+    /// Multiplies the frequency of the noise, e.g. for the octaves of a fractal. Tileable output rounds the frequency
+    /// to whole cells per image.
+    pub(crate) fn scaled(self, factor: f64) -> Self {
+        match self {
+            NoisePoint::Space(point) => NoisePoint::Space(point.map(|axis| axis * factor)),
+            NoisePoint::Tiled { point, period } => {
+                let scaled = period.map(|cells| ((cells as f64 * factor).round() as i64).max(1));
+
+                NoisePoint::Tiled {
+                    point: [
+                        point[0] * scaled[0] as f64 / period[0] as f64,
+                        point[1] * scaled[1] as f64 / period[1] as f64,
+                        point[2] * factor,
+                    ],
+                    period: scaled,
+                }
+            }
+        }
+    }
+}
+
+/// The number of cells per image of tileable output, the scale rounded to whole cells.
+pub(crate) fn tile_period(scale: &Coordinate) -> [i64; 2] {
+    [
+        (scale.x().round() as i64).max(1),
+        (scale.y().round() as i64).max(1),
+    ]
+}
+
+/// Maps a pixel to the point where a noise function gets sampled, `x` and `y` go from `0.0` to `1.0` over the image
+/// before `offset` and `scale` are applied.
+pub(crate) fn noise_point(
+    position: &Coordinate,
+    size: &(u32, u32),
+    offset: &Coordinate,
+    scale: &Coordinate,
+) -> [f64; 3] {
+    [
+        (((position.x()) / ((size.0 - 1) as f64)) + offset.x()) * scale.x(),
+        (((position.y()) / ((size.1 - 1) as f64)) + offset.y()) * scale.y(),
+        ((position.z()) + offset.z()) * scale.z(),
+    ]
 }
 
 impl Node for Noise {
+    fn node_type(&self) -> &'static str {
+        self.algorithm().node_type()
+    }
+
     fn generate(
         &self,
         position: &Coordinate,
         size: &(u32, u32),
         _input: HashMap<String, InputOutputValue>,
     ) -> Result<InputOutputValue> {
-        let value = self.perlin.get([
-            (((position.x()) / ((size.0 - 1) as f64)) + self.offset.x()) * self.scale.x(),
-            (((position.y()) / ((size.1 - 1) as f64)) + self.offset.y()) * self.scale.y(),
-            ((position.z()) + self.offset.z()) * self.scale.z(),
-        ]);
+        Ok(InputOutputValue::Float(self.sample(position, size, false)))
+    }
 
-        Ok(InputOutputValue::Float(value))
+    fn generate_batch(
+        &self,
+        positions: &[Coordinate],
+        size: &(u32, u32),
+        inputs: &BatchInputs,
+    ) -> Result<Vec<InputOutputValue>> {
+        Ok(positions
+            .iter()
+            .map(|position| InputOutputValue::Float(self.sample(position, size, inputs.tileable())))
+            .collect())
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![
+            self.algorithm().parameter(),
+            Parameter::new("seed", ParameterKind::Seed, ParameterValue::Seed(self.seed)),
+            Parameter::new(
+                "offset",
+                ParameterKind::Coordinate,
+                ParameterValue::Coordinate(self.offset),
+            ),
+            Parameter::new(
+                "scale",
+                ParameterKind::Coordinate,
+                ParameterValue::Coordinate(self.scale),
+            ),
+        ]
+    }
+
+    fn set_parameter(&mut self, name: &str, value: ParameterValue) -> Result<()> {
+        match (name, value) {
+            ("algorithm", ParameterValue::Enum(algorithm)) => {
+                self.set_algorithm(NoiseAlgorithm::from_parameter(name, &algorithm)?)
+            }
+            ("seed", ParameterValue::Seed(seed)) => self.set_seed(seed),
+            ("offset", ParameterValue::Coordinate(offset)) => self.set_offset(offset),
+            ("scale", ParameterValue::Coordinate(scale)) => self.set_scale(scale),
+            (name, value) => return Err(unsupported_parameter(self, name, &value)),
+        }
+
+        Ok(())
+    }
+
+    fn outputs(&self) -> Vec<Port> {
+        vec![Port::new(DEFAULT_OUTPUT).with_kinds(&[InputOutputKind::Float])]
     }
 
     fn render(&self, plane: &mut Plane) -> Result<()> {
@@ -85,6 +511,18 @@ impl Node for Noise {
         )?;
         delta_y += delta;
 
+        let (_, delta) = BitmapChar::render_multiple_with_scale(
+            plane,
+            (
+                space_info.position.0,
+                space_info.position.1 + 5 + delta_y as i64,
+            ),
+            format!("Algorithm: {}", self.algorithm().label()),
+            Pixel::new(0, 0, 0, 255),
+            1,
+        )?;
+        delta_y += delta;
+
         let (_, delta) = BitmapChar::render_multiple_with_scale(
             plane,
             (
@@ -131,3 +569,113 @@ impl Node for Noise {
         &mut self.space_info
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Noise, NoiseAlgorithm, NoisePoint, NoiseSource};
+    use crate::coordinate::Coordinate;
+    use crate::input_output_value::InputOutputValue;
+    use crate::node::{BatchInputs, Node};
+    use crate::parameter::ParameterValue;
+
+    #[test]
+    fn every_algorithm_stays_between_minus_one_and_one() {
+        for algorithm in NoiseAlgorithm::ALL {
+            let mut noise = Noise::new(5);
+            noise
+                .set_parameter(
+                    "algorithm",
+                    ParameterValue::Enum(algorithm.name().to_string()),
+                )
+                .unwrap();
+            assert_eq!(noise.node_type(), algorithm.node_type());
+
+            // Nothing clamps the samples, they have to be in range on their own and still use most of it.
+            let (mut min, mut max) = (f64::MAX, f64::MIN);
+            for index in 0..50_000 {
+                let index = index as f64;
+                let point = [
+                    (index * 0.618_034).fract() * 40.0,
+                    (index * 0.754_878).fract() * 40.0,
+                    (index * 0.569_840).fract() * 40.0,
+                ];
+                let value = noise.source.get(NoisePoint::Space(point));
+                min = min.min(value);
+                max = max.max(value);
+            }
+
+            assert!(min >= -1.0 && max <= 1.0, "{algorithm:?}: {min} to {max}");
+            assert!(max - min > 1.2, "{algorithm:?}: {min} to {max}");
+        }
+    }
+
+    #[test]
+    fn tileable_noise_repeats_after_one_image() {
+        let size = (16, 12);
+        for algorithm in NoiseAlgorithm::ALL {
+            let mut noise = Noise::with_algorithm(algorithm, 9);
+            noise.set_scale(Coordinate::new(3.0, 2.0, 1.0));
+            let mut inputs = BatchInputs::new();
+            inputs.set_tileable(true);
+
+            let sample = |x: f64, y: f64| match noise.generate_batch(
+                &[Coordinate::new_xy(x, y)],
+                &size,
+                &inputs,
+            ) {
+                Ok(values) => match values[0] {
+                    InputOutputValue::Float(value) => value,
+                    other => panic!("{algorithm:?}: {other:?}"),
+                },
+                other => panic!("{algorithm:?}: {other:?}"),
+            };
+            for (x, y) in [(0.0, 0.0), (3.0, 7.0), (15.0, 11.0), (-2.5, 4.0)] {
+                let value = sample(x, y);
+                assert!((value - sample(x + 16.0, y)).abs() < 1e-9, "{algorithm:?}");
+                assert!((value - sample(x, y + 12.0)).abs() < 1e-9, "{algorithm:?}");
+                assert!(
+                    (value - sample(x - 16.0, y - 12.0)).abs() < 1e-9,
+                    "{algorithm:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn tiles_keep_their_contrast_in_the_middle() {
+        let spread = |values: &[f64]| {
+            let mean = values.iter().sum::<f64>() / values.len() as f64;
+            (values
+                .iter()
+                .map(|value| (value - mean).powi(2))
+                .sum::<f64>()
+                / values.len() as f64)
+                .sqrt()
+        };
+
+        let period = [4, 4];
+        for algorithm in [
+            NoiseAlgorithm::Simplex,
+            NoiseAlgorithm::OpenSimplex,
+            NoiseAlgorithm::SuperSimplex,
+        ] {
+            let source = NoiseSource::new(algorithm, 3);
+            // `z` moves through independent parts of the noise.
+            let values = |x: f64, y: f64| {
+                (0..2000)
+                    .map(|z| {
+                        source.get(NoisePoint::Tiled {
+                            point: [x, y, z as f64 * 1.7],
+                            period,
+                        })
+                    })
+                    .collect::<Vec<_>>()
+            };
+
+            let middle = spread(&values(2.0, 2.0));
+            let edge = spread(&values(0.1, 0.1));
+            let ratio = middle / edge;
+            assert!((0.8..1.25).contains(&ratio), "{algorithm:?}: {ratio}");
+        }
+    }
+}
@@ -1,11 +1,15 @@
 use std::collections::HashMap;
 
-use anyhow::Result;
 use rusvid_core::prelude::Pixel;
 
 use crate::coordinate::Coordinate;
-use crate::input_output_value::InputOutputValue;
+use crate::error::Result;
+use crate::input_output_value::{InputOutputKind, InputOutputValue};
 use crate::node::{Node, SpaceInfo};
+use crate::parameter::{
+    check_range, unsupported_parameter, Parameter, ParameterKind, ParameterValue,
+};
+use crate::port::{Port, DEFAULT_INPUT, DEFAULT_OUTPUT};
 
 #[derive(Debug)]
 pub struct Normalize {
@@ -26,13 +30,17 @@ impl Normalize {
 }
 
 impl Node for Normalize {
+    fn node_type(&self) -> &'static str {
+        "normalize"
+    }
+
     fn generate(
         &self,
         _position: &Coordinate,
         _size: &(u32, u32),
         input: HashMap<String, InputOutputValue>,
     ) -> Result<InputOutputValue> {
-        let (_, first_input) = input.iter().next().unwrap();
+        let first_input = self.input_value(&input, DEFAULT_INPUT)?;
 
         let raw = first_input.to_common_ground()?.to_raw();
 
@@ -49,6 +57,36 @@ impl Node for Normalize {
         Ok(InputOutputValue::Pixel(Pixel::new_raw(arr)))
     }
 
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![Parameter::new(
+            "value",
+            ParameterKind::Float {
+                min: 0.0001,
+                max: 1.0,
+            },
+            ParameterValue::Float(self.value),
+        )]
+    }
+
+    fn set_parameter(&mut self, name: &str, value: ParameterValue) -> Result<()> {
+        match (name, value) {
+            ("value", ParameterValue::Float(value)) => {
+                self.value = check_range(name, value, 0.0001, 1.0)?
+            }
+            (name, value) => return Err(unsupported_parameter(self, name, &value)),
+        }
+
+        Ok(())
+    }
+
+    fn inputs(&self) -> Vec<Port> {
+        vec![Port::new(DEFAULT_INPUT)]
+    }
+
+    fn outputs(&self) -> Vec<Port> {
+        vec![Port::new(DEFAULT_OUTPUT).with_kinds(&[InputOutputKind::Pixel])]
+    }
+
     fn space_info(&self) -> &SpaceInfo {
         &self.space_info
     }
@@ -1,25 +1,40 @@
 use std::collections::HashMap;
 
-use anyhow::Result;
 use rusvid_core::prelude::{Pixel, Plane, ResizeMode};
 
 use crate::coordinate::Coordinate;
+use crate::error::{GeneratorError, Result};
 use crate::input_output_value::InputOutputValue;
 use crate::node::{Node, SpaceInfo};
+use crate::parameter::{unsupported_parameter, Parameter, ParameterKind, ParameterValue};
+use crate::port::{Port, DEFAULT_INPUT};
+
+/// Name of the channel of the output node that every generator has.
+pub const DEFAULT_CHANNEL: &str = "output";
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Output {
+    /// Name of the image in [`Generator::generate_all`](crate::generator::Generator::generate_all), e.g. `"albedo"`.
+    channel: String,
+
     space_info: SpaceInfo,
 }
 
 impl Output {
     pub fn new() -> Self {
+        Output::with_channel(DEFAULT_CHANNEL)
+    }
+
+    pub fn with_channel<S: Into<String>>(channel: S) -> Self {
         let mut space_info = SpaceInfo::default();
         space_info.name = "Output".to_string();
         space_info.color = Pixel::new(166, 166, 166, 255);
         space_info.size = (220, 235);
 
-        Output { space_info }
+        Output {
+            channel: channel.into(),
+            space_info,
+        }
     }
 
     pub fn draw_generated_output_into_node(
@@ -42,19 +57,59 @@ impl Output {
 }
 
 impl Node for Output {
+    fn node_type(&self) -> &'static str {
+        "output"
+    }
+
     fn generate(
         &self,
         _position: &Coordinate,
         _size: &(u32, u32),
         _input: HashMap<String, InputOutputValue>,
     ) -> Result<InputOutputValue> {
-        unreachable!()
+        Err(GeneratorError::MissingInput {
+            node: self.space_info.name.clone(),
+            port: DEFAULT_INPUT.to_string(),
+        })
+    }
+
+    fn inputs(&self) -> Vec<Port> {
+        vec![Port::new(DEFAULT_INPUT)]
+    }
+
+    fn outputs(&self) -> Vec<Port> {
+        Vec::new()
     }
 
     fn is_output(&self) -> bool {
         true
     }
 
+    fn output_channel(&self) -> Option<&str> {
+        Some(&self.channel)
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![Parameter::new(
+            "channel",
+            ParameterKind::Text,
+            ParameterValue::Text(self.channel.clone()),
+        )]
+    }
+
+    fn set_parameter(&mut self, name: &str, value: ParameterValue) -> Result<()> {
+        match (name, value) {
+            ("channel", ParameterValue::Text(channel)) => self.channel = channel,
+            (name, value) => return Err(unsupported_parameter(self, name, &value)),
+        }
+
+        Ok(())
+    }
+
+    fn render_preview(&self, plane: &mut Plane, preview: &Plane) -> Result<()> {
+        self.draw_generated_output_into_node(plane, preview)
+    }
+
     fn space_info(&self) -> &SpaceInfo {
         &self.space_info
     }
@@ -1,11 +1,12 @@
 use std::collections::HashMap;
 
-use anyhow::Result;
 use rusvid_core::pixel::Pixel;
 
 use crate::coordinate::Coordinate;
-use crate::input_output_value::InputOutputValue;
+use crate::error::Result;
+use crate::input_output_value::{InputOutputKind, InputOutputValue};
 use crate::node::{Node, SpaceInfo};
+use crate::port::{Port, DEFAULT_INPUT, DEFAULT_OUTPUT};
 
 #[derive(Debug)]
 pub struct Pattern {
@@ -21,13 +22,17 @@ impl Pattern {
 }
 
 impl Node for Pattern {
+    fn node_type(&self) -> &'static str {
+        "pattern"
+    }
+
     fn generate(
         &self,
         position: &Coordinate,
         _size: &(u32, u32),
         input: HashMap<String, InputOutputValue>,
     ) -> Result<InputOutputValue> {
-        let (_, first_input) = input.iter().next().unwrap();
+        let first_input = self.input_value(&input, DEFAULT_INPUT)?;
 
         let p = first_input.to_common_ground()?;
 
@@ -47,6 +52,14 @@ impl Node for Pattern {
         }
     }
 
+    fn inputs(&self) -> Vec<Port> {
+        vec![Port::new(DEFAULT_INPUT)]
+    }
+
+    fn outputs(&self) -> Vec<Port> {
+        vec![Port::new(DEFAULT_OUTPUT).with_kinds(&[InputOutputKind::Pixel])]
+    }
+
     fn space_info(&self) -> &SpaceInfo {
         &self.space_info
     }
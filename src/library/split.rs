@@ -0,0 +1,82 @@
+use std::collections::HashMap;
+
+use crate::coordinate::Coordinate;
+use crate::error::Result;
+use crate::input_output_value::{InputOutputKind, InputOutputValue};
+use crate::node::{Node, SpaceInfo};
+use crate::port::{Port, DEFAULT_INPUT};
+
+/// Splits a color into its channels, every channel is a separate output with a value between `0.0` and `1.0`.
+#[derive(Debug)]
+pub struct Split {
+    space_info: SpaceInfo,
+}
+
+/// Names of the outputs of [`Split`].
+pub const CHANNELS: [&str; 4] = ["r", "g", "b", "a"];
+
+impl Split {
+    pub fn new() -> Self {
+        Split {
+            space_info: SpaceInfo::default(),
+        }
+    }
+}
+
+impl Default for Split {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Node for Split {
+    fn node_type(&self) -> &'static str {
+        "split"
+    }
+
+    fn generate(
+        &self,
+        position: &Coordinate,
+        size: &(u32, u32),
+        input: HashMap<String, InputOutputValue>,
+    ) -> Result<InputOutputValue> {
+        let mut outputs = self.generate_outputs(position, size, input)?;
+
+        Ok(outputs.remove(0))
+    }
+
+    fn generate_outputs(
+        &self,
+        _position: &Coordinate,
+        _size: &(u32, u32),
+        input: HashMap<String, InputOutputValue>,
+    ) -> Result<Vec<InputOutputValue>> {
+        let value = self.input_value(&input, DEFAULT_INPUT)?;
+
+        Ok(vec![
+            InputOutputValue::Float(value.r_percentage()?),
+            InputOutputValue::Float(value.g_percentage()?),
+            InputOutputValue::Float(value.b_percentage()?),
+            InputOutputValue::Float(value.a_percentage()?),
+        ])
+    }
+
+    fn inputs(&self) -> Vec<Port> {
+        vec![Port::new(DEFAULT_INPUT)]
+    }
+
+    fn outputs(&self) -> Vec<Port> {
+        CHANNELS
+            .iter()
+            .map(|channel| Port::new(*channel).with_kinds(&[InputOutputKind::Float]))
+            .collect()
+    }
+
+    fn space_info(&self) -> &SpaceInfo {
+        &self.space_info
+    }
+
+    fn space_info_mut(&mut self) -> &mut SpaceInfo {
+        &mut self.space_info
+    }
+}
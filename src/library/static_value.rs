@@ -1,10 +1,11 @@
 use std::collections::HashMap;
 
-use anyhow::Result;
-
 use crate::coordinate::Coordinate;
+use crate::error::Result;
 use crate::input_output_value::InputOutputValue;
 use crate::node::{Node, SpaceInfo};
+use crate::parameter::{unsupported_parameter, Parameter, ParameterKind, ParameterValue};
+use crate::port::{Port, DEFAULT_OUTPUT};
 
 #[derive(Debug)]
 pub struct StaticValue {
@@ -24,6 +25,10 @@ impl StaticValue {
 }
 
 impl Node for StaticValue {
+    fn node_type(&self) -> &'static str {
+        "static_value"
+    }
+
     fn generate(
         &self,
         _position: &Coordinate,
@@ -33,6 +38,27 @@ impl Node for StaticValue {
         Ok(self.value)
     }
 
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![Parameter::new(
+            "value",
+            ParameterKind::Value,
+            ParameterValue::Value(self.value),
+        )]
+    }
+
+    fn set_parameter(&mut self, name: &str, value: ParameterValue) -> Result<()> {
+        match (name, value) {
+            ("value", ParameterValue::Value(value)) => self.value = value,
+            (name, value) => return Err(unsupported_parameter(self, name, &value)),
+        }
+
+        Ok(())
+    }
+
+    fn outputs(&self) -> Vec<Port> {
+        vec![Port::new(DEFAULT_OUTPUT).with_kinds(&[self.value.kind()])]
+    }
+
     fn space_info(&self) -> &SpaceInfo {
         &self.space_info
     }
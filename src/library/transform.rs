@@ -0,0 +1,365 @@
+use std::collections::HashMap;
+
+use rusvid_core::pixel::Pixel;
+use rusvid_core::plane::Plane;
+
+use crate::bitmap::BitmapChar;
+use crate::coordinate::{Coordinate, Matrix};
+use crate::error::{GeneratorError, Result};
+use crate::input_output_value::InputOutputValue;
+use crate::node::{BatchInputs, Node, SpaceInfo};
+use crate::parameter::{
+    check_range, unsupported_parameter, Parameter, ParameterKind, ParameterValue,
+};
+use crate::port::{Port, DEFAULT_INPUT, DEFAULT_OUTPUT};
+use crate::utils::render_square;
+
+/// Moves, rotates, scales and shears the image of its input by evaluating the nodes before it at transformed
+/// coordinates.
+///
+/// The translation and the pivot are measured in units of the image size, `(0.5, 0.5)` is the center of the image. The
+/// rotation is in degrees around the pivot, the scale and the shear are applied around the pivot as well.
+#[derive(Debug)]
+pub struct Transform {
+    translation: Coordinate,
+    rotation: f64,
+    pivot: Coordinate,
+    scale: Coordinate,
+    shear_x: f64,
+    shear_y: f64,
+
+    space_info: SpaceInfo,
+}
+
+impl Transform {
+    pub fn new() -> Self {
+        Transform {
+            translation: Coordinate::new(0.0, 0.0, 0.0),
+            rotation: 0.0,
+            pivot: Coordinate::new(0.5, 0.5, 0.0),
+            scale: Coordinate::new(1.0, 1.0, 1.0),
+            shear_x: 0.0,
+            shear_y: 0.0,
+
+            space_info: {
+                let mut si = SpaceInfo::default();
+
+                si.size = (250, 100);
+
+                si
+            },
+        }
+    }
+
+    pub fn translation(&self) -> Coordinate {
+        self.translation
+    }
+
+    pub fn set_translation(&mut self, translation: Coordinate) {
+        self.translation = translation;
+    }
+
+    pub fn rotation(&self) -> f64 {
+        self.rotation
+    }
+
+    pub fn set_rotation(&mut self, rotation: f64) -> Result<()> {
+        self.rotation = check_range("rotation", rotation, -360.0, 360.0)?;
+
+        Ok(())
+    }
+
+    pub fn pivot(&self) -> Coordinate {
+        self.pivot
+    }
+
+    pub fn set_pivot(&mut self, pivot: Coordinate) {
+        self.pivot = pivot;
+    }
+
+    pub fn scale(&self) -> Coordinate {
+        self.scale
+    }
+
+    /// Fails if the scale collapses an axis, the transformation couldn't be undone to find the coordinates of the input.
+    pub fn set_scale(&mut self, scale: Coordinate) -> Result<()> {
+        Transform::check_invertible("scale", Matrix::scaling(scale) * self.shearing())?;
+        self.scale = scale;
+
+        Ok(())
+    }
+
+    pub fn shear(&self) -> (f64, f64) {
+        (self.shear_x, self.shear_y)
+    }
+
+    /// Fails if the shear collapses the image into a line, see [`Transform::set_scale`].
+    pub fn set_shear(&mut self, shear_x: f64, shear_y: f64) -> Result<()> {
+        check_range("shear_x", shear_x, -10.0, 10.0)?;
+        check_range("shear_y", shear_y, -10.0, 10.0)?;
+        Transform::check_invertible(
+            "shear",
+            Matrix::scaling(self.scale) * Matrix::shear(shear_x, shear_y),
+        )?;
+        self.shear_x = shear_x;
+        self.shear_y = shear_y;
+
+        Ok(())
+    }
+
+    fn shearing(&self) -> Matrix {
+        Matrix::shear(self.shear_x, self.shear_y)
+    }
+
+    fn check_invertible(parameter: &str, matrix: Matrix) -> Result<()> {
+        match matrix.inverse() {
+            Some(_) => Ok(()),
+            None => Err(GeneratorError::InvalidParameter {
+                parameter: parameter.to_string(),
+                reason: "the transformation collapses the image".to_string(),
+            }),
+        }
+    }
+
+    /// The transformation of the image in pixels for an image of `size`.
+    pub fn matrix(&self, size: &(u32, u32)) -> Matrix {
+        let pixels = Coordinate::new(
+            size.0.saturating_sub(1) as f64,
+            size.1.saturating_sub(1) as f64,
+            1.0,
+        );
+
+        let linear = Matrix::rotation_z(self.rotation.to_radians())
+            * Matrix::scaling(self.scale)
+            * self.shearing();
+
+        Matrix::translation(self.translation * pixels) * linear.around(self.pivot * pixels)
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Node for Transform {
+    fn node_type(&self) -> &'static str {
+        "transform"
+    }
+
+    /// The generator already evaluated the input at the transformed position.
+    fn generate(
+        &self,
+        _position: &Coordinate,
+        _size: &(u32, u32),
+        input: HashMap<String, InputOutputValue>,
+    ) -> Result<InputOutputValue> {
+        self.input_value(&input, DEFAULT_INPUT)
+    }
+
+    fn generate_batch(
+        &self,
+        positions: &[Coordinate],
+        _size: &(u32, u32),
+        inputs: &BatchInputs,
+    ) -> Result<Vec<InputOutputValue>> {
+        let input = self.batch_input(inputs, DEFAULT_INPUT)?;
+
+        Ok((0..positions.len()).map(|index| input.get(index)).collect())
+    }
+
+    fn warped_input(&self) -> Option<&str> {
+        Some(DEFAULT_INPUT)
+    }
+
+    /// A pixel of the transformed image shows the input at the position the transformation moved there.
+    fn warp(
+        &self,
+        positions: &[Coordinate],
+        size: &(u32, u32),
+        _inputs: &BatchInputs,
+    ) -> Result<Vec<Coordinate>> {
+        let inverse =
+            self.matrix(size)
+                .inverse()
+                .ok_or_else(|| GeneratorError::InvalidParameter {
+                    parameter: "scale".to_string(),
+                    reason: "the transformation collapses the image".to_string(),
+                })?;
+
+        Ok(positions
+            .iter()
+            .map(|position| inverse.transform(position))
+            .collect())
+    }
+
+    fn inputs(&self) -> Vec<Port> {
+        vec![Port::new(DEFAULT_INPUT)]
+    }
+
+    fn outputs(&self) -> Vec<Port> {
+        vec![Port::new(DEFAULT_OUTPUT)]
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![
+            Parameter::new(
+                "translation",
+                ParameterKind::Coordinate,
+                ParameterValue::Coordinate(self.translation),
+            ),
+            Parameter::new(
+                "rotation",
+                ParameterKind::Float {
+                    min: -360.0,
+                    max: 360.0,
+                },
+                ParameterValue::Float(self.rotation),
+            ),
+            Parameter::new(
+                "pivot",
+                ParameterKind::Coordinate,
+                ParameterValue::Coordinate(self.pivot),
+            ),
+            Parameter::new(
+                "scale",
+                ParameterKind::Coordinate,
+                ParameterValue::Coordinate(self.scale),
+            ),
+            Parameter::new(
+                "shear_x",
+                ParameterKind::Float {
+                    min: -10.0,
+                    max: 10.0,
+                },
+                ParameterValue::Float(self.shear_x),
+            ),
+            Parameter::new(
+                "shear_y",
+                ParameterKind::Float {
+                    min: -10.0,
+                    max: 10.0,
+                },
+                ParameterValue::Float(self.shear_y),
+            ),
+        ]
+    }
+
+    fn set_parameter(&mut self, name: &str, value: ParameterValue) -> Result<()> {
+        match (name, value) {
+            ("translation", ParameterValue::Coordinate(translation)) => {
+                self.set_translation(translation);
+                Ok(())
+            }
+            ("rotation", ParameterValue::Float(rotation)) => self.set_rotation(rotation),
+            ("pivot", ParameterValue::Coordinate(pivot)) => {
+                self.set_pivot(pivot);
+                Ok(())
+            }
+            ("scale", ParameterValue::Coordinate(scale)) => self.set_scale(scale),
+            ("shear_x", ParameterValue::Float(shear_x)) => self.set_shear(shear_x, self.shear_y),
+            ("shear_y", ParameterValue::Float(shear_y)) => self.set_shear(self.shear_x, shear_y),
+            (name, value) => Err(unsupported_parameter(self, name, &value)),
+        }
+    }
+
+    fn render(&self, plane: &mut Plane) -> Result<()> {
+        let space_info = self.space_info();
+
+        render_square(
+            plane,
+            space_info.position,
+            space_info.size,
+            space_info.color,
+        )?;
+
+        let (_, mut delta_y) = BitmapChar::render_multiple_with_scale(
+            plane,
+            (space_info.position.0, space_info.position.1 + 5),
+            &space_info.name,
+            Pixel::new(0, 0, 0, 255),
+            2,
+        )?;
+
+        let lines = [
+            format!("Translation: {}", self.translation),
+            format!("Rotation: {}", self.rotation),
+            format!("Pivot: {}", self.pivot),
+            format!("Scale: {}", self.scale),
+            format!("Shear: {} {}", self.shear_x, self.shear_y),
+        ];
+        for line in lines {
+            let (_, delta) = BitmapChar::render_multiple_with_scale(
+                plane,
+                (
+                    space_info.position.0,
+                    space_info.position.1 + 5 + delta_y as i64,
+                ),
+                line,
+                Pixel::new(0, 0, 0, 255),
+                1,
+            )?;
+            delta_y += delta;
+        }
+
+        Ok(())
+    }
+
+    fn space_info(&self) -> &SpaceInfo {
+        &self.space_info
+    }
+
+    fn space_info_mut(&mut self) -> &mut SpaceInfo {
+        &mut self.space_info
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Transform;
+    use crate::coordinate::Coordinate;
+    use crate::error::GeneratorError;
+    use crate::generator::Generator;
+    use crate::library::noise::Noise;
+    use crate::link::Link;
+
+    #[test]
+    fn the_input_is_rotated_around_the_pivot() {
+        let mut g = Generator::new();
+        let noise = g.add_node(Noise::new(3));
+        let mut transform = Transform::new();
+        transform.set_rotation(90.0).unwrap();
+        let transform = g.add_node(transform);
+        g.add_edge(Link::new(noise, transform).unwrap()).unwrap();
+        g.add_edge(Link::new(transform, g.output_node()).unwrap())
+            .unwrap();
+
+        let source = g.generate_from(noise, 5, 5).unwrap();
+        let rotated = g.generate(5, 5).unwrap();
+        assert_ne!(source.as_data_flatten(), rotated.as_data_flatten());
+        // Turning by a quarter around the center shows the pixel (y, 4 - x) of the input at (x, y).
+        for y in 0..5 {
+            for x in 0..5 {
+                let expected = source.pixel(y, 4 - x).unwrap().to_raw();
+                let actual = rotated.pixel(x, y).unwrap().to_raw();
+                for (expected, actual) in expected.iter().zip(actual) {
+                    assert!(expected.abs_diff(actual) <= 1, "pixel ({x}, {y})");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn collapsing_transformations_are_rejected() {
+        let mut transform = Transform::new();
+
+        assert!(matches!(
+            transform.set_scale(Coordinate::new(1.0, 0.0, 1.0)),
+            Err(GeneratorError::InvalidParameter { parameter, .. }) if parameter == "scale"
+        ));
+        assert!(transform.set_shear(1.0, 1.0).is_err());
+        assert!(transform.set_shear(1.0, 0.5).is_ok());
+        assert_eq!(transform.scale(), Coordinate::new(1.0, 1.0, 1.0));
+    }
+}
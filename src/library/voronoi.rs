@@ -0,0 +1,621 @@
+use std::collections::HashMap;
+
+use rusvid_core::pixel::Pixel;
+use rusvid_core::plane::Plane;
+
+use crate::bitmap::BitmapChar;
+use crate::coordinate::Coordinate;
+use crate::error::{GeneratorError, Result};
+use crate::input_output_value::{InputOutputKind, InputOutputValue};
+use crate::library::noise::{tile_period, NoisePoint};
+use crate::node::{BatchInputs, Node, SpaceInfo};
+use crate::parameter::{
+    check_range, unsupported_parameter, Parameter, ParameterKind, ParameterValue,
+};
+use crate::port::Port;
+use crate::utils::render_square;
+
+/// Distance to the selected feature point, see [`VoronoiMode`].
+pub const DISTANCE_OUTPUT: &str = "distance";
+/// Distance to the border of the cell. Only [`VoronoiMetric::Euclidean`] has straight borders, the other metrics
+/// approximate it with half the gap between the two nearest feature points.
+pub const EDGE_OUTPUT: &str = "edge";
+/// Random value of the cell between `0.0` and `1.0`.
+pub const CELL_OUTPUT: &str = "cell";
+/// Random colour of the cell.
+pub const COLOR_OUTPUT: &str = "color";
+
+/// How the distance between a sample and a feature point is measured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VoronoiMetric {
+    Euclidean,
+    Manhattan,
+    Chebyshev,
+    /// Generalizes the other metrics with an exponent, `1.0` is Manhattan and `2.0` Euclidean.
+    Minkowski,
+}
+
+impl VoronoiMetric {
+    pub const ALL: [VoronoiMetric; 4] = [
+        VoronoiMetric::Euclidean,
+        VoronoiMetric::Manhattan,
+        VoronoiMetric::Chebyshev,
+        VoronoiMetric::Minkowski,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            VoronoiMetric::Euclidean => "euclidean",
+            VoronoiMetric::Manhattan => "manhattan",
+            VoronoiMetric::Chebyshev => "chebyshev",
+            VoronoiMetric::Minkowski => "minkowski",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<VoronoiMetric> {
+        VoronoiMetric::ALL
+            .into_iter()
+            .find(|metric| metric.name() == name)
+    }
+
+    fn distance(&self, dx: f64, dy: f64, exponent: f64) -> f64 {
+        let (dx, dy) = (dx.abs(), dy.abs());
+
+        match self {
+            VoronoiMetric::Euclidean => (dx * dx + dy * dy).sqrt(),
+            VoronoiMetric::Manhattan => dx + dy,
+            VoronoiMetric::Chebyshev => dx.max(dy),
+            VoronoiMetric::Minkowski => {
+                (dx.powf(exponent) + dy.powf(exponent)).powf(exponent.recip())
+            }
+        }
+    }
+}
+
+/// Which distance the `distance` output of [`Voronoi`] returns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VoronoiMode {
+    /// Distance to the nearest feature point.
+    F1,
+    /// Distance to the second nearest feature point.
+    F2,
+    /// Difference of both, zero on the borders between the cells.
+    F2MinusF1,
+}
+
+impl VoronoiMode {
+    pub const ALL: [VoronoiMode; 3] = [VoronoiMode::F1, VoronoiMode::F2, VoronoiMode::F2MinusF1];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            VoronoiMode::F1 => "f1",
+            VoronoiMode::F2 => "f2",
+            VoronoiMode::F2MinusF1 => "f2_minus_f1",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<VoronoiMode> {
+        VoronoiMode::ALL
+            .into_iter()
+            .find(|mode| mode.name() == name)
+    }
+}
+
+/// The nearest feature points of a sample.
+struct Cells {
+    f1: f64,
+    f2: f64,
+    /// Distance to the border of the nearest cell, see [`EDGE_OUTPUT`].
+    edge: f64,
+    /// The cell of the nearest feature point.
+    nearest: (i64, i64),
+}
+
+/// Cellular noise: every cell of a grid has a randomly placed feature point, the outputs describe the feature points
+/// near a sample.
+///
+/// The outputs are the [`DISTANCE_OUTPUT`], the [`EDGE_OUTPUT`], and the random [`CELL_OUTPUT`] and [`COLOR_OUTPUT`]
+/// of the nearest cell. Distances are measured in cells and clamped to `0.0..=1.0`.
+///
+/// Tileable output rounds the scale to whole cells and repeats the cells after one image.
+#[derive(Debug)]
+pub struct Voronoi {
+    seed: u32,
+    offset: Coordinate,
+    scale: Coordinate,
+    metric: VoronoiMetric,
+    /// Exponent of [`VoronoiMetric::Minkowski`].
+    exponent: f64,
+    mode: VoronoiMode,
+    /// How far the feature points move away from the center of their cell, `0.0` results in a regular grid.
+    jitter: f64,
+    /// How much the value and colour of the cells vary, `0.0` makes every cell grey.
+    randomness: f64,
+
+    space_info: SpaceInfo,
+}
+
+impl Voronoi {
+    pub fn new(seed: u32) -> Self {
+        Voronoi {
+            seed,
+            offset: Coordinate::new(0.0, 0.0, 0.0),
+            scale: Coordinate::new(8.0, 8.0, 1.0),
+            metric: VoronoiMetric::Euclidean,
+            exponent: 3.0,
+            mode: VoronoiMode::F1,
+            jitter: 1.0,
+            randomness: 1.0,
+
+            space_info: {
+                let mut si = SpaceInfo::default();
+
+                si.size = (250, 110);
+
+                si
+            },
+        }
+    }
+
+    pub fn seed(&self) -> u32 {
+        self.seed
+    }
+
+    pub fn set_seed(&mut self, seed: u32) {
+        self.seed = seed;
+    }
+
+    pub fn offset(&self) -> Coordinate {
+        self.offset
+    }
+
+    pub fn set_offset(&mut self, offset: Coordinate) {
+        self.offset = offset;
+    }
+
+    pub fn scale(&self) -> Coordinate {
+        self.scale
+    }
+
+    pub fn set_scale(&mut self, scale: Coordinate) {
+        self.scale = scale;
+    }
+
+    pub fn metric(&self) -> VoronoiMetric {
+        self.metric
+    }
+
+    pub fn set_metric(&mut self, metric: VoronoiMetric) {
+        self.metric = metric;
+    }
+
+    pub fn exponent(&self) -> f64 {
+        self.exponent
+    }
+
+    pub fn set_exponent(&mut self, exponent: f64) -> Result<()> {
+        self.exponent = check_range("exponent", exponent, 0.5, 10.0)?;
+
+        Ok(())
+    }
+
+    pub fn mode(&self) -> VoronoiMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: VoronoiMode) {
+        self.mode = mode;
+    }
+
+    pub fn jitter(&self) -> f64 {
+        self.jitter
+    }
+
+    pub fn set_jitter(&mut self, jitter: f64) -> Result<()> {
+        self.jitter = check_range("jitter", jitter, 0.0, 1.0)?;
+
+        Ok(())
+    }
+
+    pub fn randomness(&self) -> f64 {
+        self.randomness
+    }
+
+    pub fn set_randomness(&mut self, randomness: f64) -> Result<()> {
+        self.randomness = check_range("randomness", randomness, 0.0, 1.0)?;
+
+        Ok(())
+    }
+
+    /// Random number between `0.0` and `1.0` for the cell, `salt` picks one of multiple numbers per cell.
+    fn random(&self, cell: (i64, i64), salt: u64) -> f64 {
+        // SplitMix64 finalizer over the cell, seed and salt.
+        let mut hash = (cell.0 as u64)
+            .wrapping_mul(0x9E37_79B9_7F4A_7C15)
+            .wrapping_add((cell.1 as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F))
+            .wrapping_add((self.seed as u64) << 32)
+            .wrapping_add(salt.wrapping_mul(0x1656_67B1_9E37_79F9));
+        hash = (hash ^ (hash >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        hash = (hash ^ (hash >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        hash ^= hash >> 31;
+
+        (hash >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// The cell whose random numbers `cell` uses, tileable output repeats the cells of the first image.
+    fn wrap(&self, cell: (i64, i64), tileable: bool) -> (i64, i64) {
+        if !tileable {
+            return cell;
+        }
+
+        let [period_x, period_y] = tile_period(&self.scale);
+
+        (cell.0.rem_euclid(period_x), cell.1.rem_euclid(period_y))
+    }
+
+    fn feature_point(&self, cell: (i64, i64), tileable: bool) -> (f64, f64) {
+        let key = self.wrap(cell, tileable);
+
+        (
+            cell.0 as f64 + 0.5 + (self.random(key, 0) - 0.5) * self.jitter,
+            cell.1 as f64 + 0.5 + (self.random(key, 1) - 0.5) * self.jitter,
+        )
+    }
+
+    fn cells(&self, position: &Coordinate, size: &(u32, u32), tileable: bool) -> Cells {
+        let (NoisePoint::Space([x, y, _])
+        | NoisePoint::Tiled {
+            point: [x, y, _], ..
+        }) = NoisePoint::new(position, size, &self.offset, &self.scale, tileable);
+        let (cell_x, cell_y) = (x.floor() as i64, y.floor() as i64);
+
+        let mut cells = Cells {
+            f1: f64::INFINITY,
+            f2: f64::INFINITY,
+            edge: f64::INFINITY,
+            nearest: self.wrap((cell_x, cell_y), tileable),
+        };
+        let mut points = [(0.0, 0.0); 25];
+        let mut nearest = 0;
+        // The feature points stay inside of their cell, the second nearest point is at most two cells away.
+        for (index, point) in points.iter_mut().enumerate() {
+            let cell = (cell_x + index as i64 % 5 - 2, cell_y + index as i64 / 5 - 2);
+            *point = self.feature_point(cell, tileable);
+            let distance = self
+                .metric
+                .distance(point.0 - x, point.1 - y, self.exponent);
+
+            if distance < cells.f1 {
+                cells.f2 = cells.f1;
+                cells.f1 = distance;
+                cells.nearest = self.wrap(cell, tileable);
+                nearest = index;
+            } else if distance < cells.f2 {
+                cells.f2 = distance;
+            }
+        }
+
+        if self.metric != VoronoiMetric::Euclidean {
+            cells.edge = (cells.f2 - cells.f1) / 2.0;
+            return cells;
+        }
+        // The border towards every other point is their bisector, (d² - f1²) / (2 * |point - nearest|) away.
+        let (near_x, near_y) = points[nearest];
+        for (index, (point_x, point_y)) in points.into_iter().enumerate() {
+            if index == nearest {
+                continue;
+            }
+            let gap = (point_x - near_x).hypot(point_y - near_y);
+            let distance_squared = (point_x - x).powi(2) + (point_y - y).powi(2);
+            cells.edge = cells
+                .edge
+                .min((distance_squared - cells.f1 * cells.f1) / (2.0 * gap));
+        }
+
+        cells
+    }
+
+    fn cell_value(&self, cell: (i64, i64), salt: u64) -> f64 {
+        0.5 + (self.random(cell, salt) - 0.5) * self.randomness
+    }
+
+    fn sample(
+        &self,
+        position: &Coordinate,
+        size: &(u32, u32),
+        tileable: bool,
+    ) -> [InputOutputValue; 4] {
+        let cells = self.cells(position, size, tileable);
+
+        let distance = match self.mode {
+            VoronoiMode::F1 => cells.f1,
+            VoronoiMode::F2 => cells.f2,
+            VoronoiMode::F2MinusF1 => cells.f2 - cells.f1,
+        };
+        let channel = |salt| (self.cell_value(cells.nearest, salt) * 255.0).round() as u8;
+
+        [
+            InputOutputValue::Float(distance.clamp(0.0, 1.0)),
+            InputOutputValue::Float(cells.edge.clamp(0.0, 1.0)),
+            InputOutputValue::Float(self.cell_value(cells.nearest, 2)),
+            InputOutputValue::Pixel(Pixel::new(channel(3), channel(4), channel(5), 255)),
+        ]
+    }
+}
+
+impl Node for Voronoi {
+    fn node_type(&self) -> &'static str {
+        "voronoi"
+    }
+
+    fn generate(
+        &self,
+        position: &Coordinate,
+        size: &(u32, u32),
+        _input: HashMap<String, InputOutputValue>,
+    ) -> Result<InputOutputValue> {
+        Ok(self.sample(position, size, false)[0])
+    }
+
+    fn generate_outputs(
+        &self,
+        position: &Coordinate,
+        size: &(u32, u32),
+        _input: HashMap<String, InputOutputValue>,
+    ) -> Result<Vec<InputOutputValue>> {
+        Ok(self.sample(position, size, false).to_vec())
+    }
+
+    fn generate_batch_outputs(
+        &self,
+        positions: &[Coordinate],
+        size: &(u32, u32),
+        inputs: &BatchInputs,
+    ) -> Result<Vec<Vec<InputOutputValue>>> {
+        let mut outputs = vec![Vec::with_capacity(positions.len()); 4];
+        for position in positions {
+            let values = self.sample(position, size, inputs.tileable());
+            for (output, value) in outputs.iter_mut().zip(values) {
+                output.push(value);
+            }
+        }
+
+        Ok(outputs)
+    }
+
+    fn outputs(&self) -> Vec<Port> {
+        vec![
+            Port::new(DISTANCE_OUTPUT).with_kinds(&[InputOutputKind::Float]),
+            Port::new(EDGE_OUTPUT).with_kinds(&[InputOutputKind::Float]),
+            Port::new(CELL_OUTPUT).with_kinds(&[InputOutputKind::Float]),
+            Port::new(COLOR_OUTPUT).with_kinds(&[InputOutputKind::Pixel]),
+        ]
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![
+            Parameter::new("seed", ParameterKind::Seed, ParameterValue::Seed(self.seed)),
+            Parameter::new(
+                "offset",
+                ParameterKind::Coordinate,
+                ParameterValue::Coordinate(self.offset),
+            ),
+            Parameter::new(
+                "scale",
+                ParameterKind::Coordinate,
+                ParameterValue::Coordinate(self.scale),
+            ),
+            Parameter::new(
+                "metric",
+                ParameterKind::Enum {
+                    variants: VoronoiMetric::ALL
+                        .iter()
+                        .map(|metric| metric.name().to_string())
+                        .collect(),
+                },
+                ParameterValue::Enum(self.metric.name().to_string()),
+            ),
+            Parameter::new(
+                "exponent",
+                ParameterKind::Float {
+                    min: 0.5,
+                    max: 10.0,
+                },
+                ParameterValue::Float(self.exponent),
+            ),
+            Parameter::new(
+                "mode",
+                ParameterKind::Enum {
+                    variants: VoronoiMode::ALL
+                        .iter()
+                        .map(|mode| mode.name().to_string())
+                        .collect(),
+                },
+                ParameterValue::Enum(self.mode.name().to_string()),
+            ),
+            Parameter::new(
+                "jitter",
+                ParameterKind::Float { min: 0.0, max: 1.0 },
+                ParameterValue::Float(self.jitter),
+            ),
+            Parameter::new(
+                "randomness",
+                ParameterKind::Float { min: 0.0, max: 1.0 },
+                ParameterValue::Float(self.randomness),
+            ),
+        ]
+    }
+
+    fn set_parameter(&mut self, name: &str, value: ParameterValue) -> Result<()> {
+        let invalid = |reason: String| GeneratorError::InvalidParameter {
+            parameter: name.to_string(),
+            reason,
+        };
+
+        match (name, value) {
+            ("seed", ParameterValue::Seed(seed)) => self.set_seed(seed),
+            ("offset", ParameterValue::Coordinate(offset)) => self.set_offset(offset),
+            ("scale", ParameterValue::Coordinate(scale)) => self.set_scale(scale),
+            ("metric", ParameterValue::Enum(metric)) => {
+                let metric = VoronoiMetric::from_name(&metric)
+                    .ok_or_else(|| invalid(format!("unknown metric {metric:?}")))?;
+                self.set_metric(metric);
+            }
+            ("exponent", ParameterValue::Float(exponent)) => self.set_exponent(exponent)?,
+            ("mode", ParameterValue::Enum(mode)) => {
+                let mode = VoronoiMode::from_name(&mode)
+                    .ok_or_else(|| invalid(format!("unknown mode {mode:?}")))?;
+                self.set_mode(mode);
+            }
+            ("jitter", ParameterValue::Float(jitter)) => self.set_jitter(jitter)?,
+            ("randomness", ParameterValue::Float(randomness)) => self.set_randomness(randomness)?,
+            (name, value) => return Err(unsupported_parameter(self, name, &value)),
+        }
+
+        Ok(())
+    }
+
+    fn render(&self, plane: &mut Plane) -> Result<()> {
+        let space_info = self.space_info();
+
+        render_square(
+            plane,
+            space_info.position,
+            space_info.size,
+            space_info.color,
+        )?;
+
+        let (_, mut delta_y) = BitmapChar::render_multiple_with_scale(
+            plane,
+            (space_info.position.0, space_info.position.1 + 5),
+            &space_info.name,
+            Pixel::new(0, 0, 0, 255),
+            2,
+        )?;
+
+        let metric = match self.metric {
+            VoronoiMetric::Minkowski => format!("Metric: minkowski {}", self.exponent),
+            metric => format!("Metric: {}", metric.name()),
+        };
+        let lines = [
+            format!("Seed: {}", self.seed),
+            format!("Offset: {}", self.offset),
+            format!("Scale: {}", self.scale),
+            metric,
+            format!("Mode: {}", self.mode.name()),
+            format!("Jitter: {}", self.jitter),
+            format!("Randomness: {}", self.randomness),
+        ];
+        for line in lines {
+            let (_, delta) = BitmapChar::render_multiple_with_scale(
+                plane,
+                (
+                    space_info.position.0,
+                    space_info.position.1 + 5 + delta_y as i64,
+                ),
+                line,
+                Pixel::new(0, 0, 0, 255),
+                1,
+            )?;
+            delta_y += delta;
+        }
+
+        Ok(())
+    }
+
+    fn space_info(&self) -> &SpaceInfo {
+        &self.space_info
+    }
+
+    fn space_info_mut(&mut self) -> &mut SpaceInfo {
+        &mut self.space_info
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use rusvid_core::pixel::Pixel;
+
+    use super::{Voronoi, VoronoiMetric, VoronoiMode};
+    use crate::coordinate::Coordinate;
+    use crate::input_output_value::InputOutputValue;
+    use crate::node::Node;
+
+    /// Without jitter the feature points sit in the center of the cells, with this size and scale every second pixel is
+    /// the center of a cell.
+    fn grid() -> Voronoi {
+        let mut voronoi = Voronoi::new(1);
+        voronoi.set_jitter(0.0).unwrap();
+        voronoi.set_scale(Coordinate::new_xy(4.0, 4.0));
+
+        voronoi
+    }
+
+    fn outputs(voronoi: &Voronoi, x: f64, y: f64) -> Vec<InputOutputValue> {
+        voronoi
+            .generate_outputs(&Coordinate::new_xy(x, y), &(9, 9), HashMap::new())
+            .unwrap()
+    }
+
+    fn float(value: InputOutputValue) -> f64 {
+        match value {
+            InputOutputValue::Float(value) => value,
+            other => panic!("expected a float but got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn distances_are_measured_to_the_feature_points() {
+        let mut voronoi = grid();
+
+        // The center of a cell.
+        let center = outputs(&voronoi, 1.0, 1.0);
+        assert_eq!(float(center[0]), 0.0);
+        assert_eq!(float(center[1]), 0.5);
+
+        // On the border between two cells.
+        let border = outputs(&voronoi, 2.0, 1.0);
+        assert_eq!(float(border[0]), 0.5);
+        assert_eq!(float(border[1]), 0.0);
+
+        // The corner between four cells.
+        let corner = |voronoi: &Voronoi| float(outputs(voronoi, 2.0, 2.0)[0]);
+        assert!((corner(&voronoi) - 0.5f64.sqrt()).abs() < 1e-9);
+        voronoi.set_metric(VoronoiMetric::Manhattan);
+        assert_eq!(corner(&voronoi), 1.0);
+        voronoi.set_metric(VoronoiMetric::Chebyshev);
+        assert_eq!(corner(&voronoi), 0.5);
+        voronoi.set_metric(VoronoiMetric::Minkowski);
+        voronoi.set_exponent(1.0).unwrap();
+        assert!((corner(&voronoi) - 1.0).abs() < 1e-9);
+
+        // Diagonally away from the center, the border is closer than half the gap between the nearest points.
+        voronoi.set_metric(VoronoiMetric::Euclidean);
+        assert!((float(outputs(&voronoi, 1.8, 1.8)[1]) - 0.1).abs() < 1e-9);
+
+        voronoi.set_metric(VoronoiMetric::Euclidean);
+        voronoi.set_mode(VoronoiMode::F2MinusF1);
+        assert_eq!(float(outputs(&voronoi, 1.0, 1.0)[0]), 1.0);
+    }
+
+    #[test]
+    fn every_cell_has_its_own_value() {
+        let mut voronoi = grid();
+
+        let cell = |voronoi: &Voronoi, x, y| outputs(voronoi, x, y)[2..].to_vec();
+        // Both pixels are closest to the center of the first cell.
+        assert_eq!(cell(&voronoi, 1.4, 0.8), cell(&voronoi, 1.0, 1.0));
+        assert_ne!(cell(&voronoi, 1.0, 1.0), cell(&voronoi, 3.0, 1.0));
+
+        voronoi.set_randomness(0.0).unwrap();
+        assert_eq!(
+            cell(&voronoi, 3.0, 1.0),
+            vec![
+                InputOutputValue::Float(0.5),
+                InputOutputValue::Pixel(Pixel::new(128, 128, 128, 255))
+            ]
+        );
+    }
+}
@@ -0,0 +1,243 @@
+use std::collections::HashMap;
+
+use rusvid_core::pixel::Pixel;
+use rusvid_core::plane::Plane;
+
+use crate::bitmap::BitmapChar;
+use crate::coordinate::Coordinate;
+use crate::error::Result;
+use crate::input_output_value::{InputOutputKind, InputOutputValue};
+use crate::node::{BatchInputs, Node, SpaceInfo};
+use crate::parameter::{
+    check_range, unsupported_parameter, Parameter, ParameterKind, ParameterValue,
+};
+use crate::port::{Port, DEFAULT_INPUT, DEFAULT_OUTPUT};
+use crate::utils::render_square;
+
+/// Name of the input that moves the positions at which the [`DEFAULT_INPUT`] gets evaluated.
+pub const OFFSET_INPUT: &str = "offset";
+
+/// Domain warping: evaluates the nodes before its input at positions that are moved by the offset input, e.g. noise
+/// warped by another noise gives marble and flow textures.
+///
+/// The offset is measured in units of the image size, a float moves both axes by the same amount, arrays use their
+/// first two values and colors their red and green channel centered around mid grey.
+#[derive(Debug)]
+pub struct Warp {
+    strength: f64,
+
+    space_info: SpaceInfo,
+}
+
+impl Warp {
+    pub fn new(strength: f64) -> Result<Self> {
+        Ok(Warp {
+            strength: check_range("strength", strength, 0.0, 10.0)?,
+
+            space_info: {
+                let mut si = SpaceInfo::default();
+
+                si.size = (200, 60);
+
+                si
+            },
+        })
+    }
+
+    pub fn strength(&self) -> f64 {
+        self.strength
+    }
+
+    pub fn set_strength(&mut self, strength: f64) -> Result<()> {
+        self.strength = check_range("strength", strength, 0.0, 10.0)?;
+
+        Ok(())
+    }
+
+    fn displacement(value: InputOutputValue) -> Result<(f64, f64)> {
+        match value {
+            InputOutputValue::Nothing => Ok((0.0, 0.0)),
+            InputOutputValue::Float(value) => Ok((value, value)),
+            InputOutputValue::F64X3Array([x, y, _])
+            | InputOutputValue::F64X4Array([x, y, _, _]) => Ok((x, y)),
+            color => Ok((
+                color.r_percentage()? * 2.0 - 1.0,
+                color.g_percentage()? * 2.0 - 1.0,
+            )),
+        }
+    }
+}
+
+impl Node for Warp {
+    fn node_type(&self) -> &'static str {
+        "warp"
+    }
+
+    /// The generator already evaluated the input at the warped position.
+    fn generate(
+        &self,
+        _position: &Coordinate,
+        _size: &(u32, u32),
+        input: HashMap<String, InputOutputValue>,
+    ) -> Result<InputOutputValue> {
+        self.input_value(&input, DEFAULT_INPUT)
+    }
+
+    fn generate_batch(
+        &self,
+        positions: &[Coordinate],
+        _size: &(u32, u32),
+        inputs: &BatchInputs,
+    ) -> Result<Vec<InputOutputValue>> {
+        let input = self.batch_input(inputs, DEFAULT_INPUT)?;
+
+        Ok((0..positions.len()).map(|index| input.get(index)).collect())
+    }
+
+    fn warped_input(&self) -> Option<&str> {
+        Some(DEFAULT_INPUT)
+    }
+
+    fn warp(
+        &self,
+        positions: &[Coordinate],
+        size: &(u32, u32),
+        inputs: &BatchInputs,
+    ) -> Result<Vec<Coordinate>> {
+        let offset = self.batch_input(inputs, OFFSET_INPUT)?;
+        let scale_x = self.strength * size.0.saturating_sub(1) as f64;
+        let scale_y = self.strength * size.1.saturating_sub(1) as f64;
+
+        positions
+            .iter()
+            .enumerate()
+            .map(|(index, position)| {
+                let (dx, dy) = Warp::displacement(offset.get(index))?;
+
+                Ok(*position + Coordinate::new_xy(dx * scale_x, dy * scale_y))
+            })
+            .collect()
+    }
+
+    fn inputs(&self) -> Vec<Port> {
+        vec![
+            Port::new(DEFAULT_INPUT),
+            Port::new(OFFSET_INPUT)
+                .with_kinds(&[
+                    InputOutputKind::Float,
+                    InputOutputKind::Pixel,
+                    InputOutputKind::U8X3Array,
+                    InputOutputKind::U8X4Array,
+                    InputOutputKind::F64X3Array,
+                    InputOutputKind::F64X4Array,
+                ])
+                .optional(InputOutputValue::Float(0.0)),
+        ]
+    }
+
+    fn outputs(&self) -> Vec<Port> {
+        vec![Port::new(DEFAULT_OUTPUT)]
+    }
+
+    fn parameters(&self) -> Vec<Parameter> {
+        vec![Parameter::new(
+            "strength",
+            ParameterKind::Float {
+                min: 0.0,
+                max: 10.0,
+            },
+            ParameterValue::Float(self.strength),
+        )]
+    }
+
+    fn set_parameter(&mut self, name: &str, value: ParameterValue) -> Result<()> {
+        match (name, value) {
+            ("strength", ParameterValue::Float(strength)) => self.set_strength(strength),
+            (name, value) => Err(unsupported_parameter(self, name, &value)),
+        }
+    }
+
+    fn render(&self, plane: &mut Plane) -> Result<()> {
+        let space_info = self.space_info();
+
+        render_square(
+            plane,
+            space_info.position,
+            space_info.size,
+            space_info.color,
+        )?;
+
+        let (_, delta_y) = BitmapChar::render_multiple_with_scale(
+            plane,
+            (space_info.position.0, space_info.position.1 + 5),
+            &space_info.name,
+            Pixel::new(0, 0, 0, 255),
+            2,
+        )?;
+        BitmapChar::render_multiple_with_scale(
+            plane,
+            (
+                space_info.position.0,
+                space_info.position.1 + 5 + delta_y as i64,
+            ),
+            format!("Strength: {}", self.strength),
+            Pixel::new(0, 0, 0, 255),
+            1,
+        )?;
+
+        Ok(())
+    }
+
+    fn space_info(&self) -> &SpaceInfo {
+        &self.space_info
+    }
+
+    fn space_info_mut(&mut self) -> &mut SpaceInfo {
+        &mut self.space_info
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Warp, OFFSET_INPUT};
+    use crate::coordinate::Coordinate;
+    use crate::generator::Generator;
+    use crate::input_output_value::InputOutputValue;
+    use crate::library::noise::Noise;
+    use crate::library::static_value::StaticValue;
+    use crate::link::Link;
+    use crate::port::DEFAULT_INPUT;
+
+    #[test]
+    fn the_input_is_evaluated_at_the_moved_positions() {
+        let mut g = Generator::new();
+        let noise = g.add_node(Noise::new(7));
+        let offset = g.add_node(StaticValue::new(InputOutputValue::Float(0.25)));
+        let warp = g.add_node(Warp::new(1.0).unwrap());
+        g.add_edge_named(Link::new(noise, warp).unwrap(), DEFAULT_INPUT)
+            .unwrap();
+        g.add_edge_named(Link::new(offset, warp).unwrap(), OFFSET_INPUT)
+            .unwrap();
+        g.add_edge(Link::new(warp, g.output_node()).unwrap())
+            .unwrap();
+        let warped = g.generate(5, 5).unwrap();
+        assert_ne!(
+            warped.as_data_flatten(),
+            g.generate_from(noise, 5, 5).unwrap().as_data_flatten()
+        );
+
+        // A quarter of the image size moves the samples by one pixel, the same as moving the noise by a quarter.
+        let mut shifted = Noise::new(7);
+        shifted.set_offset(Coordinate::new(0.25, 0.25, 0.0));
+        let mut expected = Generator::new();
+        let noise = expected.add_node(shifted);
+        expected
+            .add_edge(Link::new(noise, expected.output_node()).unwrap())
+            .unwrap();
+
+        assert_eq!(
+            warped.as_data_flatten(),
+            expected.generate(5, 5).unwrap().as_data_flatten()
+        );
+    }
+}
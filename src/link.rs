@@ -1,29 +1,52 @@
-use std::cell::RefCell;
-use std::rc::Rc;
-
 use petgraph::stable_graph::NodeIndex;
 
-use crate::node::Node;
+use crate::error::{GeneratorError, Result};
+use crate::node::SharedNode;
 
-pub type LinkNode = Rc<RefCell<dyn Node>>;
+pub type LinkNode = SharedNode;
 
 #[derive(Debug, Clone, PartialEq, PartialOrd, Eq, Hash)]
 pub struct Link {
     pub(crate) input_node: NodeIndex,
     pub(crate) output_node: NodeIndex,
-    // TODO cache the output from `input_node` so that we don't have to recalculate in every link
 }
 
 impl Link {
-    pub fn new(input_node: NodeIndex, output_node: NodeIndex) -> Self {
-        assert_ne!(
-            input_node, output_node,
-            "Input and output node are not allowed to be the same"
-        );
+    pub fn new(input_node: NodeIndex, output_node: NodeIndex) -> Result<Self> {
+        if input_node == output_node {
+            return Err(GeneratorError::SelfLink(input_node));
+        }
 
-        Link {
+        Ok(Link {
             input_node,
             output_node,
-        }
+        })
+    }
+
+    pub fn input_node(&self) -> NodeIndex {
+        self.input_node
+    }
+
+    pub fn output_node(&self) -> NodeIndex {
+        self.output_node
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use petgraph::stable_graph::NodeIndex;
+
+    use super::Link;
+    use crate::error::GeneratorError;
+
+    #[test]
+    fn a_link_can_not_connect_a_node_with_itself() {
+        let node = NodeIndex::new(1);
+
+        assert!(matches!(
+            Link::new(node, node),
+            Err(GeneratorError::SelfLink(index)) if index == node
+        ));
+        assert!(Link::new(node, NodeIndex::new(2)).is_ok());
     }
 }
@@ -1,19 +1,27 @@
 use std::collections::HashMap;
 use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, RwLock};
 
-use anyhow::Result;
 use rusvid_core::pixel::Pixel;
 use rusvid_core::plane::Plane;
+use rusvid_core::prelude::ResizeMode;
+use serde::{Deserialize, Serialize};
 
 use crate::bitmap::BitmapChar;
 use crate::coordinate::Coordinate;
+use crate::diagnostic::DiagnosticKind;
+use crate::error::{GeneratorError, Result};
+use crate::group_definition::SharedGroup;
 use crate::input_output_value::InputOutputValue;
+use crate::parameter::{unsupported_parameter, Parameter, ParameterValue};
+use crate::port::{Port, DEFAULT_OUTPUT};
 use crate::utils::render_square;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct SpaceInfo {
     pub position: (i64, i64),
     pub size: (u32, u32),
+    #[serde(with = "crate::serialization::pixel")]
     pub color: Pixel,
     pub name: String,
     pub z_index: usize,
@@ -33,7 +41,82 @@ impl Default for SpaceInfo {
     }
 }
 
-pub trait Node: std::fmt::Debug {
+/// Width and height of the thumbnail drawn by [`Node::render_preview`].
+pub const PREVIEW_SIZE: u32 = 64;
+const PREVIEW_MARGIN: u32 = 5;
+
+/// A value of an input for every sample of a batch, see [`Node::generate_batch`].
+#[derive(Debug, Clone, Copy)]
+pub enum BatchValue<'a> {
+    /// The input is connected, holds one value per sample.
+    Samples(&'a [InputOutputValue]),
+    /// The input isn't connected and uses the same default for every sample.
+    Constant(InputOutputValue),
+}
+
+impl<'a> BatchValue<'a> {
+    pub fn get(&self, index: usize) -> InputOutputValue {
+        match self {
+            BatchValue::Samples(values) => values[index],
+            BatchValue::Constant(value) => *value,
+        }
+    }
+}
+
+/// The inputs of a node for a whole batch of samples, keyed by the name of the port.
+#[derive(Debug, Clone, Default)]
+pub struct BatchInputs<'a> {
+    inputs: HashMap<&'a str, BatchValue<'a>>,
+    tileable: bool,
+}
+
+impl<'a> BatchInputs<'a> {
+    pub fn new() -> Self {
+        BatchInputs {
+            inputs: HashMap::new(),
+            tileable: false,
+        }
+    }
+
+    /// Whether nodes that depend on the position make their output wrap around the borders of the image, see
+    /// [`Generator::set_tileable`](crate::generator::Generator::set_tileable).
+    ///
+    /// Only batches know about the tiling, [`Node::generate`] always samples the plain output.
+    pub fn tileable(&self) -> bool {
+        self.tileable
+    }
+
+    pub fn set_tileable(&mut self, tileable: bool) {
+        self.tileable = tileable;
+    }
+
+    pub fn insert(&mut self, port: &'a str, value: BatchValue<'a>) {
+        self.inputs.insert(port, value);
+    }
+
+    pub fn get(&self, port: &str) -> Option<BatchValue<'a>> {
+        self.inputs.get(port).copied()
+    }
+
+    /// Collects the inputs of a single sample in the format of [`Node::generate`].
+    pub fn sample(&self, index: usize) -> HashMap<String, InputOutputValue> {
+        self.inputs
+            .iter()
+            .map(|(port, value)| (port.to_string(), value.get(index)))
+            .collect()
+    }
+}
+
+/// A node that is shared between the graph and the editor.
+///
+/// Nodes are `Send + Sync` so that the graph can be rendered from multiple threads, the lock is only written to while
+/// the graph gets edited.
+pub type SharedNode = Arc<RwLock<dyn Node>>;
+
+pub trait Node: std::fmt::Debug + Send + Sync {
+    /// Stable name of the kind of the node, e.g. `"noise.perlin"`, that identifies the node in saved graphs.
+    fn node_type(&self) -> &'static str;
+
     fn generate(
         &self,
         position: &Coordinate,
@@ -41,10 +124,158 @@ pub trait Node: std::fmt::Debug {
         input: HashMap<String, InputOutputValue>,
     ) -> Result<InputOutputValue>;
 
+    /// Evaluates the node for a whole batch of samples, e.g. a row of the image, and returns one value per position.
+    ///
+    /// The default calls [`Node::generate`] for every sample, nodes can override this to run a tight loop without the
+    /// per-sample allocations. Both must return the same values.
+    fn generate_batch(
+        &self,
+        positions: &[Coordinate],
+        size: &(u32, u32),
+        inputs: &BatchInputs,
+    ) -> Result<Vec<InputOutputValue>> {
+        positions
+            .iter()
+            .enumerate()
+            .map(|(index, position)| self.generate(position, size, inputs.sample(index)))
+            .collect()
+    }
+
+    /// Evaluates every output of the node at once, returns one value per port in the order of [`Node::outputs`].
+    ///
+    /// The default returns the value of [`Node::generate`] as the only output, nodes with more than one output must
+    /// override this.
+    fn generate_outputs(
+        &self,
+        position: &Coordinate,
+        size: &(u32, u32),
+        input: HashMap<String, InputOutputValue>,
+    ) -> Result<Vec<InputOutputValue>> {
+        Ok(vec![self.generate(position, size, input)?])
+    }
+
+    /// Like [`Node::generate_batch`] but for every output, returns one buffer per port in the order of
+    /// [`Node::outputs`].
+    fn generate_batch_outputs(
+        &self,
+        positions: &[Coordinate],
+        size: &(u32, u32),
+        inputs: &BatchInputs,
+    ) -> Result<Vec<Vec<InputOutputValue>>> {
+        let count = self.outputs().len();
+        if count <= 1 {
+            return Ok(vec![self.generate_batch(positions, size, inputs)?]);
+        }
+
+        let mut outputs = vec![Vec::with_capacity(positions.len()); count];
+        for (index, position) in positions.iter().enumerate() {
+            let values = self.generate_outputs(position, size, inputs.sample(index))?;
+            for (output, value) in outputs.iter_mut().zip(values) {
+                output.push(value);
+            }
+        }
+
+        Ok(outputs)
+    }
+
+    /// The inputs of the node, the values of connected inputs are passed to [`Node::generate`] under the name of the port.
+    fn inputs(&self) -> Vec<Port> {
+        Vec::new()
+    }
+
+    fn outputs(&self) -> Vec<Port> {
+        vec![Port::new(DEFAULT_OUTPUT)]
+    }
+
+    /// Looks up the value of the input `port`, fails with [`GeneratorError::MissingInput`] if it isn't present.
+    fn input_value(
+        &self,
+        input: &HashMap<String, InputOutputValue>,
+        port: &str,
+    ) -> Result<InputOutputValue> {
+        input
+            .get(port)
+            .copied()
+            .ok_or_else(|| GeneratorError::MissingInput {
+                node: self.space_info().name.clone(),
+                port: port.to_string(),
+            })
+    }
+
+    /// Like [`Node::input_value`] but for the inputs of [`Node::generate_batch`].
+    fn batch_input<'a>(&self, inputs: &BatchInputs<'a>, port: &str) -> Result<BatchValue<'a>> {
+        inputs
+            .get(port)
+            .ok_or_else(|| GeneratorError::MissingInput {
+                node: self.space_info().name.clone(),
+                port: port.to_string(),
+            })
+    }
+
+    /// The settings of the node with their current values, also used to save and load the node.
+    fn parameters(&self) -> Vec<Parameter> {
+        Vec::new()
+    }
+
+    fn parameter(&self, name: &str) -> Result<ParameterValue> {
+        self.parameters()
+            .into_iter()
+            .find(|parameter| parameter.spec.name == name)
+            .map(|parameter| parameter.value)
+            .ok_or_else(|| GeneratorError::UnknownParameter {
+                node: self.space_info().name.clone(),
+                parameter: name.to_string(),
+            })
+    }
+
+    /// Changes one of the [`Node::parameters`], the value must match the kind of the parameter.
+    fn set_parameter(&mut self, name: &str, value: ParameterValue) -> Result<()> {
+        Err(unsupported_parameter(self, name, &value))
+    }
+
     fn is_output(&self) -> bool {
         false
     }
 
+    /// The channel name of an output node, see [`Generator::generate_all`](crate::generator::Generator::generate_all).
+    fn output_channel(&self) -> Option<&str> {
+        None
+    }
+
+    /// The port a group input exposes on every [`Group`](crate::library::group::Group) that contains it. Inside of a
+    /// group the node outputs the value of that port, see [`Generator::evaluate`](crate::generator::Generator::evaluate).
+    fn group_input(&self) -> Option<Port> {
+        None
+    }
+
+    /// The definition of a group node, used to save the definition once for all instances.
+    fn group(&self) -> Option<&SharedGroup> {
+        None
+    }
+
+    /// An input whose nodes get evaluated at the positions returned by [`Node::warp`] instead of the positions of this
+    /// node, see [`Warp`](crate::library::warp::Warp).
+    fn warped_input(&self) -> Option<&str> {
+        None
+    }
+
+    /// Moves the positions at which the nodes before the [`Node::warped_input`] get evaluated, `inputs` holds every
+    /// other input of the batch.
+    fn warp(
+        &self,
+        positions: &[Coordinate],
+        _size: &(u32, u32),
+        _inputs: &BatchInputs,
+    ) -> Result<Vec<Coordinate>> {
+        Ok(positions.to_vec())
+    }
+
+    /// Problems of the node itself, e.g. a parameter that can't be used, reported by
+    /// [`Generator::validate`](crate::generator::Generator::validate).
+    fn diagnostics(&self) -> Vec<DiagnosticKind> {
+        Vec::new()
+    }
+
     fn render(&self, plane: &mut Plane) -> Result<()> {
         let space_info = self.space_info();
 
@@ -65,6 +296,33 @@ pub trait Node: std::fmt::Debug {
         Ok(())
     }
 
+    /// Draws `preview`, the output of the node (see [`Generator::generate_from`](crate::generator::Generator::generate_from)),
+    /// as a thumbnail below the node. Nothing is drawn if the thumbnail doesn't fit into `plane`.
+    fn render_preview(&self, plane: &mut Plane, preview: &Plane) -> Result<()> {
+        let space_info = self.space_info();
+        let x = space_info.position.0;
+        let y = space_info.position.1 + space_info.size.1 as i64 + PREVIEW_MARGIN as i64;
+
+        if x < 0
+            || y < 0
+            || x + PREVIEW_SIZE as i64 > plane.width() as i64
+            || y + PREVIEW_SIZE as i64 > plane.height() as i64
+        {
+            return Ok(());
+        }
+
+        plane.copy_into(
+            preview,
+            x as u32,
+            y as u32,
+            PREVIEW_SIZE,
+            PREVIEW_SIZE,
+            ResizeMode::NearestNeighbor,
+        )?;
+
+        Ok(())
+    }
+
     fn space_info(&self) -> &SpaceInfo;
     fn space_info_mut(&mut self) -> &mut SpaceInfo;
 }
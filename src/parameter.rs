@@ -0,0 +1,204 @@
+use rusvid_core::pixel::Pixel;
+use serde::{Deserialize, Serialize};
+
+use crate::coordinate::Coordinate;
+use crate::error::{GeneratorError, Result};
+use crate::input_output_value::InputOutputValue;
+use crate::node::Node;
+
+/// The kind of value a parameter of a node holds.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ParameterKind {
+    Float {
+        min: f64,
+        max: f64,
+    },
+    Int {
+        min: i64,
+        max: i64,
+    },
+    Seed,
+    Color,
+    Coordinate,
+    Text,
+    /// One of the listed variants.
+    Enum {
+        variants: Vec<String>,
+    },
+    /// A list of values with their position between `0.0` and `1.0`.
+    Gradient,
+    /// Any [`InputOutputValue`].
+    Value,
+}
+
+/// Describes a setting of a node, e.g. to build an editor for it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ParameterSpec {
+    pub name: String,
+    pub kind: ParameterKind,
+}
+
+impl ParameterSpec {
+    pub fn new<S: Into<String>>(name: S, kind: ParameterKind) -> Self {
+        ParameterSpec {
+            name: name.into(),
+            kind,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParameterValue {
+    Float(f64),
+    Int(i64),
+    Seed(u32),
+    Color(Pixel),
+    Coordinate(Coordinate),
+    Text(String),
+    Enum(String),
+    Gradient(Vec<(InputOutputValue, f64)>),
+    Value(InputOutputValue),
+}
+
+impl ParameterValue {
+    /// Returns `true` if the value can be stored in a parameter of the given kind, ranges are not checked.
+    pub fn is_kind(&self, kind: &ParameterKind) -> bool {
+        matches!(
+            (self, kind),
+            (ParameterValue::Float(_), ParameterKind::Float { .. })
+                | (ParameterValue::Int(_), ParameterKind::Int { .. })
+                | (ParameterValue::Seed(_), ParameterKind::Seed)
+                | (ParameterValue::Color(_), ParameterKind::Color)
+                | (ParameterValue::Coordinate(_), ParameterKind::Coordinate)
+                | (ParameterValue::Text(_), ParameterKind::Text)
+                | (ParameterValue::Enum(_), ParameterKind::Enum { .. })
+                | (ParameterValue::Gradient(_), ParameterKind::Gradient)
+                | (ParameterValue::Value(_), ParameterKind::Value)
+        )
+    }
+
+    /// Converts the value into the format used in saved graphs.
+    pub fn to_json(&self) -> Result<serde_json::Value> {
+        let value = match self {
+            ParameterValue::Float(value) => serde_json::to_value(value)?,
+            ParameterValue::Int(value) => serde_json::to_value(value)?,
+            ParameterValue::Seed(value) => serde_json::to_value(value)?,
+            ParameterValue::Color(value) => serde_json::to_value(value.to_raw())?,
+            ParameterValue::Coordinate(value) => serde_json::to_value(value)?,
+            ParameterValue::Text(value) => serde_json::to_value(value)?,
+            ParameterValue::Enum(value) => serde_json::to_value(value)?,
+            ParameterValue::Gradient(value) => serde_json::to_value(value)?,
+            ParameterValue::Value(value) => serde_json::to_value(value)?,
+        };
+
+        Ok(value)
+    }
+
+    /// Reads a value written by [`ParameterValue::to_json`], the format depends on the `kind` of the parameter.
+    pub fn from_json(kind: &ParameterKind, value: serde_json::Value) -> Result<ParameterValue> {
+        let value = match kind {
+            ParameterKind::Float { .. } => ParameterValue::Float(serde_json::from_value(value)?),
+            ParameterKind::Int { .. } => ParameterValue::Int(serde_json::from_value(value)?),
+            ParameterKind::Seed => ParameterValue::Seed(serde_json::from_value(value)?),
+            ParameterKind::Color => {
+                let [r, g, b, a]: [u8; 4] = serde_json::from_value(value)?;
+                ParameterValue::Color(Pixel::new(r, g, b, a))
+            }
+            ParameterKind::Coordinate => ParameterValue::Coordinate(serde_json::from_value(value)?),
+            ParameterKind::Text => ParameterValue::Text(serde_json::from_value(value)?),
+            ParameterKind::Enum { .. } => ParameterValue::Enum(serde_json::from_value(value)?),
+            ParameterKind::Gradient => ParameterValue::Gradient(serde_json::from_value(value)?),
+            ParameterKind::Value => ParameterValue::Value(serde_json::from_value(value)?),
+        };
+
+        Ok(value)
+    }
+}
+
+/// A setting of a node together with its current value, see [`Node::parameters`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parameter {
+    pub spec: ParameterSpec,
+    pub value: ParameterValue,
+}
+
+impl Parameter {
+    pub fn new<S: Into<String>>(name: S, kind: ParameterKind, value: ParameterValue) -> Self {
+        Parameter {
+            spec: ParameterSpec::new(name, kind),
+            value,
+        }
+    }
+}
+
+/// Builds the error for a value that `node` doesn't accept in [`Node::set_parameter`].
+pub(crate) fn unsupported_parameter<N: Node + ?Sized>(
+    node: &N,
+    name: &str,
+    value: &ParameterValue,
+) -> GeneratorError {
+    match node
+        .parameters()
+        .into_iter()
+        .find(|parameter| parameter.spec.name == name)
+    {
+        Some(parameter) => GeneratorError::InvalidParameter {
+            parameter: name.to_string(),
+            reason: format!("expected {:?} but got {:?}", parameter.spec.kind, value),
+        },
+        None => GeneratorError::UnknownParameter {
+            node: node.space_info().name.clone(),
+            parameter: name.to_string(),
+        },
+    }
+}
+
+/// Checks that `value` is in the range of a [`ParameterKind::Float`].
+pub(crate) fn check_range(name: &str, value: f64, min: f64, max: f64) -> Result<f64> {
+    if value < min || value > max || value.is_nan() {
+        return Err(GeneratorError::InvalidParameter {
+            parameter: name.to_string(),
+            reason: format!("expected a value between {min} and {max} but got {value}"),
+        });
+    }
+
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use rusvid_core::pixel::Pixel;
+
+    use super::{ParameterKind, ParameterValue};
+    use crate::coordinate::Coordinate;
+
+    #[test]
+    fn values_survive_a_json_round_trip() {
+        let values = [
+            (
+                ParameterKind::Float { min: 0.0, max: 1.0 },
+                ParameterValue::Float(0.25),
+            ),
+            (
+                ParameterKind::Int { min: 0, max: 10 },
+                ParameterValue::Int(3),
+            ),
+            (ParameterKind::Seed, ParameterValue::Seed(42)),
+            (
+                ParameterKind::Color,
+                ParameterValue::Color(Pixel::new(1, 2, 3, 4)),
+            ),
+            (
+                ParameterKind::Coordinate,
+                ParameterValue::Coordinate(Coordinate::new(1.0, 2.0, 3.0)),
+            ),
+        ];
+
+        for (kind, value) in values {
+            assert!(value.is_kind(&kind));
+
+            let json = value.to_json().unwrap();
+            assert_eq!(ParameterValue::from_json(&kind, json).unwrap(), value);
+        }
+    }
+}
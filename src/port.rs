@@ -0,0 +1,88 @@
+use crate::input_output_value::{InputOutputKind, InputOutputValue};
+
+/// Name of the input of nodes that only have a single input.
+pub const DEFAULT_INPUT: &str = "input";
+/// Name of the output every node has unless it declares its own outputs.
+pub const DEFAULT_OUTPUT: &str = "output";
+
+/// A named socket of a node through which values flow in or out of the node.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Port {
+    pub name: String,
+    /// The kinds of values the port can handle, an empty list means that every kind is allowed.
+    pub kinds: Vec<InputOutputKind>,
+    /// Value that is used for an optional input if no edge is connected to it.
+    pub default: Option<InputOutputValue>,
+    pub required: bool,
+}
+
+impl Port {
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        Port {
+            name: name.into(),
+            kinds: Vec::new(),
+            default: None,
+            required: true,
+        }
+    }
+
+    pub fn with_kinds(mut self, kinds: &[InputOutputKind]) -> Self {
+        self.kinds = kinds.to_vec();
+
+        self
+    }
+
+    pub fn optional(mut self, default: InputOutputValue) -> Self {
+        self.default = Some(default);
+        self.required = false;
+
+        self
+    }
+
+    pub fn accepts(&self, kind: InputOutputKind) -> bool {
+        self.kinds.is_empty() || self.kinds.contains(&kind)
+    }
+}
+
+/// The sockets an edge connects: an output of the source node and an input of the target node.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Sockets {
+    pub output: String,
+    pub input: String,
+}
+
+impl Sockets {
+    pub fn new<O: Into<String>, I: Into<String>>(output: O, input: I) -> Self {
+        Sockets {
+            output: output.into(),
+            input: input.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Port;
+    use crate::input_output_value::{InputOutputKind, InputOutputValue};
+
+    #[test]
+    fn a_port_without_kinds_accepts_everything() {
+        let port = Port::new("value");
+
+        assert!(port.accepts(InputOutputKind::Nothing));
+        assert!(port.accepts(InputOutputKind::Float));
+        assert!(port.accepts(InputOutputKind::Pixel));
+    }
+
+    #[test]
+    fn optional_ports_have_a_default() {
+        let port = Port::new("value")
+            .with_kinds(&[InputOutputKind::Float])
+            .optional(InputOutputValue::Float(0.5));
+
+        assert!(!port.required);
+        assert_eq!(port.default, Some(InputOutputValue::Float(0.5)));
+        assert!(port.accepts(InputOutputKind::Float));
+        assert!(!port.accepts(InputOutputKind::Pixel));
+    }
+}
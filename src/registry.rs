@@ -0,0 +1,314 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+
+use rusvid_core::pixel::Pixel;
+
+use crate::error::{GeneratorError, Result};
+use crate::input_output_value::InputOutputValue;
+use crate::library::expression::Expression;
+use crate::library::fractal::{Fractal, FractalKind};
+use crate::library::group::GroupInput;
+use crate::library::map::Map;
+use crate::library::math::{Math, MathOperation};
+use crate::library::mix::Mix;
+use crate::library::noise::{Noise, NoiseAlgorithm};
+use crate::library::normalize::Normalize;
+use crate::library::output::Output;
+use crate::library::pattern::Pattern;
+use crate::library::split::Split;
+use crate::library::static_value::StaticValue;
+use crate::library::transform::Transform;
+use crate::library::voronoi::Voronoi;
+use crate::library::warp::Warp;
+use crate::node::SharedNode;
+use crate::parameter::{ParameterSpec, ParameterValue};
+
+/// Creates a node with its default parameters.
+pub type NodeFactory = Box<dyn Fn() -> Result<SharedNode> + Send + Sync>;
+
+struct NodeRegistration {
+    name: String,
+    factory: NodeFactory,
+}
+
+/// Maps the [`Node::node_type`](crate::node::Node::node_type) of every known kind of node to a factory that creates it.
+///
+/// [`NodeRegistry::default`] knows every node of the [`library`](crate::library), other crates can add their own nodes
+/// with [`NodeRegistry::register`].
+pub struct NodeRegistry {
+    nodes: BTreeMap<String, NodeRegistration>,
+}
+
+impl std::fmt::Debug for NodeRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.nodes.keys()).finish()
+    }
+}
+
+impl Default for NodeRegistry {
+    fn default() -> Self {
+        let mut registry = NodeRegistry::new();
+
+        registry.register("expression", "Expression", || {
+            Ok(Arc::new(RwLock::new(Expression::new("u"))))
+        });
+        registry.register("group.input", "Group Input", || {
+            Ok(Arc::new(RwLock::new(GroupInput::default())))
+        });
+        registry.register("map", "Map", || {
+            let steps = vec![
+                (InputOutputValue::Pixel(Pixel::new(0, 0, 0, 255)), 0.0),
+                (InputOutputValue::Pixel(Pixel::new(255, 255, 255, 255)), 1.0),
+            ];
+
+            Ok(Arc::new(RwLock::new(Map::new(steps)?)))
+        });
+        for operation in MathOperation::ALL {
+            registry.register(operation.node_type(), operation.label(), move || {
+                Ok(Arc::new(RwLock::new(Math::new(operation))))
+            });
+        }
+        registry.register("mix", "Mix", || Ok(Arc::new(RwLock::new(Mix::new()))));
+        for algorithm in NoiseAlgorithm::ALL {
+            registry.register(
+                algorithm.node_type(),
+                format!("{} Noise", algorithm.label()),
+                move || Ok(Arc::new(RwLock::new(Noise::with_algorithm(algorithm, 0)))),
+            );
+        }
+        registry.register("noise.fractal", "Fractal Noise", || {
+            Ok(Arc::new(RwLock::new(Fractal::new(FractalKind::Fbm, 0))))
+        });
+        registry.register("normalize", "Normalize", || {
+            Ok(Arc::new(RwLock::new(Normalize::new(1.0))))
+        });
+        registry.register("output", "Output", || {
+            Ok(Arc::new(RwLock::new(Output::new())))
+        });
+        registry.register("pattern", "Pattern", || {
+            Ok(Arc::new(RwLock::new(Pattern::new())))
+        });
+        registry.register("split", "Split", || Ok(Arc::new(RwLock::new(Split::new()))));
+        registry.register("static_value", "Static Value", || {
+            Ok(Arc::new(RwLock::new(StaticValue::new(
+                InputOutputValue::Float(0.0),
+            ))))
+        });
+
+        registry.register("transform", "Transform", || {
+            Ok(Arc::new(RwLock::new(Transform::new())))
+        });
+        registry.register("voronoi", "Voronoi", || {
+            Ok(Arc::new(RwLock::new(Voronoi::new(0))))
+        });
+        registry.register("warp", "Warp", || {
+            Ok(Arc::new(RwLock::new(Warp::new(0.1)?)))
+        });
+
+        registry
+    }
+}
+
+impl NodeRegistry {
+    /// Creates a registry without any nodes, use [`NodeRegistry::default`] for a registry with the library nodes.
+    pub fn new() -> Self {
+        NodeRegistry {
+            nodes: BTreeMap::new(),
+        }
+    }
+
+    /// Adds a kind of node, an already registered node with the same `node_type` gets replaced.
+    ///
+    /// `node_type` must be the same as [`Node::node_type`](crate::node::Node::node_type) of the created nodes.
+    pub fn register<T, S, F>(&mut self, node_type: T, name: S, factory: F)
+    where
+        T: Into<String>,
+        S: Into<String>,
+        F: Fn() -> Result<SharedNode> + Send + Sync + 'static,
+    {
+        self.nodes.insert(
+            node_type.into(),
+            NodeRegistration {
+                name: name.into(),
+                factory: Box::new(factory),
+            },
+        );
+    }
+
+    pub fn contains(&self, node_type: &str) -> bool {
+        self.nodes.contains_key(node_type)
+    }
+
+    /// Returns the registered node types in alphabetical order.
+    pub fn node_types(&self) -> impl Iterator<Item = &str> {
+        self.nodes.keys().map(|node_type| node_type.as_str())
+    }
+
+    /// Returns the human readable name of the node type.
+    pub fn name(&self, node_type: &str) -> Option<&str> {
+        self.nodes
+            .get(node_type)
+            .map(|registration| registration.name.as_str())
+    }
+
+    /// Returns the parameters of the node type, taken from a node with the default parameters.
+    pub fn parameters(&self, node_type: &str) -> Result<Vec<ParameterSpec>> {
+        let node = self.create(node_type)?;
+        let parameters = node.read().unwrap().parameters();
+
+        Ok(parameters
+            .into_iter()
+            .map(|parameter| parameter.spec)
+            .collect())
+    }
+
+    /// Creates a node with the default parameters.
+    pub fn create(&self, node_type: &str) -> Result<SharedNode> {
+        let registration = self
+            .nodes
+            .get(node_type)
+            .ok_or_else(|| GeneratorError::UnknownNodeType(node_type.to_string()))?;
+
+        (registration.factory)()
+    }
+
+    /// Creates a node and applies parameters in the format of [`ParameterValue::to_json`], keyed by their name.
+    ///
+    /// Parameters that are missing keep their default value.
+    pub fn create_with_parameters(
+        &self,
+        node_type: &str,
+        parameters: serde_json::Value,
+    ) -> Result<SharedNode> {
+        let node = self.create(node_type)?;
+
+        let parameters = match parameters {
+            serde_json::Value::Null => return Ok(node),
+            serde_json::Value::Object(parameters) => parameters,
+            _ => {
+                return Err(GeneratorError::InvalidParameter {
+                    parameter: node_type.to_string(),
+                    reason: "expected an object with the parameters".to_string(),
+                })
+            }
+        };
+
+        {
+            let mut node = node.write().unwrap();
+            let specs = node.parameters();
+
+            for (name, value) in parameters {
+                let spec = specs
+                    .iter()
+                    .find(|parameter| parameter.spec.name == name)
+                    .ok_or_else(|| GeneratorError::UnknownParameter {
+                        node: node.space_info().name.clone(),
+                        parameter: name.clone(),
+                    })?;
+
+                let value = ParameterValue::from_json(&spec.spec.kind, value)?;
+                node.set_parameter(&name, value)?;
+            }
+        }
+
+        Ok(node)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::{Arc, RwLock};
+
+    use super::NodeRegistry;
+    use crate::coordinate::Coordinate;
+    use crate::error::{GeneratorError, Result};
+    use crate::input_output_value::InputOutputValue;
+    use crate::node::{Node, SpaceInfo};
+    use crate::parameter::ParameterValue;
+
+    #[derive(Debug)]
+    struct Constant {
+        space_info: SpaceInfo,
+    }
+
+    impl Node for Constant {
+        fn node_type(&self) -> &'static str {
+            "test.constant"
+        }
+
+        fn generate(
+            &self,
+            _position: &Coordinate,
+            _size: &(u32, u32),
+            _input: HashMap<String, InputOutputValue>,
+        ) -> Result<InputOutputValue> {
+            Ok(InputOutputValue::Float(1.0))
+        }
+
+        fn space_info(&self) -> &SpaceInfo {
+            &self.space_info
+        }
+
+        fn space_info_mut(&mut self) -> &mut SpaceInfo {
+            &mut self.space_info
+        }
+    }
+
+    #[test]
+    fn creates_every_library_node_with_default_parameters() {
+        let registry = NodeRegistry::default();
+
+        for node_type in registry.node_types() {
+            let node = registry.create(node_type).unwrap();
+
+            assert_eq!(node.read().unwrap().node_type(), node_type);
+        }
+    }
+
+    #[test]
+    fn nodes_can_be_registered() {
+        let mut registry = NodeRegistry::new();
+        assert!(matches!(
+            registry.create("test.constant"),
+            Err(GeneratorError::UnknownNodeType(_))
+        ));
+
+        registry.register("test.constant", "Constant", || {
+            Ok(Arc::new(RwLock::new(Constant {
+                space_info: SpaceInfo::default(),
+            })))
+        });
+
+        assert!(registry.contains("test.constant"));
+        assert_eq!(registry.name("test.constant"), Some("Constant"));
+        assert_eq!(
+            registry
+                .create("test.constant")
+                .unwrap()
+                .read()
+                .unwrap()
+                .node_type(),
+            "test.constant"
+        );
+    }
+
+    #[test]
+    fn parameters_can_be_changed_by_name() {
+        let registry = NodeRegistry::default();
+        let node = registry.create("noise.perlin").unwrap();
+        let mut node = node.write().unwrap();
+
+        node.set_parameter("seed", ParameterValue::Seed(7)).unwrap();
+        assert_eq!(node.parameter("seed").unwrap(), ParameterValue::Seed(7));
+
+        assert!(matches!(
+            node.set_parameter("seed", ParameterValue::Float(1.0)),
+            Err(GeneratorError::InvalidParameter { .. })
+        ));
+        assert!(matches!(
+            node.set_parameter("octaves", ParameterValue::Int(2)),
+            Err(GeneratorError::UnknownParameter { .. })
+        ));
+    }
+}
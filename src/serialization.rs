@@ -0,0 +1,499 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{GeneratorError, Result};
+use crate::generator::Generator;
+use crate::group_definition::is_recursive;
+use crate::library::group::{self, Group, GroupDefinition, SharedGroup};
+use crate::link::Link;
+use crate::node::{Node, SharedNode, SpaceInfo};
+use crate::registry::NodeRegistry;
+
+/// Version of the format written by [`Generator::to_json`] and [`Generator::to_ron`].
+///
+/// Must be increased whenever the format changes, older versions must still be loadable.
+pub const SCHEMA_VERSION: u32 = 4;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SavedGraph {
+    version: u32,
+    output_node: usize,
+    nodes: Vec<SavedNode>,
+    edges: Vec<SavedEdge>,
+    /// The definitions of every group used in the graph or in other groups, saved since version 3. A group is listed
+    /// after the groups it uses.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    groups: Vec<SavedGroup>,
+    /// See [`Generator::set_tileable`], saved since version 4.
+    #[serde(default)]
+    tileable: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SavedGroup {
+    name: String,
+    graph: SavedGraph,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SavedNode {
+    id: usize,
+    #[serde(rename = "type")]
+    node_type: String,
+    #[serde(default)]
+    parameters: serde_json::Value,
+    space_info: SpaceInfo,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SavedEdge {
+    from: usize,
+    to: usize,
+    /// The input of the target node.
+    port: String,
+    /// The output of the source node, saved since version 2. Older graphs connect the first output.
+    #[serde(default)]
+    output: Option<String>,
+}
+
+fn save_parameters(node: &dyn Node) -> Result<serde_json::Value> {
+    let parameters = node.parameters();
+    if parameters.is_empty() {
+        return Ok(serde_json::Value::Null);
+    }
+
+    parameters
+        .into_iter()
+        .map(|parameter| Ok((parameter.spec.name, parameter.value.to_json()?)))
+        .collect::<Result<serde_json::Map<_, _>>>()
+        .map(serde_json::Value::Object)
+}
+
+/// Adds the definition of `group` to `groups` unless it is already there, every definition is saved once no matter how
+/// many instances use it.
+///
+/// A group that contains an instance of itself can't be saved, its definition would have to be saved before itself.
+fn save_group(group: &SharedGroup, groups: &mut Vec<(SharedGroup, SavedGroup)>) -> Result<()> {
+    if groups.iter().any(|(saved, _)| Arc::ptr_eq(saved, group)) {
+        return Ok(());
+    }
+    if is_recursive(group) {
+        return Err(GeneratorError::RecursiveGroup(
+            group.read().unwrap().name.clone(),
+        ));
+    }
+
+    let definition = group.read().unwrap();
+    if groups
+        .iter()
+        .any(|(_, saved)| saved.name == definition.name)
+    {
+        return Err(GeneratorError::DuplicateGroup(definition.name.clone()));
+    }
+
+    let graph = definition.generator.save_graph(groups)?;
+    groups.push((
+        group.clone(),
+        SavedGroup {
+            name: definition.name.clone(),
+            graph,
+        },
+    ));
+
+    Ok(())
+}
+
+fn load_group(
+    parameters: &serde_json::Value,
+    groups: &HashMap<String, SharedGroup>,
+) -> Result<SharedNode> {
+    let name = parameters
+        .get("group")
+        .and_then(serde_json::Value::as_str)
+        .unwrap_or_default();
+    let definition = groups
+        .get(name)
+        .ok_or_else(|| GeneratorError::UnknownGroup(name.to_string()))?;
+
+    Ok(Arc::new(RwLock::new(Group::new(definition.clone()))))
+}
+
+impl Generator {
+    fn save(&self) -> Result<SavedGraph> {
+        let mut groups = Vec::new();
+        let mut saved = self.save_graph(&mut groups)?;
+        saved.groups = groups.into_iter().map(|(_, group)| group).collect();
+
+        Ok(saved)
+    }
+
+    /// Saves the graph without the definitions of its groups, these are collected in `groups`.
+    fn save_graph(&self, groups: &mut Vec<(SharedGroup, SavedGroup)>) -> Result<SavedGraph> {
+        let nodes = self
+            .internal_graph
+            .node_indices()
+            .map(|index| {
+                let node = self.internal_graph[index].read().unwrap();
+                if let Some(group) = node.group() {
+                    save_group(group, groups)?;
+                }
+
+                Ok(SavedNode {
+                    id: index.index(),
+                    node_type: node.node_type().to_string(),
+                    parameters: save_parameters(&*node)?,
+                    space_info: node.space_info().clone(),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let edges = self
+            .internal_graph
+            .edge_indices()
+            .filter_map(|edge| {
+                let (from, to) = self.internal_graph.edge_endpoints(edge)?;
+
+                Some(SavedEdge {
+                    from: from.index(),
+                    to: to.index(),
+                    port: self.internal_graph[edge].input.clone(),
+                    output: Some(self.internal_graph[edge].output.clone()),
+                })
+            })
+            .collect();
+
+        Ok(SavedGraph {
+            version: SCHEMA_VERSION,
+            output_node: self.output_node().index(),
+            nodes,
+            edges,
+            groups: Vec::new(),
+            tileable: self.tileable(),
+        })
+    }
+
+    fn load(mut saved: SavedGraph, registry: &NodeRegistry) -> Result<Generator> {
+        if saved.version > SCHEMA_VERSION {
+            return Err(GeneratorError::UnsupportedVersion {
+                found: saved.version,
+                supported: SCHEMA_VERSION,
+            });
+        }
+
+        let mut groups = HashMap::new();
+        for group in std::mem::take(&mut saved.groups) {
+            if groups.contains_key(&group.name) {
+                return Err(GeneratorError::DuplicateGroup(group.name));
+            }
+
+            let generator = Generator::load_graph(group.graph, registry, &groups)?;
+            groups.insert(
+                group.name.clone(),
+                GroupDefinition::new(group.name, generator).shared(),
+            );
+        }
+
+        Generator::load_graph(saved, registry, &groups)
+    }
+
+    fn load_graph(
+        saved: SavedGraph,
+        registry: &NodeRegistry,
+        groups: &HashMap<String, SharedGroup>,
+    ) -> Result<Generator> {
+        let mut generator = Generator::new();
+        let mut indices = HashMap::new();
+
+        for node in saved.nodes {
+            let loaded = if node.node_type == group::NODE_TYPE {
+                load_group(&node.parameters, groups)?
+            } else {
+                registry.create_with_parameters(&node.node_type, node.parameters)?
+            };
+            let index = if node.id == saved.output_node {
+                if !loaded.read().unwrap().is_output() {
+                    return Err(GeneratorError::OutputNodeRequired);
+                }
+
+                let index = generator.output_node();
+                generator.internal_graph[index] = loaded;
+
+                index
+            } else {
+                generator.add_shared_node(loaded)
+            };
+
+            *generator.internal_graph[index]
+                .write()
+                .unwrap()
+                .space_info_mut() = node.space_info;
+            indices.insert(node.id, index);
+        }
+
+        let index_of = |id: usize| {
+            indices.get(&id).copied().ok_or(GeneratorError::UnknownNode(
+                petgraph::stable_graph::NodeIndex::new(id),
+            ))
+        };
+        for edge in saved.edges {
+            let link = Link::new(index_of(edge.from)?, index_of(edge.to)?)?;
+            match edge.output {
+                Some(output) => generator.add_edge_sockets(link, output, edge.port)?,
+                None => generator.add_edge_named(link, edge.port)?,
+            };
+        }
+        generator.set_tileable(saved.tileable);
+
+        Ok(generator)
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(&self.save()?)?)
+    }
+
+    pub fn from_json(input: &str) -> Result<Generator> {
+        Generator::from_json_with_registry(input, &NodeRegistry::default())
+    }
+
+    /// Like [`Generator::from_json`], but also knows the nodes added to `registry`.
+    pub fn from_json_with_registry(input: &str, registry: &NodeRegistry) -> Result<Generator> {
+        Generator::load(serde_json::from_str(input)?, registry)
+    }
+
+    pub fn to_ron(&self) -> Result<String> {
+        Ok(ron::ser::to_string_pretty(
+            &self.save()?,
+            ron::ser::PrettyConfig::default(),
+        )?)
+    }
+
+    pub fn from_ron(input: &str) -> Result<Generator> {
+        Generator::from_ron_with_registry(input, &NodeRegistry::default())
+    }
+
+    /// Like [`Generator::from_ron`], but also knows the nodes added to `registry`.
+    pub fn from_ron_with_registry(input: &str, registry: &NodeRegistry) -> Result<Generator> {
+        Generator::load(ron::from_str(input)?, registry)
+    }
+}
+
+/// (De-)serializes a [`Pixel`](rusvid_core::pixel::Pixel) as `[r, g, b, a]`, use with `#[serde(with = "crate::serialization::pixel")]`.
+pub(crate) mod pixel {
+    use rusvid_core::pixel::Pixel;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(pixel: &Pixel, serializer: S) -> Result<S::Ok, S::Error> {
+        pixel.to_raw().serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Pixel, D::Error> {
+        Ok(Pixel::new_raw(<[u8; 4]>::deserialize(deserializer)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rusvid_core::pixel::Pixel;
+
+    use super::SCHEMA_VERSION;
+    use crate::coordinate::Coordinate;
+    use crate::error::GeneratorError;
+    use crate::generator::Generator;
+    use crate::input_output_value::InputOutputValue;
+    use crate::library::group::tests::example_group;
+    use crate::library::group::Group;
+    use crate::library::map::Map;
+    use crate::library::mix::Mix;
+    use crate::library::noise::Noise;
+    use crate::library::static_value::StaticValue;
+    use crate::link::Link;
+    use crate::node::Node;
+
+    fn example_generator() -> Generator {
+        let mut g = Generator::new();
+
+        let node_mix = g.add_node(Mix::new());
+        let node_noise = g.add_node({
+            let mut n = Noise::new(7);
+            n.set_offset(Coordinate::new(0.25, 0.5, 0.0));
+            n.set_scale(Coordinate::new_xy(10.0, 5.0));
+            n.space_info_mut().position = (20, -40);
+            n
+        });
+        let node_map = g.add_node(
+            Map::new(vec![
+                (InputOutputValue::Pixel(Pixel::new(0, 0, 0, 255)), 0.0),
+                (InputOutputValue::Pixel(Pixel::new(50, 100, 0, 255)), 0.3),
+                (InputOutputValue::Pixel(Pixel::new(255, 255, 255, 255)), 1.0),
+            ])
+            .unwrap(),
+        );
+        let node_color = g.add_node(StaticValue::new(InputOutputValue::Pixel(Pixel::new(
+            255, 0, 100, 255,
+        ))));
+        let node_output = g.output_node();
+
+        g.add_edge_named(Link::new(node_noise, node_mix).unwrap(), "value")
+            .unwrap();
+        g.add_edge(Link::new(node_noise, node_map).unwrap())
+            .unwrap();
+        g.add_edge_named(Link::new(node_map, node_mix).unwrap(), "input1")
+            .unwrap();
+        g.add_edge_named(Link::new(node_color, node_mix).unwrap(), "input2")
+            .unwrap();
+        g.add_edge(Link::new(node_mix, node_output).unwrap())
+            .unwrap();
+
+        g
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let g = example_generator();
+
+        let json = g.to_json().unwrap();
+        let loaded = Generator::from_json(&json).unwrap();
+
+        assert_eq!(loaded.to_json().unwrap(), json);
+        assert_eq!(
+            loaded.generate(20, 20).unwrap().as_data_flatten(),
+            g.generate(20, 20).unwrap().as_data_flatten()
+        );
+    }
+
+    #[test]
+    fn tileable_round_trip() {
+        let mut g = example_generator();
+        g.set_tileable(true);
+
+        let json = g.to_json().unwrap();
+        let loaded = Generator::from_json(&json).unwrap();
+
+        assert!(loaded.tileable());
+        assert_eq!(loaded.to_json().unwrap(), json);
+        assert_eq!(
+            loaded.generate(20, 20).unwrap().as_data_flatten(),
+            g.generate(20, 20).unwrap().as_data_flatten()
+        );
+    }
+
+    #[test]
+    fn ron_round_trip() {
+        let g = example_generator();
+
+        let ron = g.to_ron().unwrap();
+        let loaded = Generator::from_ron(&ron).unwrap();
+
+        assert_eq!(loaded.to_ron().unwrap(), ron);
+        assert_eq!(
+            loaded.generate(20, 20).unwrap().as_data_flatten(),
+            g.generate(20, 20).unwrap().as_data_flatten()
+        );
+    }
+
+    #[test]
+    fn every_output_is_saved() {
+        let mut g = example_generator();
+        let roughness = g.add_output("roughness").unwrap();
+        let source = g.edges_of(g.output_node())[0].1.input_node();
+        g.add_edge(Link::new(source, roughness).unwrap()).unwrap();
+
+        let loaded = Generator::from_json(&g.to_json().unwrap()).unwrap();
+
+        assert_eq!(loaded.outputs().len(), 2);
+        assert!(loaded.output("roughness").is_some());
+        assert_eq!(
+            loaded.generate_all(8, 8).unwrap()["roughness"].as_data_flatten(),
+            g.generate_all(8, 8).unwrap()["roughness"].as_data_flatten()
+        );
+    }
+
+    #[test]
+    fn groups_are_saved_once() {
+        let group = example_group();
+
+        let mut g = Generator::new();
+        let first = g.add_node(Group::new(group.clone()));
+        let second = g.add_node(Group::new(group));
+        g.add_edge(Link::new(first, second).unwrap()).unwrap();
+        g.add_edge(Link::new(second, g.output_node()).unwrap())
+            .unwrap();
+
+        let json = g.to_json().unwrap();
+        assert_eq!(json.matches("\"graph\"").count(), 1);
+
+        let loaded = Generator::from_json(&json).unwrap();
+        assert_eq!(loaded.to_json().unwrap(), json);
+        assert_eq!(
+            loaded.generate(4, 4).unwrap().as_data_flatten(),
+            g.generate(4, 4).unwrap().as_data_flatten()
+        );
+    }
+
+    #[test]
+    fn group_names_must_be_unique() {
+        let mut g = Generator::new();
+        let group = g.add_node(Group::new(example_group()));
+        g.add_edge(Link::new(group, g.output_node()).unwrap())
+            .unwrap();
+
+        let mut saved: serde_json::Value = serde_json::from_str(&g.to_json().unwrap()).unwrap();
+        let groups = saved["groups"].as_array_mut().unwrap();
+        groups.push(groups[0].clone());
+
+        assert!(matches!(
+            Generator::from_json(&saved.to_string()),
+            Err(GeneratorError::DuplicateGroup(name)) if name == "passthrough"
+        ));
+    }
+
+    #[test]
+    fn a_group_inside_of_itself_is_not_saved() {
+        let group = example_group();
+        let instance = Group::new(group.clone());
+        group.write().unwrap().generator.add_node(instance);
+
+        let mut g = Generator::new();
+        g.add_node(Group::new(group));
+
+        assert!(matches!(
+            g.to_json(),
+            Err(GeneratorError::RecursiveGroup(name)) if name == "passthrough"
+        ));
+        assert!(matches!(
+            g.to_ron(),
+            Err(GeneratorError::RecursiveGroup(name)) if name == "passthrough"
+        ));
+    }
+
+    #[test]
+    fn version_1_edges_connect_the_first_output() {
+        let g = example_generator();
+
+        let mut saved: serde_json::Value = serde_json::from_str(&g.to_json().unwrap()).unwrap();
+        saved["version"] = 1.into();
+        for edge in saved["edges"].as_array_mut().unwrap() {
+            edge.as_object_mut().unwrap().remove("output");
+        }
+
+        let loaded = Generator::from_json(&saved.to_string()).unwrap();
+
+        assert_eq!(loaded.to_json().unwrap(), g.to_json().unwrap());
+    }
+
+    #[test]
+    fn newer_versions_are_rejected() {
+        let json = example_generator().to_json().unwrap().replacen(
+            &format!("\"version\": {}", SCHEMA_VERSION),
+            &format!("\"version\": {}", SCHEMA_VERSION + 1),
+            1,
+        );
+
+        assert!(matches!(
+            Generator::from_json(&json),
+            Err(GeneratorError::UnsupportedVersion { .. })
+        ));
+    }
+}
@@ -0,0 +1,157 @@
+use rusvid_core::pixel::Pixel;
+use rusvid_core::plane::Plane;
+
+/// How well an image tiles, see [`seam_error`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SeamError {
+    /// Mean difference between the last and the first column and between the last and the first row, from `0.0` to
+    /// `1.0`.
+    pub seam: f64,
+    /// Mean difference between neighbouring pixels inside of the image, from `0.0` to `1.0`.
+    pub interior: f64,
+}
+
+impl SeamError {
+    /// How much stronger the seams are than the usual change from one pixel to the next, around `1.0` for images that
+    /// tile seamlessly.
+    pub fn ratio(&self) -> f64 {
+        if self.interior > 0.0 {
+            self.seam / self.interior
+        } else if self.seam > 0.0 {
+            f64::INFINITY
+        } else {
+            1.0
+        }
+    }
+}
+
+/// Measures the seams that show up when the plane gets tiled, e.g. the output of a
+/// [tileable generator](crate::generator::Generator::set_tileable).
+pub fn seam_error(plane: &Plane) -> SeamError {
+    let (width, height) = (plane.width(), plane.height());
+    let pixel = |x: u32, y: u32| plane.pixel_unchecked(x, y);
+
+    let mut seam = Vec::new();
+    for y in 0..height {
+        seam.push(difference(pixel(width - 1, y), pixel(0, y)));
+    }
+    for x in 0..width {
+        seam.push(difference(pixel(x, height - 1), pixel(x, 0)));
+    }
+
+    let mut interior = Vec::new();
+    for y in 0..height {
+        for x in 0..width {
+            if x + 1 < width {
+                interior.push(difference(pixel(x, y), pixel(x + 1, y)));
+            }
+            if y + 1 < height {
+                interior.push(difference(pixel(x, y), pixel(x, y + 1)));
+            }
+        }
+    }
+
+    SeamError {
+        seam: mean(&seam),
+        interior: mean(&interior),
+    }
+}
+
+/// Mean difference of the channels, from `0.0` to `1.0`.
+fn difference(a: &Pixel, b: &Pixel) -> f64 {
+    let total = a
+        .to_raw()
+        .iter()
+        .zip(b.to_raw())
+        .map(|(a, b)| a.abs_diff(b) as f64)
+        .sum::<f64>();
+
+    total / (4.0 * 255.0)
+}
+
+fn mean(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use rusvid_core::pixel::Pixel;
+    use rusvid_core::plane::Plane;
+
+    use super::{seam_error, SeamError};
+    use crate::coordinate::Coordinate;
+    use crate::generator::Generator;
+    use crate::library::fractal::{Fractal, FractalKind};
+    use crate::library::noise::{Noise, NoiseAlgorithm};
+    use crate::library::voronoi::Voronoi;
+    use crate::link::Link;
+    use crate::node::Node;
+
+    #[test]
+    fn seams_are_compared_with_the_interior() {
+        // A diagonal ramp changes by one step from pixel to pixel but jumps back by four steps across the seams.
+        let mut plane = Plane::new(5, 5).unwrap();
+        for y in 0..5 {
+            for x in 0..5 {
+                let value = ((x + y) * 25) as u8;
+                plane
+                    .put_pixel(x, y, Pixel::new(value, value, value, 255))
+                    .unwrap();
+            }
+        }
+
+        let error = seam_error(&plane);
+        let step = 25.0 * 3.0 / (4.0 * 255.0);
+        assert!((error.interior - step).abs() < 1e-9);
+        assert!((error.seam - 4.0 * step).abs() < 1e-9);
+        assert!((error.ratio() - 4.0).abs() < 1e-9);
+    }
+
+    /// The seam errors of the node without and with tiling.
+    fn seams<N: Node + 'static>(node: impl Fn() -> N) -> (SeamError, SeamError) {
+        let render = |tileable| {
+            let mut g = Generator::new();
+            g.set_tileable(tileable);
+            let node = g.add_node(node());
+            g.add_edge(Link::new(node, g.output_node()).unwrap())
+                .unwrap();
+
+            seam_error(&g.generate(48, 48).unwrap())
+        };
+
+        (render(false), render(true))
+    }
+
+    #[test]
+    fn tileable_nodes_wrap_around_the_borders() {
+        let scale = Coordinate::new_xy(4.0, 4.0);
+        let errors = [
+            seams(|| {
+                let mut noise = Noise::new(2);
+                noise.set_scale(scale);
+                noise
+            }),
+            seams(|| {
+                let mut noise = Noise::with_algorithm(NoiseAlgorithm::SuperSimplex, 2);
+                noise.set_scale(scale);
+                noise
+            }),
+            seams(|| {
+                let mut fractal = Fractal::new(FractalKind::Fbm, 2);
+                fractal.set_scale(scale);
+                fractal
+            }),
+            seams(|| Voronoi::new(2)),
+        ];
+
+        // The seams are measured along a single row or column, they vary more than the interior.
+        for (plain, tiled) in errors {
+            assert!(tiled.ratio() < 2.0, "{tiled:?}");
+            assert!(plain.ratio() > tiled.ratio(), "{plain:?}");
+        }
+    }
+}
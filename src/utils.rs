@@ -1,8 +1,9 @@
-use anyhow::Result;
 use itertools::Itertools;
 use rusvid_core::pixel::Pixel;
 use rusvid_core::plane::Plane;
 
+use crate::error::Result;
+
 #[allow(unused)]
 pub fn render_square(
     plane: &mut Plane,